@@ -1,6 +1,6 @@
 use std::{f32::consts::PI, rc::Rc, time::Duration};
 use crate::{map, pong::{PongPhysics, PongState}, render};
-use render::{entity::Entity, gpu::{self, wgpu}, model::ModelData, scene::SceneObjectHandle, vertex_type::ColorVertex, RenderManager};
+use render::{entity::Entity, gpu::{self, wgpu}, model::ModelData, scene::SceneObjectHandle, vertex_type::ColorVertex, RenderManager, SafehouseResult};
 use safehouse_render::{entity::EntityPipeline, named_entity};
 
 #[derive(Debug,Default)]
@@ -19,6 +19,7 @@ impl Entity for Ball {
 
     fn on_instantiate(rm: &mut RenderManager, handle: SceneObjectHandle) -> Self {
         let mut b = Self::default();
+        b.scene_handle = handle;
         b.speed = 1.4; // 0.5 units/second
         // Initial distribution of speed stat
         b.vx = -0.9*b.speed;
@@ -26,13 +27,12 @@ impl Entity for Ball {
         let (px, py) = b.get_pos(rm);
         b.px = px;
         b.py = py;
-        b.scene_handle = handle;
         b
     }
 
-    fn load_model(state: &gpu::State) -> ModelData {
+    fn load_model(state: &gpu::State) -> SafehouseResult<ModelData> {
         
-        ModelData::new::<Self,()> (
+        Ok(ModelData::new::<Self,()> (
             state,
             gpu::buffer::VertexBuffer::new(&state, &[
                 ColorVertex { pos: [-0.01,0.01,0.0,1.0], color: [1.0,1.0,1.0,1.0]},
@@ -45,11 +45,11 @@ impl Entity for Ball {
             ]),
             vec![0..6],
             None
-        )
+        ))
         
     }
 
-    fn load_shader(rm: &safehouse_render::RenderManager, group_model: u32, group_entity: u32) -> Option<gpu::shaderprogram::Program> {
+    fn load_shader(rm: &safehouse_render::RenderManager, group_model: u32, group_entity: u32) -> Option<safehouse_render::entity::EntityShaderEntry> {
         None
     }
     
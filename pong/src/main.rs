@@ -31,6 +31,12 @@ impl WinitApp for PongWindow {
 
     fn on_start(window: &std::sync::Arc<winit::window::Window>) -> Self {
         let mut rm = RenderManager::new(&window); 
+        if let Err(e) = rm.set_sample_count(4) {
+            println!("MSAA disabled: {}", e);
+        }
+        if cfg!(debug_assertions) {
+            rm.enable_shader_hot_reload();
+        }
 
         let mut camera = Camera::new(pong::SCREEN_WIDTH, pong::SCREEN_HEIGHT);
 
@@ -92,7 +98,7 @@ impl WinitApp for PongWindow {
                     self.rm.render(&self.camera);
                     self.last_rendered = Instant::now();
                 }
-                self.rm.window.request_redraw();
+                self.rm.request_redraw();
             }
 
             _ => (),
@@ -1,9 +1,9 @@
 use std::{f32::consts::PI, rc::Rc};
 
 use crate::{ball::Ball, pong::{BackForwVecs, PongPhysics, SCREEN_HEIGHT, SCREEN_WIDTH}, render};
-use render::{BINDGROUP_GLOBAL, BINDGROUP_SCENEOBJECT, entity::Entity, glam, gpu::{self, buffer::UniformPtr, program}, model::ModelData, scene::SceneObjectHandle, vertex_type::{AdvVertex, ColorVertex}, RenderManager};
+use render::{entity::Entity, glam, gpu, model::ModelData, scene::SceneObjectHandle, vertex_type::{AdvVertex, ColorVertex}, RenderManager, SafehouseResult};
 use gpu::{vertex::Vertex, wgpu,shaderprogram::Program};
-use safehouse_render::{entity::EntityPipeline, gpu::{binding::Binder, buffer::Buffer}, named_entity};
+use safehouse_render::{entity::EntityPipeline, material::{Material, MaterialLayout}, gpu::{binding::Binder, buffer::Buffer}, named_entity};
 
 pub const PADDLE_LENGTH: f32 = 0.15;
 pub const PADDLE_THICK: f32 = 0.01;
@@ -11,7 +11,8 @@ pub const PADDLE_THICK: f32 = 0.01;
 #[derive(Debug)]
 pub struct Paddle {
     scene_handle: SceneObjectHandle, 
-    color: UniformPtr<[f32; 3]>,
+    /// The paddle's color, padded to a `vec4<f32>`.
+    material: Material<[f32; 4]>,
 }
 
 impl Entity for Paddle {
@@ -19,24 +20,17 @@ impl Entity for Paddle {
     const ENTITY_TYPE_NAME: &'static str = "Paddle";
 
     fn on_instantiate(rm: &mut RenderManager, handle: SceneObjectHandle) -> Self {
-        let color = UniformPtr::new(&rm.gpu_state, [0.0, 1.0, 0.0]);
+        let material = Material::new(rm, "paddle_material", [0.0, 1.0, 0.0, 1.0], vec![]);
+        rm.set_material(handle, &material).expect("The paddle pipeline has no material!");
 
         Self {
             scene_handle: handle,
-            color
+            material
         }
     }
 
-    fn load_model(state: &gpu::State) -> ModelData {
-        let model_bytes = include_bytes!("model/paddle.dat");
-        ModelData::new::<Self,()> (
-            state,
-            gpu::buffer::VertexBuffer::new_from_raw::<ColorVertex>(&state, model_bytes),
-            vec![
-                0..(model_bytes.len()/std::mem::size_of::<ColorVertex>()) as u32
-            ],
-            None
-        )
+    fn load_model(state: &gpu::State) -> SafehouseResult<ModelData> {
+        ModelData::from_container::<Self, ColorVertex, ()>(state, include_bytes!(concat!(env!("OUT_DIR"), "/paddle.model")), None)
     }
 
     fn load_pipeline(rm: &RenderManager) -> Option<EntityPipeline> {
@@ -47,57 +41,24 @@ impl Entity for Paddle {
                 ..Default::default()
             },
             depth_stencil: None,
+            material: Some(MaterialLayout::new::<[f32; 4]>(0)),
+            ..Default::default()
         })
 
     }
     
     fn load_bindings<'a>() -> Vec<Binder<Self>> where Self: Sized {
-        vec![
-            Binder::new(0, wgpu::ShaderStages::all(), &|x|{&x.color})
-        ]
+        vec![]
     }
     
-    fn load_shader(rm: &safehouse_render::RenderManager, group_model: u32, group_entity: u32) -> Option<gpu::shaderprogram::Program> {
-        Some(program!(
-            &rm.gpu_state,
-            source: format!("
-
-            @group({BINDGROUP_GLOBAL}) @binding(0)
-            var<uniform> time: f32;
-
-            @group({BINDGROUP_SCENEOBJECT}) @binding(0)
-            var<uniform> model_mat: mat4x4<f32>;
-
-            @group({group_entity}) @binding(0)
-            var<uniform> paddle_color: vec3<f32>;
-
-            struct VIn {{
-                @location(0)
-                pos: vec4<f32>,
-                @location(1)
-                col: vec4<f32>
-            }}
-            struct VOut {{
-                @builtin(position)
-                pos: vec4<f32>,
-                @location(0)
-                col: vec4<f32>
-            }}
-
-            @vertex
-            fn vs_main(i: VIn) -> VOut {{
-                var o: VOut;
-                o.pos = model_mat * i.pos;
-                o.col = vec4<f32>(paddle_color, i.col.w);
-                return o;
-            }}
-
-            @fragment
-            fn fs_main(i: VOut) -> @location(0) vec4<f32> {{
-                return i.col;
-            }}
-            ")
-        ))
+    fn load_shader(rm: &safehouse_render::RenderManager, group_model: u32, group_entity: u32) -> Option<safehouse_render::entity::EntityShaderEntry> {
+        Some(rm.build_shader_file(
+            Self::shader_name(),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader/paddle.wgsl"),
+            include_str!("shader/paddle.wgsl"),
+            group_model,
+            group_entity,
+        ).expect("Could not build the Paddle shader!").into())
     }
     
 }
@@ -121,8 +82,7 @@ impl Paddle {
 
     /// Set the color for the paddle.
     pub fn set_color(&mut self, rm: &RenderManager, rgb: [f32; 3]) {
-        *self.color.as_mut() = rgb;
-        self.color.update(&rm.gpu_state);
+        self.material.set_params(rm, [rgb[0], rgb[1], rgb[2], 1.0]);
     }
 
     /// Detect collision if Ball hits the Paddle
@@ -6,7 +6,7 @@ use tagmap::TagMap;
 use winit_app_handler::WinitApp;
 
 use crate::paddle::{self, PongCollision, PADDLE_LENGTH, PADDLE_THICK};
-use crate::render::{entity::Entity, model::ModelData, vertex_type::ColorVertex, RenderManager};
+use crate::render::{entity::Entity, model::ModelData, vertex_type::ColorVertex, RenderManager, SafehouseResult};
 use crate::{ball::Ball, paddle::Paddle};
 
 pub const SCREEN_WIDTH: f32 = 800.0;
@@ -21,7 +21,7 @@ pub struct Pong {
 
 impl Pong {
     pub fn start(rm: &mut RenderManager) -> Self {
-        let mut state = PongState::load_game(rm);
+        let mut state = PongState::load_game(rm).expect("Could not load the game!");
         state.init(rm);
 
         Self {
@@ -112,20 +112,20 @@ pub struct PongState {
 
 impl PongState {
     /// Load resources to start the game
-    pub fn load_game(rm: &mut RenderManager) -> Self {
+    pub fn load_game(rm: &mut RenderManager) -> SafehouseResult<Self> {
 
         // Loading entity data
-        rm.load_entity::<Paddle>();
-        rm.load_entity::<Ball>();
+        rm.load_entity::<Paddle>()?;
+        rm.load_entity::<Ball>()?;
 
         // Spawn the scene objects and serve.
-        Self {
-            player: rm.spawn_sceneobject_entity::<Paddle>("Player"),
-            cpu: rm.spawn_sceneobject_entity::<Paddle>("CPU"),
-            ball: rm.spawn_sceneobject_entity::<Ball>("Ball"),
+        Ok(Self {
+            player: rm.spawn_sceneobject_entity::<Paddle>("Player")?,
+            cpu: rm.spawn_sceneobject_entity::<Paddle>("CPU")?,
+            ball: rm.spawn_sceneobject_entity::<Ball>("Ball")?,
             player_score: 0,
             cpu_score: 0,
-        }
+        })
 
     }
 
@@ -0,0 +1,28 @@
+@group(#{BINDGROUP_MATERIAL}) @binding(0)
+var<uniform> paddle_color: vec4<f32>;
+
+struct VIn {
+    @location(0)
+    pos: vec4<f32>,
+    @location(1)
+    col: vec4<f32>
+}
+struct VOut {
+    @builtin(position)
+    pos: vec4<f32>,
+    @location(0)
+    col: vec4<f32>
+}
+
+@vertex
+fn vs_main(i: VIn) -> VOut {
+    var o: VOut;
+    o.pos = obj_mat * i.pos;
+    o.col = vec4<f32>(paddle_color.rgb, i.col.w);
+    return o;
+}
+
+@fragment
+fn fs_main(i: VOut) -> @location(0) vec4<f32> {
+    return i.col;
+}
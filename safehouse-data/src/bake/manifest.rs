@@ -0,0 +1,213 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use super::BakeError;
+
+/// The kind of an asset in a manifest, from it's `[kind name]` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+    /// An OBJ or glTF model, baked into a model container.
+    Model,
+    /// An image, baked into a texture with optional mip levels.
+    Texture,
+    /// A TTF/OTF font, copied as-is.
+    Font,
+}
+
+impl AssetKind {
+    fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "model" => Some(Self::Model),
+            "texture" => Some(Self::Texture),
+            "font" => Some(Self::Font),
+            _ => None,
+        }
+    }
+
+    /// The settings an asset of this kind accepts.
+    fn keys(&self) -> &'static [&'static str] {
+        match self {
+            Self::Model => &["source", "vertex", "indexed", "mesh", "normals", "hard_angle", "winding"],
+            Self::Texture => &["source", "format", "mips"],
+            Self::Font => &["source"],
+        }
+    }
+}
+
+/// One asset of a manifest and it's settings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssetEntry {
+    pub kind: AssetKind,
+    pub name: String,
+    /// The line of the asset's header, for errors.
+    pub line: usize,
+    pub settings: Vec<(String, String)>,
+}
+
+impl AssetEntry {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.settings.iter().find(|(k, _)| k == key).map(|(_, value)| value.as_str())
+    }
+
+    /// A `true`/`false` setting.
+    pub fn get_bool(&self, key: &str, default: bool) -> Result<bool, String> {
+        match self.get(key) {
+            None => Ok(default),
+            Some("true") => Ok(true),
+            Some("false") => Ok(false),
+            Some(value) => Err(format!("'{}' should be true or false, not '{}'", key, value)),
+        }
+    }
+}
+
+/// A list of assets to bake, e.g.:
+/// ```text
+/// # Paths are relative to the manifest.
+/// [model paddle]
+/// source = res/paddle/paddle.obj
+/// vertex = ColorVertex
+///
+/// [texture bunny]
+/// source = res/obj/bunny/buntex.1001.png
+/// format = rgba8-srgb
+/// mips = true
+///
+/// [font ui]
+/// source = res/fonts/ui.ttf
+/// ```
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub path: PathBuf,
+    pub assets: Vec<AssetEntry>,
+}
+
+impl Manifest {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BakeError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|error| BakeError::Io { path: path.to_path_buf(), error })?;
+        Self::parse(&source, path)
+    }
+
+    pub fn parse(source: &str, path: &Path) -> Result<Self, BakeError> {
+        let mut assets: Vec<AssetEntry> = vec![];
+
+        for (i, line) in source.lines().enumerate() {
+            let error = |message: String| BakeError::Manifest { path: path.to_path_buf(), line: i + 1, message };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header.strip_suffix(']').ok_or_else(|| error(String::from("unclosed asset header")))?;
+                let (kind, name) = header.trim().split_once(char::is_whitespace)
+                    .ok_or_else(|| error(String::from("an asset header should be [kind name]")))?;
+                let kind = AssetKind::parse(kind).ok_or_else(|| error(format!("unknown asset kind '{}'", kind)))?;
+                let name = name.trim();
+                if assets.iter().any(|asset| asset.name == name) {
+                    return Err(error(format!("there is already an asset named '{}'", name)));
+                }
+                assets.push(AssetEntry {
+                    kind,
+                    name: String::from(name),
+                    line: i + 1,
+                    settings: vec![],
+                });
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| error(String::from("a setting should be key = value")))?;
+            let (key, value) = (key.trim(), value.trim());
+            let asset = assets.last_mut().ok_or_else(|| error(format!("'{}' before any asset header", key)))?;
+            if !asset.kind.keys().contains(&key) {
+                return Err(error(format!("{:?} assets have no '{}' setting", asset.kind, key)));
+            }
+            if asset.get(key).is_some() {
+                return Err(error(format!("'{}' is set twice", key)));
+            }
+            asset.settings.push((String::from(key), String::from(value)));
+        }
+
+        if let Some(asset) = assets.iter().find(|asset| asset.get("source").is_none()) {
+            return Err(BakeError::Manifest { path: path.to_path_buf(), line: asset.line, message: format!("'{}' has no source", asset.name) });
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            assets,
+        })
+    }
+
+    /// The directory that asset sources are relative to.
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new(""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Manifest, BakeError> {
+        Manifest::parse(source, Path::new("assets/assets.manifest"))
+    }
+
+    /// The line and message of a manifest error.
+    fn error(source: &str) -> (usize, String) {
+        match parse(source) {
+            Err(BakeError::Manifest { line, message, .. }) => (line, message),
+            other => panic!("Expected a manifest error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn assets_and_settings_are_parsed() {
+        let manifest = parse("# Paths are relative\n[model paddle]\nsource = res/paddle.obj\nvertex = ColorVertex\n\n[texture bunny skin]\nsource = bunny.png\nmips = true\n").unwrap();
+        assert_eq!(manifest.dir(), Path::new("assets"));
+        assert_eq!(manifest.assets.len(), 2);
+
+        let paddle = &manifest.assets[0];
+        assert_eq!((paddle.kind, paddle.name.as_str(), paddle.line), (AssetKind::Model, "paddle", 2));
+        assert_eq!(paddle.get("source"), Some("res/paddle.obj"));
+        assert_eq!(paddle.get("vertex"), Some("ColorVertex"));
+        assert_eq!(paddle.get("indexed"), None);
+
+        let bunny = &manifest.assets[1];
+        assert_eq!((bunny.kind, bunny.name.as_str()), (AssetKind::Texture, "bunny skin"));
+        assert_eq!(bunny.get_bool("mips", false), Ok(true));
+        assert_eq!(bunny.get_bool("missing", true), Ok(true));
+    }
+
+    #[test]
+    fn duplicate_names_are_errors() {
+        let (line, message) = error("[model crate]\nsource = crate.obj\n[texture crate]\nsource = crate.png\n");
+        assert_eq!(line, 3);
+        assert_eq!(message, "there is already an asset named 'crate'");
+    }
+
+    #[test]
+    fn unknown_keys_are_errors() {
+        assert_eq!(error("[texture crate]\nsource = crate.png\nvertex = TexVertex\n"), (3, String::from("Texture assets have no 'vertex' setting")));
+        assert_eq!(error("[font ui]\nsource = ui.ttf\nmips = true\n").1, "Font assets have no 'mips' setting");
+    }
+
+    #[test]
+    fn keys_set_twice_are_errors() {
+        assert_eq!(error("[model crate]\nsource = crate.obj\nindexed = true\nindexed = false\n"), (4, String::from("'indexed' is set twice")));
+    }
+
+    #[test]
+    fn assets_without_a_source_are_errors() {
+        // The error points at the asset's header
+        assert_eq!(error("[font ui]\nsource = ui.ttf\n\n[model crate]\nvertex = TexVertex\n"), (4, String::from("'crate' has no source")));
+    }
+
+    #[test]
+    fn malformed_lines_are_errors() {
+        assert_eq!(error("source = crate.obj\n"), (1, String::from("'source' before any asset header")));
+        assert_eq!(error("[model crate\n").1, "unclosed asset header");
+        assert_eq!(error("[model]\n").1, "an asset header should be [kind name]");
+        assert_eq!(error("[sound boom]\n").1, "unknown asset kind 'sound'");
+        assert_eq!(error("[model crate]\nsource crate.obj\n"), (2, String::from("a setting should be key = value")));
+    }
+}
@@ -0,0 +1,422 @@
+pub mod manifest;
+
+use std::{collections::{hash_map::DefaultHasher, HashMap}, fs, hash::{Hash, Hasher}, io, path::{Path, PathBuf}, time::UNIX_EPOCH};
+
+use safehouse_render::{gpu::image, vertex_type::{AdvVertex, ColorVertex, SkinnedVertex, TangentVertex, TexVertex}};
+use safehouse_shared::{model::MODEL_VERSION, texture::{TextureFileFormat, TEXTURE_VERSION}, vertex::Vertex};
+
+use crate::{model::{container::ModelWriter, gltf::{load_gltf, GltfVertex}, mesh::{MeshOptions, NormalMode, Winding, DEFAULT_HARD_ANGLE}, obj::{load_obj_file, IndexedModel, ObjVertex}}, texture::bake_texture};
+use manifest::{AssetEntry, AssetKind, Manifest};
+
+/// The file in the output directory that records what each asset was baked from.
+const DEPS_FILE: &str = "bake.deps";
+
+#[derive(Debug)]
+pub enum BakeError {
+    /// A manifest, source or output file could not be read or written.
+    Io { path: PathBuf, error: io::Error },
+
+    /// The manifest could not be parsed.
+    Manifest { path: PathBuf, line: usize, message: String },
+
+    /// An asset could not be baked.
+    Asset { name: String, message: String },
+}
+
+impl std::fmt::Display for BakeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BakeError::Io { path, error } => write!(f, "'{}': {}", path.display(), error),
+            BakeError::Manifest { path, line, message } => write!(f, "{}:{}: {}", path.display(), line, message),
+            BakeError::Asset { name, message } => write!(f, "Could not bake '{}': {}", name, message),
+        }
+    }
+}
+
+impl std::error::Error for BakeError {}
+
+/// The assets that were baked or skipped as up to date, and every file they were baked from.
+#[derive(Debug, Default)]
+pub struct BakeReport {
+    pub baked: Vec<String>,
+    pub skipped: Vec<String>,
+    pub inputs: Vec<PathBuf>,
+}
+
+/// Bakes the assets of a manifest into an output directory.\
+/// Assets whose sources and settings haven't changed since the last bake are skipped.
+pub struct Baker {
+    manifest: PathBuf,
+    out_dir: PathBuf,
+    force: bool,
+    cargo: bool,
+}
+
+impl Baker {
+    /// Bake into a `baked` directory next to the manifest.
+    pub fn new(manifest: impl AsRef<Path>) -> Self {
+        let manifest = manifest.as_ref().to_path_buf();
+        let out_dir = manifest.parent().unwrap_or(Path::new("")).join("baked");
+        Self {
+            manifest,
+            out_dir,
+            force: false,
+            cargo: false,
+        }
+    }
+
+    pub fn out_dir(mut self, out_dir: impl AsRef<Path>) -> Self {
+        self.out_dir = out_dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Bake every asset, even if it is up to date.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Print `cargo:rerun-if-changed` for the manifest and every source, when driven from a `build.rs`.
+    pub fn cargo(mut self, cargo: bool) -> Self {
+        self.cargo = cargo;
+        self
+    }
+
+    pub fn bake(&self) -> Result<BakeReport, BakeError> {
+        let manifest = Manifest::load(&self.manifest)?;
+        fs::create_dir_all(&self.out_dir).map_err(|error| BakeError::Io { path: self.out_dir.clone(), error })?;
+
+        let deps_path = self.out_dir.join(DEPS_FILE);
+        let old_stamps = read_stamps(&deps_path);
+        let mut stamps = HashMap::new();
+        let mut report = BakeReport {
+            inputs: vec![manifest.path.clone()],
+            ..Default::default()
+        };
+
+        let result = manifest.assets.iter().try_for_each(|asset| {
+            let source = manifest.dir().join(asset.get("source").unwrap_or_default());
+            let inputs = asset_inputs(asset.kind, &source);
+            let stamp = stamp(asset, &inputs);
+            let output = self.out_dir.join(output_name(asset, &source));
+            report.inputs.extend(inputs);
+
+            if !self.force && old_stamps.get(&asset.name) == Some(&stamp) && output.exists() {
+                report.skipped.push(asset.name.clone());
+            } else {
+                println!("\tBaking {:?} \"{}\"", asset.kind, asset.name);
+                let data = bake_asset(asset, &source).map_err(|message| BakeError::Asset { name: asset.name.clone(), message })?;
+                fs::write(&output, data).map_err(|error| BakeError::Io { path: output.clone(), error })?;
+                report.baked.push(asset.name.clone());
+            }
+            stamps.insert(asset.name.clone(), stamp);
+            Ok(())
+        });
+
+        // Keep the stamps of assets that were baked before an error
+        write_stamps(&deps_path, &stamps)?;
+        if self.cargo {
+            for input in &report.inputs {
+                println!("cargo:rerun-if-changed={}", input.display());
+            }
+        }
+        result.map(|_| report)
+    }
+}
+
+/// The file name of an asset in the output directory.
+pub fn output_name(asset: &AssetEntry, source: &Path) -> String {
+    match asset.kind {
+        AssetKind::Model => format!("{}.model", asset.name),
+        AssetKind::Texture => format!("{}.tex", asset.name),
+        AssetKind::Font => match source.extension() {
+            Some(ext) => format!("{}.{}", asset.name, ext.to_string_lossy()),
+            None => asset.name.clone(),
+        },
+    }
+}
+
+/// The files an asset is baked from: the source, and an OBJ's material libraries or a glTF's external buffers and images.
+fn asset_inputs(kind: AssetKind, source: &Path) -> Vec<PathBuf> {
+    let dir = source.parent().unwrap_or(Path::new(""));
+    let mut inputs = vec![source.to_path_buf()];
+    if kind != AssetKind::Model {
+        return inputs;
+    }
+
+    let text = fs::read_to_string(source).unwrap_or_default();
+    match source.extension().and_then(|ext| ext.to_str()) {
+        Some("obj") => inputs.extend(text.lines()
+            .filter_map(|line| line.trim().strip_prefix("mtllib "))
+            .flat_map(|libraries| libraries.split_whitespace())
+            .map(|library| dir.join(library))),
+        Some("gltf") => inputs.extend(text.split("\"uri\"").skip(1)
+            .filter_map(|rest| rest.split('"').nth(1))
+            .filter(|uri| !uri.starts_with("data:"))
+            .map(|uri| dir.join(uri))),
+        _ => {},
+    }
+    inputs
+}
+
+/// A hash of an asset's settings, the size and modification time of it's inputs, and the container versions.
+fn stamp(asset: &AssetEntry, inputs: &[PathBuf]) -> String {
+    let mut hasher = DefaultHasher::new();
+    (MODEL_VERSION, TEXTURE_VERSION).hash(&mut hasher);
+    asset.hash(&mut hasher);
+    for input in inputs {
+        input.hash(&mut hasher);
+        if let Ok(metadata) = fs::metadata(input) {
+            metadata.len().hash(&mut hasher);
+            metadata.modified().ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .hash(&mut hasher);
+        }
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+fn read_stamps(path: &Path) -> HashMap<String, String> {
+    fs::read_to_string(path).unwrap_or_default().lines()
+        .filter_map(|line| line.rsplit_once(' '))
+        .map(|(name, stamp)| (String::from(name), String::from(stamp)))
+        .collect()
+}
+
+fn write_stamps(path: &Path, stamps: &HashMap<String, String>) -> Result<(), BakeError> {
+    let mut lines: Vec<String> = stamps.iter().map(|(name, stamp)| format!("{} {}\n", name, stamp)).collect();
+    lines.sort();
+    fs::write(path, lines.concat()).map_err(|error| BakeError::Io { path: path.to_path_buf(), error })
+}
+
+fn bake_asset(asset: &AssetEntry, source: &Path) -> Result<Vec<u8>, String> {
+    match asset.kind {
+        AssetKind::Model => bake_model(asset, source),
+        AssetKind::Texture => {
+            let format = match asset.get("format").unwrap_or("rgba8-srgb") {
+                "rgba8" => TextureFileFormat::Rgba8,
+                "rgba8-srgb" => TextureFileFormat::Rgba8Srgb,
+                format => return Err(format!("unknown texture format '{}'", format)),
+            };
+            let mips = asset.get_bool("mips", false)?;
+            let image = image::open(source).map_err(|e| format!("could not decode '{}': {}", source.display(), e))?;
+            Ok(bake_texture(&image.to_rgba8(), format, mips))
+        },
+        AssetKind::Font => {
+            let data = fs::read(source).map_err(|e| format!("could not read '{}': {}", source.display(), e))?;
+            match data.get(0..4) {
+                Some([0, 1, 0, 0]) | Some(b"OTTO") | Some(b"true") | Some(b"ttcf") => Ok(data),
+                _ => Err(format!("'{}' is not a TTF or OTF font", source.display())),
+            }
+        },
+    }
+}
+
+fn bake_model(asset: &AssetEntry, source: &Path) -> Result<Vec<u8>, String> {
+    // Colors come from normals, like the paddle in `pong`
+    let normal_color = |n: [f32; 3]| [n[0], n[1], n[2], 1.0];
+    const MAGENTA: [f32; 3] = [1.0, 0.0, 1.0];
+
+    match asset.get("vertex").unwrap_or("AdvVertex") {
+        "ColorVertex" => build_model::<ColorVertex>(asset, source, false,
+            &|v| ColorVertex::new(v.position, normal_color(v.normal.unwrap_or(MAGENTA))),
+            &|v| ColorVertex::new([v.position[0], v.position[1], v.position[2], 1.0], normal_color(v.normal.unwrap_or(MAGENTA))),
+        ),
+        "TexVertex" => build_model::<TexVertex>(asset, source, false,
+            &|v| TexVertex::new(v.position, v.tex_coord.map_or([0.0; 2], |t| [t[0], t[1]])),
+            &|v| TexVertex::new([v.position[0], v.position[1], v.position[2], 1.0], v.tex_coord.unwrap_or_default()),
+        ),
+        "AdvVertex" => build_model::<AdvVertex>(asset, source, false, &ObjVertex::adv, &GltfVertex::adv),
+        "SkinnedVertex" => build_model::<SkinnedVertex>(asset, source, false,
+            &|v| SkinnedVertex {
+                pos: v.position,
+                normal: v.normal.unwrap_or_default(),
+                tex_coord: v.tex_coord.map_or([0.0; 2], |t| [t[0], t[1]]),
+                joints: [0; 4],
+                weights: [1.0, 0.0, 0.0, 0.0],
+            },
+            &GltfVertex::skinned,
+        ),
+        "TangentVertex" => build_model::<TangentVertex>(asset, source, true, &ObjVertex::tangent_vertex, &GltfVertex::tangent_vertex),
+        vertex => Err(format!("unknown vertex type '{}'", vertex)),
+    }
+}
+
+/// The mesh processing settings of a model: `normals` is `imported` (the default), `flat` or `smooth`,
+/// `hard_angle` is the smoothing threshold in degrees, and `winding` is `keep` (the default), `cw` or `ccw`.
+fn mesh_options(asset: &AssetEntry, tangents: bool) -> Result<MeshOptions, String> {
+    let hard_angle = match asset.get("hard_angle") {
+        None => DEFAULT_HARD_ANGLE,
+        Some(angle) => angle.parse::<f32>().map_err(|_| format!("'hard_angle' should be a number of degrees, not '{}'", angle))?,
+    };
+    let normals = match asset.get("normals").unwrap_or("imported") {
+        "imported" => NormalMode::Imported,
+        "flat" => NormalMode::Flat,
+        "smooth" => NormalMode::Smooth { hard_angle },
+        normals => return Err(format!("'normals' should be imported, flat or smooth, not '{}'", normals)),
+    };
+    let winding = match asset.get("winding").unwrap_or("keep") {
+        "keep" => Winding::Keep,
+        "cw" => Winding::Cw,
+        "ccw" => Winding::Ccw,
+        winding => return Err(format!("'winding' should be keep, cw or ccw, not '{}'", winding)),
+    };
+    Ok(MeshOptions::default().with_normals(normals).with_tangents(tangents).with_winding(winding))
+}
+
+/// The position of a vertex type's `pos` field, for the model's bounds.
+trait VertexPosition {
+    fn position(&self) -> [f32; 3];
+}
+
+macro_rules! vertex_position {
+    ($($vertex:ty),*) => {
+        $(impl VertexPosition for $vertex {
+            fn position(&self) -> [f32; 3] {
+                [self.pos[0], self.pos[1], self.pos[2]]
+            }
+        })*
+    };
+}
+
+vertex_position!(ColorVertex, TexVertex, AdvVertex, SkinnedVertex, TangentVertex);
+
+fn build_model<V: Vertex + VertexPosition + Clone>(
+    asset: &AssetEntry,
+    source: &Path,
+    tangents: bool,
+    obj_f: &dyn Fn(&ObjVertex) -> V,
+    gltf_f: &dyn Fn(&GltfVertex) -> V,
+) -> Result<Vec<u8>, String> {
+    let options = mesh_options(asset, tangents)?;
+    let model: IndexedModel<V> = match source.extension().and_then(|ext| ext.to_str()) {
+        Some("obj") => load_obj_file(source, &options).map_err(|e| e.to_string())?.map(obj_f),
+        Some("gltf") | Some("glb") => {
+            let gltf = load_gltf(source).map_err(|e| e.to_string())?;
+            let mesh = match asset.get("mesh") {
+                None => gltf.meshes.first(),
+                Some(mesh) => match mesh.parse::<usize>() {
+                    Ok(index) => gltf.meshes.get(index),
+                    Err(_) => gltf.meshes.iter().find(|m| m.name.as_deref() == Some(mesh)),
+                },
+            }.ok_or_else(|| format!("'{}' has no mesh {}", source.display(), asset.get("mesh").unwrap_or("0")))?;
+            mesh.process(&gltf.materials, &options, gltf_f)
+        },
+        _ => return Err(format!("'{}' is not an OBJ or glTF file", source.display())),
+    };
+
+    let bytes = if asset.get_bool("indexed", true)? {
+        ModelWriter::from_indexed(&model).with_bounds_from(V::position).to_bytes()
+    } else {
+        let vertices = model.expand();
+        ModelWriter::from_expanded(&model, &vertices).with_bounds_from(V::position).to_bytes()
+    };
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &[u8] = b"\0\x01\0\0 not really a font";
+    const OBJ: &str = "mtllib crate.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nusemtl wood\nf 1//1 2//1 3//1\n";
+    const MANIFEST: &str = "[font ui]\nsource = ui.ttf\n\n[model crate]\nsource = crate.obj\nvertex = ColorVertex\n";
+
+    /// A directory with a manifest, a font and an OBJ model with a material library, removed when dropped.
+    struct TempAssets(PathBuf);
+
+    impl TempAssets {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("safehouse_bake_{}_{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let assets = Self(dir);
+            assets.write("assets.manifest", MANIFEST.as_bytes());
+            assets.write("ui.ttf", FONT);
+            assets.write("crate.obj", OBJ.as_bytes());
+            assets.write("crate.mtl", b"newmtl wood\nKd 1 0.5 0\n");
+            assets
+        }
+
+        fn write(&self, file: &str, data: &[u8]) {
+            fs::write(self.0.join(file), data).unwrap();
+        }
+
+        fn bake(&self) -> BakeReport {
+            Baker::new(self.0.join("assets.manifest")).bake().unwrap()
+        }
+    }
+
+    impl Drop for TempAssets {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn unchanged_assets_are_skipped() {
+        let assets = TempAssets::new("skip");
+
+        let first = assets.bake();
+        assert_eq!(first.baked, ["ui", "crate"]);
+        assert!(first.skipped.is_empty());
+        assert!(assets.0.join("baked/ui.ttf").exists());
+        assert!(assets.0.join("baked/crate.model").exists());
+        assert!(first.inputs.contains(&assets.0.join("crate.mtl")), "The material library is an input of the model");
+
+        let second = assets.bake();
+        assert!(second.baked.is_empty());
+        assert_eq!(second.skipped, ["ui", "crate"]);
+
+        // A forced bake ignores the stamps
+        let forced = Baker::new(assets.0.join("assets.manifest")).force(true).bake().unwrap();
+        assert_eq!(forced.baked, ["ui", "crate"]);
+    }
+
+    #[test]
+    fn changed_settings_and_sources_are_rebaked() {
+        let assets = TempAssets::new("rebake");
+        assets.bake();
+
+        assets.write("assets.manifest", format!("{}normals = flat\n", MANIFEST).as_bytes());
+        let settings = assets.bake();
+        assert_eq!(settings.baked, ["crate"]);
+        assert_eq!(settings.skipped, ["ui"]);
+
+        // Stamps include the size of each input, so a longer file changes them regardless of timestamp resolution
+        assets.write("ui.ttf", &[FONT, b" with more bytes"].concat());
+        let source = assets.bake();
+        assert_eq!(source.baked, ["ui"]);
+        assert_eq!(source.skipped, ["crate"]);
+
+        assets.write("crate.mtl", b"newmtl wood\nKd 1 0.5 0\nNs 10\n");
+        assert_eq!(assets.bake().baked, ["crate"]);
+
+        // A deleted output is baked again
+        fs::remove_file(assets.0.join("baked/ui.ttf")).unwrap();
+        assert_eq!(assets.bake().baked, ["ui"]);
+    }
+
+    #[test]
+    fn model_inputs_include_libraries_and_external_files() {
+        let assets = TempAssets::new("inputs");
+        let obj = assets.0.join("crate.obj");
+        assert_eq!(asset_inputs(AssetKind::Model, &obj), [obj.clone(), assets.0.join("crate.mtl")]);
+        assert_eq!(asset_inputs(AssetKind::Font, &obj), [obj]);
+
+        let gltf = assets.0.join("scene.gltf");
+        assets.write("scene.gltf", br#"{ "buffers": [{ "uri": "scene.bin" }, { "uri": "data:application/octet-stream;base64,AAAA" }], "images": [{ "uri": "tex/albedo.png" }] }"#);
+        assert_eq!(asset_inputs(AssetKind::Model, &gltf), [gltf.clone(), assets.0.join("scene.bin"), assets.0.join("tex/albedo.png")]);
+    }
+
+    #[test]
+    fn stamps_round_trip_through_the_deps_file() {
+        let assets = TempAssets::new("stamps");
+        let path = assets.0.join(DEPS_FILE);
+        let stamps = HashMap::from([(String::from("bunny skin"), String::from("0123456789abcdef")), (String::from("ui"), String::from("fedcba9876543210"))]);
+        write_stamps(&path, &stamps).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "bunny skin 0123456789abcdef\nui fedcba9876543210\n");
+        assert_eq!(read_stamps(&path), stamps);
+        assert!(read_stamps(&assets.0.join("missing.deps")).is_empty());
+    }
+}
@@ -12,6 +12,8 @@
 use std::{fs::File, io::{Error, Write}};
 pub use safehouse_render as render;
 pub mod model;
+pub mod texture;
+pub mod bake;
 
 pub fn create_file<T>(path: &str, data: &[T]) -> Result<(),Error> {
     let mut f = File::create(path)?;
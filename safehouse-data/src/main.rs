@@ -0,0 +1,62 @@
+use std::process::ExitCode;
+
+use safehouse_data::bake::Baker;
+
+const USAGE: &str = "Usage: safehouse-data <manifest> [--out <dir>] [--force] [--cargo]
+
+Bakes the assets of a manifest into a directory, `baked` next to the manifest by default.
+  --out <dir>  The output directory.
+  --force      Bake every asset, even if it is up to date.
+  --cargo      Print cargo:rerun-if-changed for the manifest and every source.";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let mut manifest = None;
+    let mut out_dir = None;
+    let mut force = false;
+    let mut cargo = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => match args.next() {
+                Some(dir) => out_dir = Some(dir),
+                None => {
+                    eprintln!("--out needs a directory\n\n{}", USAGE);
+                    return ExitCode::FAILURE;
+                },
+            },
+            "--force" => force = true,
+            "--cargo" => cargo = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            },
+            _ if manifest.is_none() && !arg.starts_with('-') => manifest = Some(arg),
+            _ => {
+                eprintln!("Unexpected argument '{}'\n\n{}", arg, USAGE);
+                return ExitCode::FAILURE;
+            },
+        }
+    }
+
+    let Some(manifest) = manifest else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let mut baker = Baker::new(manifest).force(force).cargo(cargo);
+    if let Some(dir) = out_dir {
+        baker = baker.out_dir(dir);
+    }
+
+    match baker.bake() {
+        Ok(report) => {
+            println!("Baked {} assets, {} up to date.", report.baked.len(), report.skipped.len());
+            ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
@@ -0,0 +1,326 @@
+use std::{fs::{self, File}, io::{Error, Write}, path::Path};
+
+use safehouse_shared::{model::{ModelBounds, ModelGroup, VertexAttributeDesc, FLAG_INDEXED, FLAG_INDEX_U32, MODEL_MAGIC, MODEL_VERSION, NO_MATERIAL}, vertex::Vertex};
+
+use super::obj::IndexedModel;
+
+/// Writes vertices into a model container, which `ModelFile::read` checks against the vertex type at load time.\
+/// See `safehouse_shared::model` for the format.
+pub struct ModelWriter<'a, V> {
+    vertices: &'a [V],
+    indices: Option<&'a [u32]>,
+    groups: Vec<ModelGroup>,
+    materials: Vec<String>,
+    bounds: ModelBounds,
+}
+
+impl<'a, V: Vertex> ModelWriter<'a, V> {
+    /// A model with a single group of all the vertices, no materials and zero bounds.
+    pub fn new(vertices: &'a [V]) -> Self {
+        Self {
+            vertices,
+            indices: None,
+            groups: vec![ModelGroup { range: 0..vertices.len() as u32, material: None }],
+            materials: vec![],
+            bounds: ModelBounds::default(),
+        }
+    }
+
+    /// Write an index buffer, with a single group of all the indices.\
+    /// The indices are stored as `u16` if every vertex can be indexed with one.
+    pub fn with_indices(mut self, indices: &'a [u32]) -> Self {
+        self.indices = Some(indices);
+        self.groups = vec![ModelGroup { range: 0..indices.len() as u32, material: None }];
+        self
+    }
+
+    /// Replace the groups, as ranges of indices if the model has an index buffer, or of vertices otherwise.
+    pub fn with_groups(mut self, groups: Vec<ModelGroup>) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    /// The material names that groups refer to by index.
+    pub fn with_materials(mut self, materials: Vec<String>) -> Self {
+        self.materials = materials;
+        self
+    }
+
+    pub fn with_bounds(mut self, bounds: ModelBounds) -> Self {
+        self.bounds = bounds;
+        self
+    }
+
+    /// Compute the bounds from the position of each vertex.
+    pub fn with_bounds_from(mut self, position: impl Fn(&V) -> [f32; 3]) -> Self {
+        self.bounds = ModelBounds::from_positions(self.vertices.iter().map(position));
+        self
+    }
+
+    /// Write an `IndexedModel` with it's groups, and the name of each group's material.
+    pub fn from_indexed(model: &'a IndexedModel<V>) -> Self {
+        let (groups, materials) = Self::material_groups(model);
+        Self::new(&model.vertices)
+            .with_indices(&model.indices)
+            .with_groups(groups)
+            .with_materials(materials)
+    }
+
+    /// Write the vertices from `IndexedModel::expand` without an index buffer, with the model's groups and materials.
+    pub fn from_expanded(model: &IndexedModel<V>, vertices: &'a [V]) -> Self {
+        let (groups, materials) = Self::material_groups(model);
+        Self::new(vertices)
+            .with_groups(groups)
+            .with_materials(materials)
+    }
+
+    fn material_groups(model: &IndexedModel<V>) -> (Vec<ModelGroup>, Vec<String>) {
+        let mut materials: Vec<String> = vec![];
+        let groups = model.groups.iter().map(|group| ModelGroup {
+            range: group.indices.clone(),
+            material: group.material.as_ref().map(|name| {
+                match materials.iter().position(|m| m == name) {
+                    Some(i) => i as u32,
+                    None => {
+                        materials.push(name.clone());
+                        (materials.len() - 1) as u32
+                    },
+                }
+            }),
+        }).collect();
+        (groups, materials)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let attributes = VertexAttributeDesc::of::<V>();
+        let index_u32 = self.vertices.len() > u16::MAX as usize + 1;
+        let mut flags = 0;
+        if self.indices.is_some() {
+            flags |= FLAG_INDEXED;
+            if index_u32 {
+                flags |= FLAG_INDEX_U32;
+            }
+        }
+
+        let mut out = vec![];
+        out.extend_from_slice(&MODEL_MAGIC);
+        out.extend_from_slice(&MODEL_VERSION.to_le_bytes());
+        out.extend_from_slice(&flags.to_le_bytes());
+        for value in [
+            std::mem::size_of::<V>() as u32,
+            self.vertices.len() as u32,
+            attributes.len() as u32,
+            self.groups.len() as u32,
+            self.indices.map_or(0, |indices| indices.len() as u32),
+            self.materials.len() as u32,
+        ] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in self.bounds.min.iter().chain(self.bounds.max.iter()) {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+
+        for attribute in &attributes {
+            for value in [attribute.format, attribute.offset, attribute.shader_location] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        for group in &self.groups {
+            for value in [group.range.start, group.range.end, group.material.unwrap_or(NO_MATERIAL)] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        for material in &self.materials {
+            out.extend_from_slice(&(material.len() as u32).to_le_bytes());
+            out.extend_from_slice(material.as_bytes());
+            pad(&mut out);
+        }
+
+        out.extend_from_slice(unsafe { slicebytes::cast_bytes(self.vertices) });
+        pad(&mut out);
+
+        if let Some(indices) = self.indices {
+            for &index in indices {
+                if index_u32 {
+                    out.extend_from_slice(&index.to_le_bytes());
+                } else {
+                    out.extend_from_slice(&(index as u16).to_le_bytes());
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Create or overwrite a model container file, and it's directory if it doesn't exist.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut f = File::create(path)?;
+        f.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+}
+
+fn pad(out: &mut Vec<u8>) {
+    out.resize(out.len().next_multiple_of(4), 0);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use safehouse_render::gpu::wgpu;
+    use safehouse_shared::{model::{ModelFile, ModelFileError, ModelIndices}, vertex::{ColorVertex, TexVertex}};
+
+    use super::*;
+
+    /// A vertex the same size as `ColorVertex` that only has a position attribute.
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct PositionOnlyVertex {
+        pos: [f32; 4],
+        padding: [f32; 4],
+    }
+
+    impl Vertex for PositionOnlyVertex {
+        fn desc() -> &'static wgpu::VertexBufferLayout<'static> {
+            &wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<PositionOnlyVertex>() as u64,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &[
+                    wgpu::VertexAttribute {
+                        format: wgpu::VertexFormat::Float32x4,
+                        offset: 0,
+                        shader_location: 0,
+                    },
+                ],
+            }
+        }
+    }
+
+    fn vertices(count: usize) -> Vec<ColorVertex> {
+        (0..count).map(|i| ColorVertex::new([i as f32, 0.0, -(i as f32), 1.0], [1.0, 0.5, 0.0, 1.0])).collect()
+    }
+
+    /// A valid indexed container of 4 vertices with 2 groups and a material.
+    fn quad_bytes() -> Vec<u8> {
+        let vertices = vertices(4);
+        ModelWriter::new(&vertices)
+            .with_indices(&[0, 1, 2, 2, 3, 0])
+            .with_groups(vec![
+                ModelGroup { range: 0..3, material: Some(0) },
+                ModelGroup { range: 3..6, material: None },
+            ])
+            .with_materials(vec![String::from("brick")])
+            .with_bounds_from(|v| [v.pos[0], v.pos[1], v.pos[2]])
+            .to_bytes()
+    }
+
+    fn read_indices(indices: &ModelIndices) -> Vec<u32> {
+        match indices {
+            ModelIndices::U16(bytes) => bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u32).collect(),
+            ModelIndices::U32(bytes) => bytes.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect(),
+        }
+    }
+
+    #[test]
+    fn round_trip_with_u16_indices() {
+        let bytes = quad_bytes();
+        let file = ModelFile::read::<ColorVertex>(&bytes).unwrap();
+
+        assert_eq!(file.vertex_count, 4);
+        assert_eq!(file.vertex_stride, std::mem::size_of::<ColorVertex>() as u32);
+        assert_eq!(file.vertex_data, unsafe { slicebytes::cast_bytes(vertices(4).as_slice()) });
+        assert_eq!(file.group_ranges(), [0..3, 3..6]);
+        assert_eq!(file.group_material(0), Some("brick"));
+        assert_eq!(file.group_material(1), None);
+        assert_eq!(file.bounds, ModelBounds { min: [0.0, 0.0, -3.0], max: [3.0, 0.0, 0.0] });
+
+        let indices = file.indices.expect("The model should be indexed");
+        assert!(matches!(indices, ModelIndices::U16(_)));
+        assert_eq!(read_indices(&indices), [0, 1, 2, 2, 3, 0]);
+    }
+
+    #[test]
+    fn round_trip_with_u32_indices() {
+        // One more vertex than a u16 can index
+        let vertices = vertices(u16::MAX as usize + 2);
+        let last = vertices.len() as u32 - 1;
+        let bytes = ModelWriter::new(&vertices).with_indices(&[0, last, 1]).to_bytes();
+        let file = ModelFile::read::<ColorVertex>(&bytes).unwrap();
+
+        assert_eq!(file.vertex_count, last + 1);
+        assert_eq!(file.groups[0].range, 0..3);
+        let indices = file.indices.expect("The model should be indexed");
+        assert!(matches!(indices, ModelIndices::U32(_)));
+        assert_eq!(read_indices(&indices), [0, last, 1]);
+    }
+
+    #[test]
+    fn round_trip_without_indices() {
+        let vertices = vertices(3);
+        let bytes = ModelWriter::new(&vertices).to_bytes();
+        let file = ModelFile::read::<ColorVertex>(&bytes).unwrap();
+
+        assert!(file.indices.is_none());
+        assert_eq!(file.groups[0].range, 0..3);
+        assert!(file.materials.is_empty());
+    }
+
+    #[test]
+    fn bad_headers_are_rejected() {
+        let mut bytes = quad_bytes();
+        bytes[0] = b'X';
+        assert_eq!(ModelFile::parse(&bytes).unwrap_err(), ModelFileError::BadMagic);
+        assert_eq!(ModelFile::parse(b"SH").unwrap_err(), ModelFileError::BadMagic);
+
+        let mut bytes = quad_bytes();
+        bytes[4..6].copy_from_slice(&(MODEL_VERSION + 1).to_le_bytes());
+        assert_eq!(ModelFile::parse(&bytes).unwrap_err(), ModelFileError::UnsupportedVersion(MODEL_VERSION + 1));
+    }
+
+    #[test]
+    fn truncated_data_is_rejected() {
+        // The quad's header is 56 bytes, then 24 bytes of attributes, 24 of groups, 12 of materials, 128 of vertices and 12 of indices
+        let bytes = quad_bytes();
+        assert_eq!(bytes.len(), 256);
+
+        for (len, expected) in [(8, "header"), (40, "header"), (70, "attributes"), (90, "groups"), (110, "materials"), (150, "vertices"), (255, "indices")] {
+            match ModelFile::parse(&bytes[..len]) {
+                Err(ModelFileError::Truncated { section, needed, len: actual }) => {
+                    assert_eq!((section, actual), (expected, len));
+                    assert!(needed > len);
+                },
+                other => panic!("Expected the {} section to be truncated at {} bytes, got {:?}", expected, len, other),
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range_references_are_rejected() {
+        let vertices = vertices(3);
+        let invalid = |writer: ModelWriter<ColorVertex>| matches!(ModelFile::parse(&writer.to_bytes()), Err(ModelFileError::Invalid(_)));
+
+        assert!(invalid(ModelWriter::new(&vertices).with_groups(vec![ModelGroup { range: 0..4, material: None }])));
+        assert!(invalid(ModelWriter::new(&vertices).with_indices(&[0, 1, 2]).with_groups(vec![ModelGroup { range: std::ops::Range { start: 2, end: 1 }, material: None }])));
+        assert!(invalid(ModelWriter::new(&vertices).with_groups(vec![ModelGroup { range: 0..3, material: Some(1) }]).with_materials(vec![String::from("brick")])));
+        assert!(invalid(ModelWriter::new(&vertices).with_indices(&[0, 1, 3])));
+    }
+
+    #[test]
+    fn mismatched_vertex_types_are_rejected() {
+        let bytes = quad_bytes();
+        let file = ModelFile::parse(&bytes).unwrap();
+
+        assert!(file.check_vertex::<ColorVertex>().is_ok());
+        // A different size
+        assert!(matches!(file.check_vertex::<TexVertex>(), Err(ModelFileError::VertexMismatch(_))));
+        // The same size with different attributes
+        assert!(matches!(file.check_vertex::<PositionOnlyVertex>(), Err(ModelFileError::VertexMismatch(_))));
+        assert!(matches!(ModelFile::read::<TexVertex>(&bytes), Err(ModelFileError::VertexMismatch(_))));
+    }
+}
@@ -0,0 +1,603 @@
+use std::path::Path;
+
+use glam::{Mat4, Quat, Vec3};
+use safehouse_render::{gpu::image::RgbaImage, vertex_type::{AdvVertex, SkinnedVertex, TangentVertex}};
+
+use super::{mesh::{process, MeshOptions}, obj::{IndexedModel, MaterialGroup}};
+
+pub use ::gltf::Error as GltfError;
+
+/// A glTF 2.0 file, with it's buffers read into vertex streams and it's images decoded to RGBA8.\
+/// Indices into `meshes`, `nodes`, `materials`, `images`, `skins` and `animations` are the same as in the file.
+pub struct GltfModel {
+    pub meshes: Vec<GltfMesh>,
+    pub nodes: Vec<GltfNode>,
+    /// The root nodes of the default scene, or of the first scene if there is no default.
+    pub scene_roots: Vec<usize>,
+    pub materials: Vec<PbrMaterial>,
+    pub images: Vec<RgbaImage>,
+    pub skins: Vec<GltfSkin>,
+    pub animations: Vec<GltfAnimation>,
+}
+
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+/// The vertex streams of one primitive. Streams that the primitive doesn't have are empty.
+pub struct GltfPrimitive {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tangents: Vec<[f32; 4]>,
+    pub tex_coords: Vec<[f32; 2]>,
+    pub joints: Vec<[u16; 4]>,
+    pub weights: Vec<[f32; 4]>,
+    /// A triangle list, or `None` if the vertices are drawn in order.
+    pub indices: Option<Vec<u32>>,
+    /// The index of the primitive's material in `GltfModel::materials`, or `None` for the default material.
+    pub material: Option<usize>,
+}
+
+/// The attributes of one vertex of a primitive, passed to the function that builds each vertex.
+#[derive(Debug, Clone, Copy)]
+pub struct GltfVertex {
+    pub position: [f32; 3],
+    pub normal: Option<[f32; 3]>,
+    pub tangent: Option<[f32; 4]>,
+    pub tex_coord: Option<[f32; 2]>,
+    pub joints: Option<[u16; 4]>,
+    pub weights: Option<[f32; 4]>,
+    /// The index of the primitive in it's mesh.
+    pub primitive: usize,
+}
+
+impl GltfVertex {
+    /// An `AdvVertex` bound to the most weighted joint, with the primitive as it's group.
+    pub fn adv(&self) -> AdvVertex {
+        let bone_id = match (self.joints, self.weights) {
+            (Some(joints), Some(weights)) => {
+                let strongest = (0..4).fold(0, |best, i| if weights[i] > weights[best] { i } else { best });
+                joints[strongest] as u32
+            },
+            _ => 0,
+        };
+        let tex_coord = self.tex_coord.unwrap_or_default();
+        AdvVertex {
+            pos: [self.position[0], self.position[1], self.position[2], 1.0],
+            texcoord: [tex_coord[0], tex_coord[1], 0.0],
+            normal: self.normal.unwrap_or_default(),
+            group_id: self.primitive as u32,
+            bone_id,
+        }
+    }
+
+    /// A `TangentVertex`. Run `GltfMesh::process` with tangents first if the primitive has none.
+    pub fn tangent_vertex(&self) -> TangentVertex {
+        TangentVertex {
+            pos: [self.position[0], self.position[1], self.position[2], 1.0],
+            normal: self.normal.unwrap_or_default(),
+            tex_coord: self.tex_coord.unwrap_or_default(),
+            tangent: self.tangent.unwrap_or([1.0, 0.0, 0.0, 1.0]),
+        }
+    }
+
+    /// A `SkinnedVertex` with all 4 joints. Unskinned vertices are fully weighted to joint 0.
+    pub fn skinned(&self) -> SkinnedVertex {
+        let joints = self.joints.unwrap_or_default();
+        SkinnedVertex {
+            pos: [self.position[0], self.position[1], self.position[2], 1.0],
+            normal: self.normal.unwrap_or_default(),
+            tex_coord: self.tex_coord.unwrap_or_default(),
+            joints: joints.map(|joint| joint as u32),
+            weights: self.weights.unwrap_or([1.0, 0.0, 0.0, 0.0]),
+        }
+    }
+}
+
+pub struct GltfNode {
+    pub name: Option<String>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub skin: Option<usize>,
+    pub translation: [f32; 3],
+    /// A quaternion, as `[x, y, z, w]`.
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+impl GltfNode {
+    /// The transform of this node relative to it's parent.
+    pub fn local_transform(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            Vec3::from(self.scale),
+            Quat::from_array(self.rotation),
+            Vec3::from(self.translation),
+        )
+    }
+}
+
+/// A reference to an image in `GltfModel::images`, and the texture coordinate set it is read with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureRef {
+    pub image: usize,
+    pub tex_coord: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    /// Fragments with an alpha below the cutoff are discarded.
+    Mask(f32),
+    Blend,
+}
+
+/// A metallic-roughness material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PbrMaterial {
+    pub name: Option<String>,
+    pub base_color: [f32; 4],
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Metalness in the blue channel and roughness in the green channel.
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive: [f32; 3],
+    pub emissive_texture: Option<TextureRef>,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+}
+
+impl PbrMaterial {
+    /// The factors of this material as a uniform block, for `GroupMaterial::new` or `Material::new`.
+    pub fn params(&self) -> PbrParams {
+        PbrParams {
+            base_color: self.base_color,
+            emissive: self.emissive,
+            metallic: self.metallic,
+            roughness: self.roughness,
+            normal_scale: self.normal_scale,
+            occlusion_strength: self.occlusion_strength,
+            alpha_cutoff: match self.alpha_mode {
+                AlphaMode::Mask(cutoff) => cutoff,
+                _ => 0.0,
+            },
+        }
+    }
+}
+
+/// The uniform block of a `PbrMaterial`, laid out like this WGSL struct:
+/// ```wgsl
+/// struct PbrParams {
+///     base_color: vec4<f32>,
+///     emissive: vec3<f32>,
+///     metallic: f32,
+///     roughness: f32,
+///     normal_scale: f32,
+///     occlusion_strength: f32,
+///     alpha_cutoff: f32,
+/// }
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PbrParams {
+    pub base_color: [f32; 4],
+    pub emissive: [f32; 3],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    pub alpha_cutoff: f32,
+}
+
+pub struct GltfSkin {
+    pub name: Option<String>,
+    /// The nodes of each joint, in the order `GltfVertex::joints` refers to them.
+    pub joints: Vec<usize>,
+    /// One matrix per joint, or identity matrices if the file has none.
+    pub inverse_bind_matrices: Vec<[[f32; 4]; 4]>,
+    pub skeleton: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+    /// Each keyframe has an in-tangent, a value and an out-tangent, in that order.
+    CubicSpline,
+}
+
+/// The keyframe values of a channel.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelValues {
+    Translation(Vec<[f32; 3]>),
+    /// Quaternions, as `[x, y, z, w]`.
+    Rotation(Vec<[f32; 4]>),
+    Scale(Vec<[f32; 3]>),
+    /// The weight of every morph target, for each keyframe.
+    MorphWeights(Vec<f32>),
+}
+
+pub struct GltfChannel {
+    pub node: usize,
+    pub interpolation: Interpolation,
+    /// The time of each keyframe in seconds.
+    pub times: Vec<f32>,
+    pub values: ChannelValues,
+}
+
+pub struct GltfAnimation {
+    pub name: Option<String>,
+    pub channels: Vec<GltfChannel>,
+}
+
+impl GltfAnimation {
+    /// The time of the last keyframe in seconds.
+    pub fn duration(&self) -> f32 {
+        self.channels.iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max)
+    }
+}
+
+/// Load a `.gltf` or `.glb` file, with it's external buffers and images resolved relative to it.
+pub fn load_gltf(path: impl AsRef<Path>) -> Result<GltfModel, GltfError> {
+    let (document, buffers, images) = ::gltf::import(path)?;
+    GltfModel::new(&document, &buffers, images)
+}
+
+/// Load a `.glb` file, or a `.gltf` file with embedded buffers and images, from bytes.\
+/// This works with `include_bytes!` at runtime, but external files can't be resolved.
+pub fn load_gltf_slice(data: &[u8]) -> Result<GltfModel, GltfError> {
+    let (document, buffers, images) = ::gltf::import_slice(data)?;
+    GltfModel::new(&document, &buffers, images)
+}
+
+impl GltfModel {
+    /// Read the document into vertex streams, failing if a primitive's indices are out of range of it's vertices or an image has less pixels than it's size.
+    fn new(document: &::gltf::Document, buffers: &[::gltf::buffer::Data], images: Vec<::gltf::image::Data>) -> Result<Self, GltfError> {
+        let get_buffer = |buffer: ::gltf::Buffer| Some(&*buffers[buffer.index()]);
+
+        let meshes = document.meshes().map(|mesh| Ok(GltfMesh {
+            name: mesh.name().map(String::from),
+            primitives: mesh.primitives().filter(|primitive| {
+                let triangles = primitive.mode() == ::gltf::mesh::Mode::Triangles;
+                if !triangles {
+                    println!("\tSkipping a {:?} primitive in mesh {}", primitive.mode(), mesh.index());
+                }
+                triangles
+            }).map(|primitive| {
+                let reader = primitive.reader(get_buffer);
+                let positions: Vec<[f32; 3]> = reader.read_positions().map(|it| it.collect()).unwrap_or_default();
+                let indices: Option<Vec<u32>> = reader.read_indices().map(|it| it.into_u32().collect());
+
+                // Out of range indices would otherwise panic when the mesh is built
+                if let Some(index) = indices.iter().flatten().find(|&&index| index as usize >= positions.len()) {
+                    println!("\tIndex {} of primitive {} in mesh {} is out of range for it's {} vertices", index, primitive.index(), mesh.index(), positions.len());
+                    let path = ::gltf::json::Path::new().field("meshes").index(mesh.index()).field("primitives").index(primitive.index()).field("indices");
+                    return Err(GltfError::Validation(vec![(path, ::gltf::json::validation::Error::IndexOutOfBounds)]));
+                }
+
+                Ok(GltfPrimitive {
+                    positions,
+                    normals: reader.read_normals().map(|it| it.collect()).unwrap_or_default(),
+                    tangents: reader.read_tangents().map(|it| it.collect()).unwrap_or_default(),
+                    tex_coords: reader.read_tex_coords(0).map(|it| it.into_f32().collect()).unwrap_or_default(),
+                    joints: reader.read_joints(0).map(|it| it.into_u16().collect()).unwrap_or_default(),
+                    weights: reader.read_weights(0).map(|it| it.into_f32().collect()).unwrap_or_default(),
+                    indices,
+                    material: primitive.material().index(),
+                })
+            }).collect::<Result<_, GltfError>>()?,
+        })).collect::<Result<_, GltfError>>()?;
+
+        let mut nodes: Vec<GltfNode> = document.nodes().map(|node| {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            GltfNode {
+                name: node.name().map(String::from),
+                parent: None,
+                children: node.children().map(|child| child.index()).collect(),
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                skin: node.skin().map(|skin| skin.index()),
+                translation,
+                rotation,
+                scale,
+            }
+        }).collect();
+        for i in 0..nodes.len() {
+            for child in nodes[i].children.clone() {
+                nodes[child].parent = Some(i);
+            }
+        }
+
+        let scene_roots = document.default_scene().or_else(|| document.scenes().next())
+            .map(|scene| scene.nodes().map(|node| node.index()).collect())
+            .unwrap_or_default();
+
+        let texture_ref = |texture: ::gltf::Texture, tex_coord: u32| TextureRef {
+            image: texture.source().index(),
+            tex_coord,
+        };
+        let materials = document.materials().map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            PbrMaterial {
+                name: material.name().map(String::from),
+                base_color: pbr.base_color_factor(),
+                base_color_texture: pbr.base_color_texture().map(|info| texture_ref(info.texture(), info.tex_coord())),
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                metallic_roughness_texture: pbr.metallic_roughness_texture().map(|info| texture_ref(info.texture(), info.tex_coord())),
+                normal_texture: material.normal_texture().map(|normal| texture_ref(normal.texture(), normal.tex_coord())),
+                normal_scale: material.normal_texture().map_or(1.0, |normal| normal.scale()),
+                occlusion_texture: material.occlusion_texture().map(|occlusion| texture_ref(occlusion.texture(), occlusion.tex_coord())),
+                occlusion_strength: material.occlusion_texture().map_or(1.0, |occlusion| occlusion.strength()),
+                emissive: material.emissive_factor(),
+                emissive_texture: material.emissive_texture().map(|info| texture_ref(info.texture(), info.tex_coord())),
+                alpha_mode: match material.alpha_mode() {
+                    ::gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                    ::gltf::material::AlphaMode::Mask => AlphaMode::Mask(material.alpha_cutoff().unwrap_or(0.5)),
+                    ::gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+                },
+                double_sided: material.double_sided(),
+            }
+        }).collect();
+
+        let skins = document.skins().map(|skin| {
+            let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+            let inverse_bind_matrices = skin.reader(get_buffer).read_inverse_bind_matrices()
+                .map(|it| it.collect())
+                .unwrap_or_else(|| vec![Mat4::IDENTITY.to_cols_array_2d(); joints.len()]);
+            GltfSkin {
+                name: skin.name().map(String::from),
+                joints,
+                inverse_bind_matrices,
+                skeleton: skin.skeleton().map(|node| node.index()),
+            }
+        }).collect();
+
+        let animations = document.animations().map(|animation| GltfAnimation {
+            name: animation.name().map(String::from),
+            channels: animation.channels().filter_map(|channel| {
+                let reader = channel.reader(get_buffer);
+                let times = reader.read_inputs()?.collect();
+                let values = match reader.read_outputs()? {
+                    ::gltf::animation::util::ReadOutputs::Translations(it) => ChannelValues::Translation(it.collect()),
+                    ::gltf::animation::util::ReadOutputs::Rotations(it) => ChannelValues::Rotation(it.into_f32().collect()),
+                    ::gltf::animation::util::ReadOutputs::Scales(it) => ChannelValues::Scale(it.collect()),
+                    ::gltf::animation::util::ReadOutputs::MorphTargetWeights(it) => ChannelValues::MorphWeights(it.into_f32().collect()),
+                };
+                Some(GltfChannel {
+                    node: channel.target().node().index(),
+                    interpolation: match channel.sampler().interpolation() {
+                        ::gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                        ::gltf::animation::Interpolation::Step => Interpolation::Step,
+                        ::gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                    },
+                    times,
+                    values,
+                })
+            }).collect(),
+        }).collect();
+
+        Ok(Self {
+            meshes,
+            nodes,
+            scene_roots,
+            materials,
+            images: images.into_iter().enumerate().map(|(i, image)| rgba8_image(i, image)).collect::<Result<_, GltfError>>()?,
+            skins,
+            animations,
+        })
+    }
+
+    /// The transform of a node relative to the scene, including all of it's parents.
+    pub fn world_transform(&self, node: usize) -> Mat4 {
+        let mut transform = self.nodes[node].local_transform();
+        let mut parent = self.nodes[node].parent;
+        while let Some(p) = parent {
+            transform = self.nodes[p].local_transform() * transform;
+            parent = self.nodes[p].parent;
+        }
+        transform
+    }
+}
+
+impl GltfMesh {
+    /// Build the primitives of this mesh into one indexed model, with a group per primitive.\
+    /// Each group's `material_index` refers to `GltfModel::materials`, so `GroupMaterial`s can be made from them.
+    pub fn build<V>(&self, materials: &[PbrMaterial], vertex_f: &dyn Fn(&GltfVertex) -> V) -> IndexedModel<V> {
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        let mut groups = vec![];
+
+        for (i, primitive) in self.primitives.iter().enumerate() {
+            let base = vertices.len() as u32;
+            let start = indices.len() as u32;
+
+            vertices.extend(primitive.positions.iter().enumerate().map(|(v, position)| vertex_f(&GltfVertex {
+                position: *position,
+                normal: primitive.normals.get(v).copied(),
+                tangent: primitive.tangents.get(v).copied(),
+                tex_coord: primitive.tex_coords.get(v).copied(),
+                joints: primitive.joints.get(v).copied(),
+                weights: primitive.weights.get(v).copied(),
+                primitive: i,
+            })));
+            match &primitive.indices {
+                Some(primitive_indices) => indices.extend(primitive_indices.iter().map(|index| base + index)),
+                None => indices.extend(base..vertices.len() as u32),
+            }
+
+            groups.push(MaterialGroup {
+                material: primitive.material.and_then(|m| materials.get(m)).and_then(|m| m.name.clone()),
+                material_index: primitive.material,
+                indices: start..indices.len() as u32,
+            });
+        }
+
+        IndexedModel {
+            vertices,
+            indices,
+            groups,
+            materials: Default::default(),
+        }
+    }
+
+    /// Like `build`, but runs `mesh::process` on the mesh first, e.g. to generate the normals and tangents it doesn't have.
+    pub fn process<V>(&self, materials: &[PbrMaterial], options: &MeshOptions, vertex_f: &dyn Fn(&GltfVertex) -> V) -> IndexedModel<V> {
+        process(self.build(materials, &|vertex| *vertex), options).map(vertex_f)
+    }
+}
+
+/// Convert decoded glTF image `index` to RGBA8. 16 bit channels keep their high byte, and float channels are clamped.
+fn rgba8_image(index: usize, image: ::gltf::image::Data) -> Result<RgbaImage, GltfError> {
+    use ::gltf::image::Format;
+
+    let (channels, bytes_per_channel) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let channel = |bytes: &[u8]| match bytes_per_channel {
+        1 => bytes[0],
+        2 => bytes[1],
+        _ => (f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).clamp(0.0, 1.0) * 255.0).round() as u8,
+    };
+
+    let mut rgba = Vec::with_capacity(image.width as usize * image.height as usize * 4);
+    for pixel in image.pixels.chunks_exact(channels * bytes_per_channel) {
+        let values: Vec<u8> = pixel.chunks_exact(bytes_per_channel).map(channel).collect();
+        rgba.extend_from_slice(&match channels {
+            1 => [values[0], values[0], values[0], 255],
+            2 => [values[0], values[1], 0, 255],
+            3 => [values[0], values[1], values[2], 255],
+            _ => [values[0], values[1], values[2], values[3]],
+        });
+    }
+
+    RgbaImage::from_raw(image.width, image.height, rgba).ok_or_else(|| {
+        println!("\tImage {} has {} bytes of pixels, which is too few for it's {}x{} size", index, image.pixels.len(), image.width, image.height);
+        GltfError::Validation(vec![(::gltf::json::Path::new().field("images").index(index), ::gltf::json::validation::Error::Invalid)])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 3 positions of a right triangle, then the u16 indices 0, 1, 2 padded to 4 bytes.
+    const TRIANGLE_BUFFER: &str = "AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA=";
+    /// The same triangle with the indices 0, 1, 5.
+    const OUT_OF_RANGE_BUFFER: &str = "AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAUAAAA=";
+
+    /// A glTF file with one indexed triangle mesh in an embedded buffer, and `nodes` as it's node list.
+    fn gltf(buffer: &str, nodes: &str) -> String {
+        format!(r#"{{
+            "asset": {{ "version": "2.0" }},
+            "buffers": [{{ "byteLength": 44, "uri": "data:application/octet-stream;base64,{buffer}" }}],
+            "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": 36, "target": 34962 }},
+                {{ "buffer": 0, "byteOffset": 36, "byteLength": 6, "target": 34963 }}
+            ],
+            "accessors": [
+                {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] }},
+                {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+            ],
+            "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1 }}] }}],
+            "nodes": {nodes},
+            "scenes": [{{ "nodes": [0] }}],
+            "scene": 0
+        }}"#)
+    }
+
+    #[test]
+    fn embedded_triangles_are_loaded() {
+        let model = load_gltf_slice(gltf(TRIANGLE_BUFFER, r#"[{ "mesh": 0 }]"#).as_bytes()).unwrap();
+        assert_eq!(model.scene_roots, [0]);
+        assert_eq!(model.nodes[0].mesh, Some(0));
+
+        let primitive = &model.meshes[0].primitives[0];
+        assert_eq!(primitive.positions, [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+        assert_eq!(primitive.indices.as_deref(), Some(&[0, 1, 2][..]));
+        assert!(primitive.normals.is_empty());
+        assert_eq!(primitive.material, None);
+
+        let built = model.meshes[0].build(&model.materials, &|vertex| vertex.position);
+        assert_eq!(built.indices, [0, 1, 2]);
+        assert_eq!(built.groups.len(), 1);
+    }
+
+    #[test]
+    fn out_of_range_indices_are_errors() {
+        match load_gltf_slice(gltf(OUT_OF_RANGE_BUFFER, r#"[{ "mesh": 0 }]"#).as_bytes()) {
+            Err(GltfError::Validation(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].0.0, "meshes[0].primitives[0].indices");
+                assert_eq!(errors[0].1, ::gltf::json::validation::Error::IndexOutOfBounds);
+            },
+            Err(e) => panic!("Expected a validation error, got {:?}", e),
+            Ok(_) => panic!("Expected a validation error"),
+        }
+    }
+
+    #[test]
+    fn node_hierarchies_combine_transforms() {
+        let nodes = r#"[
+            { "name": "root", "translation": [1, 0, 0], "children": [1] },
+            { "name": "arm", "translation": [0, 2, 0], "scale": [2, 2, 2], "children": [2] },
+            { "name": "hand", "translation": [0, 0, 3], "mesh": 0 }
+        ]"#;
+        let model = load_gltf_slice(gltf(TRIANGLE_BUFFER, nodes).as_bytes()).unwrap();
+
+        let parents: Vec<_> = model.nodes.iter().map(|node| node.parent).collect();
+        assert_eq!(parents, [None, Some(0), Some(1)]);
+        assert_eq!(model.nodes[0].children, [1]);
+        assert_eq!(model.scene_roots, [0]);
+
+        // The hand's offset is scaled by the arm, then both are moved by the root
+        let hand = model.world_transform(2).transform_point3(Vec3::ZERO);
+        assert!(hand.abs_diff_eq(Vec3::new(1.0, 2.0, 6.0), 1e-6), "{}", hand);
+        assert_eq!(model.world_transform(0).transform_point3(Vec3::ZERO), Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    fn image(format: ::gltf::image::Format, pixels: Vec<u8>) -> ::gltf::image::Data {
+        ::gltf::image::Data { pixels, format, width: 2, height: 1 }
+    }
+
+    #[test]
+    fn images_are_converted_to_rgba8() {
+        use ::gltf::image::Format;
+
+        let gray = rgba8_image(0, image(Format::R8, vec![10, 20])).unwrap();
+        assert_eq!(gray.as_raw(), &[10, 10, 10, 255, 20, 20, 20, 255]);
+
+        // 16 bit channels keep their high byte
+        let rg = rgba8_image(0, image(Format::R16G16, vec![0x00, 0x12, 0xff, 0x34, 0, 0, 0, 0xff])).unwrap();
+        assert_eq!(rg.as_raw(), &[0x12, 0x34, 0, 255, 0, 0xff, 0, 255]);
+
+        let float: Vec<u8> = [2.0f32, 0.5, -1.0, 1.0, 0.0, 0.0, 0.0, 0.0].iter().flat_map(|value| value.to_le_bytes()).collect();
+        let clamped = rgba8_image(0, image(Format::R32G32B32A32FLOAT, float)).unwrap();
+        assert_eq!(clamped.as_raw(), &[255, 128, 0, 255, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn images_smaller_than_their_size_are_errors() {
+        match rgba8_image(3, image(::gltf::image::Format::R8G8B8A8, vec![255; 4])) {
+            Err(GltfError::Validation(errors)) => assert_eq!(errors[0].0.0, "images[3]"),
+            Err(e) => panic!("Expected a validation error, got {:?}", e),
+            Ok(_) => panic!("Expected a validation error"),
+        }
+    }
+}
@@ -0,0 +1,606 @@
+use std::collections::HashMap;
+
+use glam::Vec3;
+
+use super::{gltf::GltfVertex, obj::{IndexedModel, ObjVertex}};
+
+/// The hard edge threshold used to fill in missing normals with `NormalMode::Imported`.
+pub const DEFAULT_HARD_ANGLE: f32 = 60.0;
+
+/// A vertex that `process` can generate normals and tangents for.
+pub trait MeshVertex: Clone {
+    fn position(&self) -> [f32; 3];
+    fn normal(&self) -> Option<[f32; 3]>;
+    fn set_normal(&mut self, normal: [f32; 3]);
+    fn tex_coord(&self) -> Option<[f32; 2]>;
+    fn tangent(&self) -> Option<[f32; 4]>;
+    fn set_tangent(&mut self, tangent: [f32; 4]);
+}
+
+/// How `process` treats the normals of a mesh.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NormalMode {
+    /// Keep the imported normals, and generate smooth normals (with `DEFAULT_HARD_ANGLE`) only for vertices that have none.
+    #[default]
+    Imported,
+    /// Replace all normals with the normal of their triangle.
+    Flat,
+    /// Replace all normals with the angle weighted average of the triangles that share the vertex's position.\
+    /// Triangles whose normals differ by more than `hard_angle` degrees are not averaged, leaving a hard edge between them.
+    Smooth { hard_angle: f32 },
+}
+
+/// Which way round the triangles of a mesh face the camera.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Winding {
+    /// Keep the triangles as they are in the file.
+    #[default]
+    Keep,
+    /// Make each triangle clockwise as seen from the side it's normals point to, for `FrontFace::Cw` pipelines like `RenderManager`'s.
+    Cw,
+    /// Make each triangle counter-clockwise as seen from the side it's normals point to, as OBJ and glTF files should be.
+    Ccw,
+}
+
+/// The steps `process` runs on a mesh.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MeshOptions {
+    pub normals: NormalMode,
+    /// Generate MikkTSpace tangents, unless every vertex already has one. Needs texture coordinates.
+    pub tangents: bool,
+    /// Reorder the corners of triangles that face the wrong way, after normals are generated.
+    pub winding: Winding,
+}
+
+impl MeshOptions {
+    pub fn with_normals(mut self, normals: NormalMode) -> Self {
+        self.normals = normals;
+        self
+    }
+
+    pub fn with_tangents(mut self, tangents: bool) -> Self {
+        self.tangents = tangents;
+        self
+    }
+
+    pub fn with_winding(mut self, winding: Winding) -> Self {
+        self.winding = winding;
+        self
+    }
+}
+
+/// Generate the normals and tangents of an indexed triangle list, and fix it's winding.\
+/// Vertices are split where their new normals or tangents differ and welded back together where they don't,
+/// so the number and order of the indices is kept and the model's group ranges still apply.
+pub fn process<V: MeshVertex>(mut model: IndexedModel<V>, options: &MeshOptions) -> IndexedModel<V> {
+
+    if !model.indices.len().is_multiple_of(3) {
+        println!("\tMesh has {} indices, which is not a triangle list. Skipping mesh processing.", model.indices.len());
+        return model;
+    }
+
+    // Each corner of each triangle gets it's own vertex until the end.
+    let mut corners: Vec<V> = model.indices.iter().map(|&index| model.vertices[index as usize].clone()).collect();
+
+    match options.normals {
+        NormalMode::Imported => {
+            if corners.iter().any(|corner| corner.normal().is_none()) {
+                smooth_normals(&mut corners, DEFAULT_HARD_ANGLE, true);
+            }
+        },
+        NormalMode::Flat => flat_normals(&mut corners),
+        NormalMode::Smooth { hard_angle } => smooth_normals(&mut corners, hard_angle, false),
+    }
+
+    if options.winding != Winding::Keep {
+        fix_winding(&mut model.indices, &mut corners, options.winding);
+    }
+
+    if options.tangents && corners.iter().any(|corner| corner.tangent().is_none()) {
+        if corners.iter().all(|corner| corner.tex_coord().is_some()) {
+            if !mikktspace::generate_tangents(&mut TangentGeometry(&mut corners)) {
+                println!("\tMikkTSpace failed to generate tangents.");
+            }
+        } else {
+            println!("\tMesh has vertices without texture coordinates. Skipping tangent generation.");
+        }
+    }
+
+    weld(model, corners)
+
+}
+
+/// The unit normal of each triangle of `corners`, or zero for degenerate triangles.
+fn face_normals<V: MeshVertex>(corners: &[V]) -> Vec<Vec3> {
+    corners.chunks_exact(3).map(|face| {
+        let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(face[i].position()));
+        (b - a).cross(c - a).normalize_or_zero()
+    }).collect()
+}
+
+fn flat_normals<V: MeshVertex>(corners: &mut [V]) {
+    let faces = face_normals(corners);
+    for (i, corner) in corners.iter_mut().enumerate() {
+        corner.set_normal(faces[i / 3].into());
+    }
+}
+
+/// Average the normals of the triangles around each position, weighted by the angle of the triangle at that corner.\
+/// If `missing_only` is set, corners that already have a normal keep it.
+fn smooth_normals<V: MeshVertex>(corners: &mut [V], hard_angle: f32, missing_only: bool) {
+    let faces = face_normals(corners);
+    let min_cos = hard_angle.to_radians().cos();
+
+    let angles: Vec<f32> = (0..corners.len()).map(|i| {
+        let face = i - i % 3;
+        let at = Vec3::from(corners[i].position());
+        let next = Vec3::from(corners[face + (i + 1) % 3].position()) - at;
+        let prev = Vec3::from(corners[face + (i + 2) % 3].position()) - at;
+        if faces[i / 3] == Vec3::ZERO || next == Vec3::ZERO || prev == Vec3::ZERO {
+            0.0
+        } else {
+            next.angle_between(prev)
+        }
+    }).collect();
+
+    let mut shared: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+    for (i, corner) in corners.iter().enumerate() {
+        shared.entry(corner.position().map(key_bits)).or_default().push(i);
+    }
+
+    for around in shared.values() {
+        for &i in around {
+            if missing_only && corners[i].normal().is_some() {
+                continue;
+            }
+            let own = faces[i / 3];
+            let normal = around.iter()
+                .filter(|&&j| faces[j / 3].dot(own) >= min_cos)
+                .fold(Vec3::ZERO, |sum, &j| sum + faces[j / 3] * angles[j])
+                .try_normalize()
+                .unwrap_or(own);
+            corners[i].set_normal(normal.into());
+        }
+    }
+}
+
+/// Swap the last 2 corners of each triangle whose counter-clockwise normal disagrees with the winding.\
+/// Triangles are compared to the sum of their corners' normals, so that generated normals (which are counter-clockwise) count as well.
+fn fix_winding<V: MeshVertex>(indices: &mut [u32], corners: &mut [V], winding: Winding) {
+    let faces = face_normals(corners);
+    let mut flipped = 0;
+
+    for (face, normal) in faces.iter().enumerate() {
+        let corner_normals = corners[face * 3..face * 3 + 3].iter()
+            .fold(Vec3::ZERO, |sum, corner| sum + Vec3::from(corner.normal().unwrap_or_default()));
+        let ccw = normal.dot(corner_normals) >= 0.0;
+        if ccw == (winding == Winding::Cw) {
+            indices.swap(face * 3 + 1, face * 3 + 2);
+            corners.swap(face * 3 + 1, face * 3 + 2);
+            flipped += 1;
+        }
+    }
+
+    if flipped > 0 {
+        println!("\tFlipped {} of {} triangles to {:?} winding.", flipped, faces.len(), winding);
+    }
+}
+
+/// Split a planar polygon into triangles, as corners of the polygon in the same winding.\
+/// Convex polygons are split into a fan from the first corner, and concave ones by ear clipping in the polygon's plane.
+pub fn triangulate(points: &[[f32; 3]]) -> Vec<[usize; 3]> {
+    let count = points.len();
+    let fan = |corners: &[usize]| -> Vec<[usize; 3]> {
+        (1..corners.len().saturating_sub(1)).map(|i| [corners[0], corners[i], corners[i + 1]]).collect()
+    };
+    if count <= 3 {
+        return fan(&(0..count).collect::<Vec<_>>());
+    }
+
+    // Newell's method, so the normal follows the winding even for concave polygons
+    let normal = (0..count).fold(Vec3::ZERO, |sum, i| {
+        let (a, b) = (Vec3::from(points[i]), Vec3::from(points[(i + 1) % count]));
+        sum + Vec3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y))
+    });
+    let Some(normal) = normal.try_normalize() else {
+        return fan(&(0..count).collect::<Vec<_>>());
+    };
+
+    // Project into the plane so that the polygon winds counter-clockwise
+    let u = normal.any_orthonormal_vector();
+    let v = normal.cross(u);
+    let flat: Vec<[f32; 2]> = points.iter().map(|&p| [Vec3::from(p).dot(u), Vec3::from(p).dot(v)]).collect();
+    let turn = |a: usize, b: usize, c: usize| {
+        let (a, b, c) = (flat[a], flat[b], flat[c]);
+        (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+    };
+
+    let mut remaining: Vec<usize> = (0..count).collect();
+    if (0..count).all(|i| turn(i, (i + 1) % count, (i + 2) % count) >= 0.0) {
+        return fan(&remaining);
+    }
+
+    let mut triangles = vec![];
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let ear = (0..len).find(|&i| {
+            let (a, b, c) = (remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len]);
+            // Corners on the ear's edges block it too, unless they are duplicates of it's own corners
+            turn(a, b, c) > 0.0 && remaining.iter()
+                .filter(|&&p| ![a, b, c].iter().any(|&q| flat[q] == flat[p]))
+                .all(|&p| turn(a, b, p) < 0.0 || turn(b, c, p) < 0.0 || turn(c, a, p) < 0.0)
+        });
+        match ear {
+            Some(i) => {
+                triangles.push([remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len]]);
+                remaining.remove(i);
+            },
+            // Self-intersecting or degenerate, so there may be no ear left
+            None => break,
+        }
+    }
+    triangles.extend(fan(&remaining));
+    triangles
+}
+
+/// The corners of a triangle list, as MikkTSpace sees them.
+struct TangentGeometry<'a, V: MeshVertex>(&'a mut [V]);
+
+impl<V: MeshVertex> mikktspace::Geometry for TangentGeometry<'_, V> {
+    fn num_faces(&self) -> usize {
+        self.0.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.0[face * 3 + vert].position()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.0[face * 3 + vert].normal().unwrap_or_default()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.0[face * 3 + vert].tex_coord().unwrap_or_default()
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.0[face * 3 + vert].set_tangent(tangent);
+    }
+}
+
+/// The bits of a float for hashing, with `-0.0` and `0.0` the same.
+fn key_bits(value: f32) -> u32 {
+    (value + 0.0).to_bits()
+}
+
+/// A corner's source vertex index, normal bits and tangent bits.
+type WeldKey = (u32, Option<[u32; 3]>, Option<[u32; 4]>);
+
+/// Index the corners again, sharing a vertex between corners that came from the same vertex and ended up with the same normal and tangent.
+fn weld<V: MeshVertex>(model: IndexedModel<V>, corners: Vec<V>) -> IndexedModel<V> {
+    let mut welded: HashMap<WeldKey, u32> = HashMap::new();
+    let mut vertices = vec![];
+
+    let indices = model.indices.iter().zip(corners).map(|(&source, corner)| {
+        let key = (source, corner.normal().map(|n| n.map(key_bits)), corner.tangent().map(|t| t.map(key_bits)));
+        *welded.entry(key).or_insert_with(|| {
+            vertices.push(corner);
+            vertices.len() as u32 - 1
+        })
+    }).collect();
+
+    IndexedModel {
+        vertices,
+        indices,
+        groups: model.groups,
+        materials: model.materials,
+    }
+}
+
+impl MeshVertex for ObjVertex {
+    fn position(&self) -> [f32; 3] {
+        [self.position[0], self.position[1], self.position[2]]
+    }
+
+    fn normal(&self) -> Option<[f32; 3]> {
+        self.normal
+    }
+
+    fn set_normal(&mut self, normal: [f32; 3]) {
+        self.normal = Some(normal);
+    }
+
+    fn tex_coord(&self) -> Option<[f32; 2]> {
+        self.tex_coord.map(|t| [t[0], t[1]])
+    }
+
+    fn tangent(&self) -> Option<[f32; 4]> {
+        self.tangent
+    }
+
+    fn set_tangent(&mut self, tangent: [f32; 4]) {
+        self.tangent = Some(tangent);
+    }
+}
+
+impl MeshVertex for GltfVertex {
+    fn position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    fn normal(&self) -> Option<[f32; 3]> {
+        self.normal
+    }
+
+    fn set_normal(&mut self, normal: [f32; 3]) {
+        self.normal = Some(normal);
+    }
+
+    fn tex_coord(&self) -> Option<[f32; 2]> {
+        self.tex_coord
+    }
+
+    fn tangent(&self) -> Option<[f32; 4]> {
+        self.tangent
+    }
+
+    fn set_tangent(&mut self, tangent: [f32; 4]) {
+        self.tangent = Some(tangent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mtl::MaterialTable;
+
+    fn vertex(position: [f32; 3]) -> GltfVertex {
+        GltfVertex {
+            position,
+            normal: None,
+            tangent: None,
+            tex_coord: None,
+            joints: None,
+            weights: None,
+            primitive: 0,
+        }
+    }
+
+    fn model(vertices: Vec<GltfVertex>, indices: Vec<u32>) -> IndexedModel<GltfVertex> {
+        IndexedModel { vertices, indices, groups: vec![], materials: MaterialTable::default() }
+    }
+
+    /// A cube from -1 to 1 with 8 shared vertices and no normals, it's triangles counter-clockwise seen from outside.
+    fn cube() -> IndexedModel<GltfVertex> {
+        let vertices = (0..8).map(|i| vertex([i & 1, i >> 1 & 1, i >> 2 & 1].map(|bit| bit as f32 * 2.0 - 1.0))).collect();
+        let quads = [[0, 4, 6, 2], [1, 3, 7, 5], [0, 1, 5, 4], [2, 6, 7, 3], [0, 2, 3, 1], [4, 5, 7, 6]];
+        let indices = quads.iter().flat_map(|&[a, b, c, d]| [a, b, c, a, c, d]).collect();
+        model(vertices, indices)
+    }
+
+    /// The counter-clockwise normal of each triangle of `model`.
+    fn triangle_normals(model: &IndexedModel<GltfVertex>) -> Vec<Vec3> {
+        model.indices.chunks_exact(3).map(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(model.vertices[triangle[i] as usize].position));
+            (b - a).cross(c - a).normalize()
+        }).collect()
+    }
+
+    fn normal_of(vertex: &GltfVertex) -> Vec3 {
+        Vec3::from(vertex.normal.expect("Every vertex should have a normal"))
+    }
+
+    #[test]
+    fn flat_normals_split_the_cube_at_every_face() {
+        let processed = process(cube(), &MeshOptions::default().with_normals(NormalMode::Flat));
+        assert_eq!(processed.vertices.len(), 24);
+
+        for (triangle, normal) in processed.indices.chunks_exact(3).zip(triangle_normals(&processed)) {
+            for &index in triangle {
+                let vertex = &processed.vertices[index as usize];
+                assert!(normal_of(vertex).abs_diff_eq(normal, 1e-6), "{:?} should have the normal {}", vertex, normal);
+                assert!(normal.dot(Vec3::from(vertex.position)) > 0.0, "Normal {} points into the cube", normal);
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_normals_average_the_cube_corners() {
+        let processed = process(cube(), &MeshOptions::default().with_normals(NormalMode::Smooth { hard_angle: 180.0 }));
+        assert_eq!(processed.vertices.len(), 8);
+
+        for vertex in &processed.vertices {
+            let diagonal = Vec3::from(vertex.position).normalize();
+            assert!(normal_of(vertex).abs_diff_eq(diagonal, 1e-6), "{:?} should have the normal {}", vertex, diagonal);
+        }
+    }
+
+    #[test]
+    fn hard_angle_splits_vertices_at_sharper_edges() {
+        // The faces of a cube meet at 90 degrees
+        let sharp = process(cube(), &MeshOptions::default().with_normals(NormalMode::Smooth { hard_angle: 89.0 }));
+        assert_eq!(sharp.vertices.len(), 24);
+        for (triangle, normal) in sharp.indices.chunks_exact(3).zip(triangle_normals(&sharp)) {
+            assert!(triangle.iter().all(|&index| normal_of(&sharp.vertices[index as usize]).abs_diff_eq(normal, 1e-6)));
+        }
+
+        let smooth = process(cube(), &MeshOptions::default().with_normals(NormalMode::Smooth { hard_angle: 91.0 }));
+        assert_eq!(smooth.vertices.len(), 8);
+
+        // Missing normals are filled in with `DEFAULT_HARD_ANGLE`, which is below 90 degrees
+        let imported = process(cube(), &MeshOptions::default());
+        assert_eq!(imported.vertices.len(), 24);
+    }
+
+    #[test]
+    fn welding_keeps_the_index_count_and_order() {
+        let original = cube();
+        let processed = process(cube(), &MeshOptions::default().with_normals(NormalMode::Flat));
+
+        assert_eq!(processed.indices.len(), original.indices.len());
+        for (&before, &after) in original.indices.iter().zip(&processed.indices) {
+            assert_eq!(processed.vertices[after as usize].position, original.vertices[before as usize].position);
+        }
+
+        // Vertices that keep their normals are shared again just as they were
+        let shared = process(model(processed.vertices.clone(), processed.indices.clone()), &MeshOptions::default());
+        assert_eq!(shared.vertices.len(), processed.vertices.len());
+        assert_eq!(shared.indices, processed.indices);
+    }
+
+    #[test]
+    fn cw_winding_flips_ccw_faces() {
+        let original = cube();
+        let options = MeshOptions::default().with_normals(NormalMode::Flat);
+
+        let cw = process(cube(), &options.with_winding(Winding::Cw));
+        assert_eq!(cw.indices.len(), original.indices.len());
+        for ((flipped, triangle), normal) in cw.indices.chunks_exact(3).zip(original.indices.chunks_exact(3)).zip(triangle_normals(&cw)) {
+            let position = |index: u32| cw.vertices[index as usize].position;
+            let source = |index: u32| original.vertices[index as usize].position;
+            assert_eq!(flipped.iter().map(|&i| position(i)).collect::<Vec<_>>(), [triangle[0], triangle[2], triangle[1]].map(source));
+
+            // The normals still point out of the cube, and the triangles are now clockwise around them
+            let vertex = &cw.vertices[flipped[0] as usize];
+            assert!(normal_of(vertex).dot(Vec3::from(vertex.position)) > 0.0);
+            assert!(normal.dot(normal_of(vertex)) < 0.0);
+        }
+
+        let ccw = process(cube(), &options.with_winding(Winding::Ccw));
+        for (&before, &after) in original.indices.iter().zip(&ccw.indices) {
+            assert_eq!(ccw.vertices[after as usize].position, original.vertices[before as usize].position);
+        }
+
+        // Imported normals decide which way a triangle faces
+        let mut facing_down = model((0..3).map(|i| vertex([i as f32 % 2.0, (i / 2) as f32, 0.0])).collect(), vec![0, 1, 2]);
+        for vertex in &mut facing_down.vertices {
+            vertex.normal = Some([0.0, 0.0, -1.0]);
+        }
+        let positions = |winding: Winding| {
+            let processed = process(model(facing_down.vertices.clone(), vec![0, 1, 2]), &MeshOptions::default().with_winding(winding));
+            processed.indices.iter().map(|&i| processed.vertices[i as usize].position).collect::<Vec<_>>()
+        };
+        let corner = |i: usize| facing_down.vertices[i].position;
+        assert_eq!(positions(Winding::Ccw), [0, 2, 1].map(corner));
+        assert_eq!(positions(Winding::Cw), [0, 1, 2].map(corner));
+    }
+
+    #[test]
+    fn tangents_follow_the_texture_coordinates() {
+        // A quad facing +Z, with U along +X and V along +Y
+        let quad: Vec<GltfVertex> = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]].iter().map(|&[x, y]| GltfVertex {
+            normal: Some([0.0, 0.0, 1.0]),
+            tex_coord: Some([x, y]),
+            ..vertex([x, y, 0.0])
+        }).collect();
+        let textured = model(quad, vec![0, 1, 2, 0, 2, 3]);
+
+        let processed = process(model(textured.vertices.clone(), textured.indices.clone()), &MeshOptions::default().with_tangents(true));
+        assert_eq!(processed.vertices.len(), 4);
+        assert_eq!(processed.indices, textured.indices);
+        for vertex in &processed.vertices {
+            let tangent = vertex.tangent.expect("Every vertex should have a tangent");
+            assert!(Vec3::from_slice(&tangent[..3]).abs_diff_eq(Vec3::X, 1e-5), "{:?} should have the tangent +X", tangent);
+            assert_eq!(tangent[3], 1.0);
+        }
+
+        // Flipping V flips the bitangent
+        let mut mirrored = model(textured.vertices.clone(), textured.indices.clone());
+        for vertex in &mut mirrored.vertices {
+            vertex.tex_coord = vertex.tex_coord.map(|[u, v]| [u, 1.0 - v]);
+        }
+        let processed = process(mirrored, &MeshOptions::default().with_tangents(true));
+        assert!(processed.vertices.iter().all(|vertex| vertex.tangent.is_some_and(|tangent| tangent[3] == -1.0)));
+
+        // Without texture coordinates there is nothing to follow
+        let mut untextured = textured;
+        untextured.vertices[0].tex_coord = None;
+        let processed = process(untextured, &MeshOptions::default().with_tangents(true));
+        assert!(processed.vertices.iter().all(|vertex| vertex.tangent.is_none()));
+    }
+
+    /// The area of a triangle, positive if it winds counter-clockwise around `normal`.
+    fn signed_area(points: &[[f32; 3]], triangle: [usize; 3], normal: Vec3) -> f32 {
+        let [a, b, c] = triangle.map(|i| Vec3::from(points[i]));
+        (b - a).cross(c - a).dot(normal) * 0.5
+    }
+
+    /// Check that the triangles use each corner of the polygon, wind the same way and cover `area`.
+    fn assert_covers(points: &[[f32; 3]], triangles: &[[usize; 3]], normal: Vec3, area: f32) {
+        assert_eq!(triangles.len(), points.len() - 2);
+        assert!(triangles.iter().flatten().all(|&i| i < points.len()));
+        assert!((0..points.len()).all(|i| triangles.iter().flatten().any(|&j| i == j)), "Every corner should be used: {:?}", triangles);
+
+        for &triangle in triangles {
+            assert!(signed_area(points, triangle, normal) > 0.0, "Triangle {:?} is flipped or empty", triangle);
+        }
+        let total: f32 = triangles.iter().map(|&triangle| signed_area(points, triangle, normal)).sum();
+        assert!((total - area).abs() < 1e-5, "The triangles cover {} instead of {}", total, area);
+    }
+
+    #[test]
+    fn quads_are_split_in_two() {
+        let quad = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+        assert_covers(&quad, &triangulate(&quad), Vec3::Z, 1.0);
+        assert_eq!(triangulate(&quad[..3]), [[0, 1, 2]]);
+        assert!(triangulate(&quad[..2]).is_empty());
+    }
+
+    #[test]
+    fn concave_polygons_are_ear_clipped() {
+        // An L with the reflex corner at (1, 1), which a fan from the first corner would cover
+        let l_shape = [[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [2.0, 1.0, 0.0], [1.0, 1.0, 0.0], [1.0, 2.0, 0.0], [0.0, 2.0, 0.0]];
+        let triangles = triangulate(&l_shape);
+        assert_covers(&l_shape, &triangles, Vec3::Z, 3.0);
+
+        for triangle in triangles {
+            let center = triangle.iter().fold(Vec3::ZERO, |sum, &i| sum + Vec3::from(l_shape[i])) / 3.0;
+            assert!(center.x < 1.0 || center.y < 1.0, "Triangle {:?} is outside the L", triangle);
+        }
+    }
+
+    #[test]
+    fn clockwise_polygons_keep_their_winding() {
+        // A hexagon in the XZ plane, clockwise seen from +Y
+        let hexagon: Vec<[f32; 3]> = (0..6).map(|i| {
+            let angle = -(i as f32) * std::f32::consts::TAU / 6.0;
+            [angle.cos(), 0.0, -angle.sin()]
+        }).collect();
+        assert_covers(&hexagon, &triangulate(&hexagon), -Vec3::Y, 1.5 * 3f32.sqrt());
+
+        // The L again, clockwise and tilted out of the axis planes
+        let tilt = glam::Quat::from_rotation_x(0.5) * glam::Quat::from_rotation_y(0.3);
+        let l_shape: Vec<[f32; 3]> = [[0.0, 0.0], [0.0, 2.0], [1.0, 2.0], [1.0, 1.0], [2.0, 1.0], [2.0, 0.0]].iter()
+            .map(|&[x, y]| (tilt * Vec3::new(x, y, 0.0)).into())
+            .collect();
+        assert_covers(&l_shape, &triangulate(&l_shape), tilt * -Vec3::Z, 3.0);
+    }
+
+    #[test]
+    fn degenerate_polygons_still_give_triangles() {
+        // A corner in the middle of an edge
+        let square = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0], [2.0, 2.0, 0.0], [0.0, 2.0, 0.0]];
+        let triangles = triangulate(&square);
+        assert_eq!(triangles.len(), 3);
+        let total: f32 = triangles.iter().map(|&triangle| signed_area(&square, triangle, Vec3::Z)).sum();
+        assert!((total - 4.0).abs() < 1e-5);
+        assert!(triangles.iter().all(|&triangle| signed_area(&square, triangle, Vec3::Z) >= 0.0));
+
+        // All on one line, so there is no plane to project into
+        let line = [[0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [2.0, 2.0, 2.0], [3.0, 3.0, 3.0]];
+        let triangles = triangulate(&line);
+        assert_eq!(triangles.len(), 2);
+        assert!(triangles.iter().flatten().all(|&i| i < line.len()));
+
+        // Repeated corners
+        let repeated = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+        let triangles = triangulate(&repeated);
+        assert_eq!(triangles.len(), 3);
+        let total: f32 = triangles.iter().map(|&triangle| signed_area(&repeated, triangle, Vec3::Z)).sum();
+        assert!((total - 1.0).abs() < 1e-5);
+    }
+}
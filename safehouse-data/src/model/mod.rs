@@ -1 +1,5 @@
-pub mod obj;
\ No newline at end of file
+pub mod obj;
+pub mod mtl;
+pub mod gltf;
+pub mod mesh;
+pub mod container;
@@ -0,0 +1,312 @@
+use std::{fs, io, path::{Path, PathBuf}, rc::Rc};
+
+use safehouse_render::{gpu::{self, texture::{sampler::TextureSampler, Texture}}, material::{GroupMaterial, MaterialTexture}, SafehouseError, SafehouseResult};
+
+/// An error while loading a material library.
+#[derive(Debug)]
+pub enum MtlError {
+    /// An OBJ file or material library could not be read.
+    Io { path: PathBuf, error: io::Error },
+
+    /// A statement could not be parsed.
+    Parse { path: PathBuf, line: usize, message: String },
+}
+
+impl std::fmt::Display for MtlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MtlError::Io { path, error } => write!(f, "Could not read '{}': {}", path.display(), error),
+            MtlError::Parse { path, line, message } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for MtlError {}
+
+/// A material from an MTL file. Texture paths are resolved relative to the MTL file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    /// `Ka`
+    pub ambient: [f32; 3],
+    /// `Kd`
+    pub diffuse: [f32; 3],
+    /// `Ks`
+    pub specular: [f32; 3],
+    /// `Ns`
+    pub shininess: f32,
+    /// `d`, or `1 - Tr` if the material only has `Tr`.
+    pub dissolve: f32,
+    /// `map_Kd`
+    pub diffuse_map: Option<PathBuf>,
+    /// `map_Bump` or `bump`
+    pub bump_map: Option<PathBuf>,
+}
+
+impl ObjMaterial {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            ambient: [1.0; 3],
+            diffuse: [1.0; 3],
+            specular: [0.0; 3],
+            shininess: 0.0,
+            dissolve: 1.0,
+            diffuse_map: None,
+            bump_map: None,
+        }
+    }
+
+    /// The colors of this material as a uniform block, for `GroupMaterial::new` or `Material::new`.
+    pub fn params(&self) -> MtlParams {
+        MtlParams {
+            diffuse: self.diffuse,
+            dissolve: self.dissolve,
+            ambient: self.ambient,
+            shininess: self.shininess,
+            specular: self.specular,
+            _pad: 0.0,
+        }
+    }
+
+    /// A `GroupMaterial` with this material's `params`, and it's `map_Kd` texture in slot 0, or a white texture if it has none.\
+    /// Every OBJ material has the same layout, `MaterialLayout::new::<MtlParams>(1)`, so one pipeline can draw all of them.
+    pub fn group_material(&self, state: &gpu::State, sampler: &Rc<TextureSampler>) -> SafehouseResult<GroupMaterial> {
+        let texture = match &self.diffuse_map {
+            Some(path) => {
+                let image = gpu::image::open(path).map_err(|e| SafehouseError::Decode(format!("'{}': {}", path.display(), e)))?;
+                Texture::from_rgba8(state, &image.to_rgba8())
+            },
+            None => Texture::solid(state, [255, 255, 255, 255]),
+        };
+
+        Ok(GroupMaterial::new(&self.name, self.params(), vec![MaterialTexture {
+            texture: Rc::new(texture),
+            sampler: Rc::clone(sampler),
+        }]))
+    }
+}
+
+/// The uniform block of an `ObjMaterial`, laid out like this WGSL struct:
+/// ```wgsl
+/// struct MtlParams {
+///     diffuse: vec3<f32>,
+///     dissolve: f32,
+///     ambient: vec3<f32>,
+///     shininess: f32,
+///     specular: vec3<f32>,
+/// }
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MtlParams {
+    pub diffuse: [f32; 3],
+    pub dissolve: f32,
+    pub ambient: [f32; 3],
+    pub shininess: f32,
+    pub specular: [f32; 3],
+    pub _pad: f32,
+}
+
+/// The materials of every library an OBJ file uses, which it's groups refer to by index.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaterialTable {
+    pub materials: Vec<ObjMaterial>,
+}
+
+impl MaterialTable {
+    /// Load the `mtllib` libraries of an OBJ file, relative to the directory of `obj_path`.\
+    /// If several libraries define a material with the same name, the first one is used.
+    pub fn load(obj_path: impl AsRef<Path>, libraries: &[String]) -> Result<Self, MtlError> {
+        let dir = obj_path.as_ref().parent().unwrap_or(Path::new(""));
+        let mut table = Self::default();
+        for library in libraries {
+            println!("\tMaterial Library: \"{}\"", library);
+            for material in parse_mtl_file(dir.join(library))? {
+                if table.index_of(&material.name).is_none() {
+                    table.materials.push(material);
+                }
+            }
+        }
+        Ok(table)
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.materials.iter().position(|material| material.name == name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ObjMaterial> {
+        self.index_of(name).map(|i| &self.materials[i])
+    }
+
+    /// Every texture the materials use, e.g. for `cargo:rerun-if-changed`.
+    pub fn texture_paths(&self) -> impl Iterator<Item = &Path> {
+        self.materials.iter()
+            .flat_map(|material| [material.diffuse_map.as_deref(), material.bump_map.as_deref()])
+            .flatten()
+    }
+}
+
+/// Parse an MTL file, resolving texture paths relative to it.
+pub fn parse_mtl_file(path: impl AsRef<Path>) -> Result<Vec<ObjMaterial>, MtlError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|error| MtlError::Io { path: path.to_path_buf(), error })?;
+    parse_mtl(&source, path)
+}
+
+/// Parse the source of an MTL file at `path`. Unsupported statements are ignored.
+pub fn parse_mtl(source: &str, path: &Path) -> Result<Vec<ObjMaterial>, MtlError> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut materials: Vec<ObjMaterial> = vec![];
+    // Only `Tr` sets the dissolve if the material has no `d`
+    let mut has_dissolve = false;
+
+    for (i, line) in source.lines().enumerate() {
+        let error = |message: String| MtlError::Parse { path: path.to_path_buf(), line: i + 1, message };
+
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut words = line.split_whitespace();
+        let Some(statement) = words.next() else {
+            continue;
+        };
+        let args: Vec<&str> = words.collect();
+
+        if statement == "newmtl" {
+            if args.is_empty() {
+                return Err(error(String::from("newmtl has no name")));
+            }
+            materials.push(ObjMaterial::new(&args.join(" ")));
+            has_dissolve = false;
+            continue;
+        }
+
+        let material = materials.last_mut().ok_or_else(|| error(format!("'{}' before any newmtl", statement)))?;
+        match statement {
+            "Ka" => material.ambient = parse_color(&args).map_err(error)?,
+            "Kd" => material.diffuse = parse_color(&args).map_err(error)?,
+            "Ks" => material.specular = parse_color(&args).map_err(error)?,
+            "Ns" => material.shininess = parse_float(&args).map_err(error)?,
+            "d" => {
+                material.dissolve = parse_float(&args).map_err(error)?;
+                has_dissolve = true;
+            },
+            "Tr" => {
+                let transparency = parse_float(&args).map_err(error)?;
+                if !has_dissolve {
+                    material.dissolve = 1.0 - transparency;
+                }
+            },
+            "map_Kd" => material.diffuse_map = Some(dir.join(parse_map_path(&args).map_err(error)?)),
+            "map_Bump" | "map_bump" | "bump" => material.bump_map = Some(dir.join(parse_map_path(&args).map_err(error)?)),
+            _ => {},
+        }
+    }
+
+    Ok(materials)
+}
+
+fn parse_float(args: &[&str]) -> Result<f32, String> {
+    let arg = args.first().ok_or_else(|| String::from("missing value"))?;
+    arg.parse().map_err(|_| format!("'{}' is not a number", arg))
+}
+
+/// An `r g b` color, where `g` and `b` default to `r`. Spectral and XYZ colors are not supported.
+fn parse_color(args: &[&str]) -> Result<[f32; 3], String> {
+    if matches!(args.first(), Some(&"spectral") | Some(&"xyz")) {
+        return Err(format!("'{}' colors are not supported", args[0]));
+    }
+    let r = parse_float(args)?;
+    let g = if args.len() > 1 { parse_float(&args[1..])? } else { r };
+    let b = if args.len() > 2 { parse_float(&args[2..])? } else { r };
+    Ok([r, g, b])
+}
+
+/// The file name of a texture map statement, after any `-option value...` arguments.
+fn parse_map_path(args: &[&str]) -> Result<String, String> {
+    let mut i = 0;
+    while i < args.len() && args[i].starts_with('-') {
+        let values = match args[i] {
+            "-blendu" | "-blendv" | "-cc" | "-clamp" | "-bm" | "-boost" | "-texres" | "-imfchan" | "-type" => 1,
+            "-mm" => 2,
+            // Up to 3 numbers
+            "-o" | "-s" | "-t" => args[i + 1..].iter().take(3).take_while(|arg| arg.parse::<f32>().is_ok()).count(),
+            option => return Err(format!("unknown texture option '{}'", option)),
+        };
+        i += 1 + values;
+    }
+    if i >= args.len() {
+        return Err(String::from("missing texture file name"));
+    }
+    Ok(args[i..].join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Vec<ObjMaterial>, MtlError> {
+        parse_mtl(source, Path::new("models/crate.mtl"))
+    }
+
+    fn parse_error_line(source: &str) -> (usize, String) {
+        match parse(source) {
+            Err(MtlError::Parse { path, line, message }) => {
+                assert_eq!(path, Path::new("models/crate.mtl"));
+                (line, message)
+            },
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn colors_are_parsed() {
+        let materials = parse("newmtl wood # a comment\nKa 0.1 0.2 0.3\nKd 0.5\nKs 1 0\nNs 32\n").unwrap();
+        assert_eq!(materials.len(), 1);
+        assert_eq!(materials[0].name, "wood");
+        assert_eq!(materials[0].ambient, [0.1, 0.2, 0.3]);
+        // A single value is used for every channel, and missing channels default to the first
+        assert_eq!(materials[0].diffuse, [0.5, 0.5, 0.5]);
+        assert_eq!(materials[0].specular, [1.0, 0.0, 1.0]);
+        assert_eq!(materials[0].shininess, 32.0);
+    }
+
+    #[test]
+    fn dissolve_takes_precedence_over_transparency() {
+        let materials = parse("newmtl tr\nTr 0.25\nnewmtl d_first\nd 0.5\nTr 0.25\nnewmtl tr_first\nTr 0.25\nd 0.5\nnewmtl opaque\n").unwrap();
+        let dissolves: Vec<f32> = materials.iter().map(|material| material.dissolve).collect();
+        assert_eq!(dissolves, [0.75, 0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn texture_maps_skip_options_and_are_relative_to_the_library() {
+        let materials = parse("newmtl crate\nmap_Kd -o 0.5 0.5 0 -clamp on textures/crate diffuse.png\nbump -bm 2 crate_bump.png\nnewmtl other\nmap_Bump -s 2 normal.png\n").unwrap();
+        assert_eq!(materials[0].diffuse_map.as_deref(), Some(Path::new("models/textures/crate diffuse.png")));
+        assert_eq!(materials[0].bump_map.as_deref(), Some(Path::new("models/crate_bump.png")));
+        assert_eq!(materials[1].diffuse_map, None);
+        assert_eq!(materials[1].bump_map.as_deref(), Some(Path::new("models/normal.png")));
+
+        // `-o` takes up to 3 numbers, so a file name after one offset isn't skipped
+        let materials = parse("newmtl short\nmap_Kd -o 0.5 offset.png\n").unwrap();
+        assert_eq!(materials[0].diffuse_map.as_deref(), Some(Path::new("models/offset.png")));
+    }
+
+    #[test]
+    fn statements_before_newmtl_are_errors() {
+        let (line, message) = parse_error_line("# header\n\nKd 1 1 1\nnewmtl late\n");
+        assert_eq!(line, 3);
+        assert!(message.contains("before any newmtl"), "{}", message);
+    }
+
+    #[test]
+    fn malformed_values_are_errors() {
+        let (line, message) = parse_error_line("newmtl bad\nKd 1 one 1\n");
+        assert_eq!(line, 2);
+        assert_eq!(message, "'one' is not a number");
+
+        assert_eq!(parse_error_line("newmtl bad\nNs\n").1, "missing value");
+        assert_eq!(parse_error_line("newmtl\n").1, "newmtl has no name");
+        assert_eq!(parse_error_line("newmtl bad\nmap_Kd -o 1 2 3\n").1, "missing texture file name");
+        assert_eq!(parse_error_line("newmtl bad\nmap_Kd -unknown x.png\n").1, "unknown texture option '-unknown'");
+        assert!(parse_error_line("newmtl bad\nKd spectral file.rfl\n").1.contains("not supported"));
+    }
+}
@@ -1,101 +1,266 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, fs, io, ops::Range, path::{Path, PathBuf}, rc::Rc};
 
-use obj::{raw::parse_obj, Position};
-use safehouse_render::vertex_type::AdvVertex;
+use obj::raw::parse_obj;
+use safehouse_render::{gpu::{self, texture::sampler::TextureSampler}, material::GroupMaterial, vertex_type::{AdvVertex, TangentVertex}, SafehouseResult};
 
-pub fn build_obj_adv(objfile_data: &'static [u8]) -> Vec<AdvVertex> {
+use super::{mesh::{process, triangulate, MeshOptions}, mtl::{MaterialTable, MtlError}};
 
-    let bunny = parse_obj(objfile_data).unwrap();
+/// An error while loading an OBJ file.
+#[derive(Debug)]
+pub enum ObjLoadError {
+    /// The OBJ file could not be read.
+    Io { path: PathBuf, error: io::Error },
 
-    bunny.material_libraries.iter().for_each(|x| println!("\tMaterial Library: \"{}\"", x));
+    /// The OBJ file could not be parsed.
+    Parse(obj::ObjError),
+
+    /// A material library could not be loaded.
+    Material(MtlError),
+
+    /// A polygon refers to a position, texture coordinate or normal past the end of the file's list.
+    IndexOutOfRange { polygon: usize, attribute: &'static str, index: usize, len: usize },
+
+    /// A polygon has less than 3 vertices.
+    TooFewVertices { polygon: usize, vertices: usize },
+}
+
+impl std::fmt::Display for ObjLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjLoadError::Io { path, error } => write!(f, "'{}': {}", path.display(), error),
+            ObjLoadError::Parse(error) => write!(f, "Could not parse OBJ: {}", error),
+            ObjLoadError::Material(error) => write!(f, "{}", error),
+            ObjLoadError::IndexOutOfRange { polygon, attribute, index, len } => write!(f, "Polygon {} uses {} {} but there are {}", polygon, attribute, index + 1, len),
+            ObjLoadError::TooFewVertices { polygon, vertices } => write!(f, "Polygon {} has {} vertices", polygon, vertices),
+        }
+    }
+}
+
+impl std::error::Error for ObjLoadError {}
+
+impl From<MtlError> for ObjLoadError {
+    fn from(error: MtlError) -> Self {
+        ObjLoadError::Material(error)
+    }
+}
+
+/// Build an OBJ file into unindexed `AdvVertex`es. Missing normals are generated with `MeshOptions::default()`.
+pub fn build_obj_adv(objfile_data: &'static [u8]) -> Result<Vec<AdvVertex>, ObjLoadError> {
+
+    Ok(load_obj(objfile_data, &MeshOptions::default())?
+        .map(ObjVertex::adv)
+        .expand())
+
+}
 
-    let size = 0usize; 
+/// Build an OBJ file into an unindexed triangle list, in the order of it's `g` groups. Polygons are triangulated.
+pub fn build_obj<V>(
+    objfile_data: &'static [u8],
+    polygon_f: &dyn Fn(
+        &(f32,f32,f32,f32),
+        Option<&(f32,f32,f32)>,
+        Option<&(f32,f32,f32)>,
+        usize,
+    ) -> V
+) -> Result<Vec<V>, ObjLoadError> {
 
-    let posns = &bunny.positions;
-    let tcoords = &bunny.tex_coords;
-    let normals = &bunny.normals;
+    let bunny = parse_obj(objfile_data).map_err(ObjLoadError::Parse)?;
+
+    bunny.material_libraries.iter().for_each(|x| println!("\tMaterial Library: \"{}\"", x));
 
     let mut vertices = vec![];
 
-    for (gid, (group_name, group)) in bunny.groups.iter().enumerate() {
+    for (gid, (_group_name, group)) in bunny.groups.iter().enumerate() {
         for range in &group.polygons {
-
-            for poly in &bunny.polygons[range.start..range.end] {
-
-                let mut polygon = vec![];
-
-                match poly {
-                    obj::raw::object::Polygon::P(p) => {
-                        for pi in p.as_slice() {
-                            let pos = &posns[pi.clone()];
-                            polygon.push(AdvVertex{
-                                pos: [pos.0, pos.1, pos.2, pos.3],
-                                normal: [0.0, 0.0, 0.0],
-                                texcoord: [0.0, 0.0, 0.0],
-                                group_id: gid as u32,
-                                bone_id: 0,
-                            });
-                        }                    
-                    },
-                    obj::raw::object::Polygon::PT(p) => {
-                        for pi in p.as_slice() {
-                            let pos = &posns[pi.0];
-                            let tex = &tcoords[pi.1];
-                            polygon.push(AdvVertex{
-                                pos: [pos.0, pos.1, pos.2, pos.3],
-                                normal: [0.0, 0.0, 0.0],
-                                texcoord: [tex.0, tex.1, tex.2],
-                                group_id: gid as u32,
-                                bone_id: 0,
-                            });
-                        }   
-                    },
-                    obj::raw::object::Polygon::PN(p) => {
-                        for pi in p.as_slice() {
-                            let pos = &posns[pi.0];
-                            let nor = &normals[pi.1];
-                            polygon.push(AdvVertex{
-                                pos: [pos.0, pos.1, pos.2, pos.3],
-                                normal: [nor.0, nor.1, nor.2],
-                                texcoord: [0.0, 0.0, 0.0],
-                                group_id: gid as u32,
-                                bone_id: 0,
-                            });
-                        }   
-
-                    },
-                    obj::raw::object::Polygon::PTN(p) => {
-                        for pi in p.as_slice() {
-                            let pos = &posns[pi.0];
-                            let tex = &tcoords[pi.1];
-                            let nor = &normals[pi.2];
-                            polygon.push(AdvVertex{
-                                pos: [pos.0, pos.1, pos.2, pos.3],
-                                normal: [nor.0, nor.1, nor.2],
-                                texcoord: [tex.0, tex.1, tex.2],
-                                group_id: gid as u32,
-                                bone_id: 0,
-                            });
-                        }   
-
-
-                    },
+            for i in range.start..range.end {
+
+                let corners = polygon_corners(&bunny, i)?;
+                for triangle in polygon_triangles(&bunny, &corners) {
+                    vertices.extend(triangle.map(|corner| {
+                        let (p, t, n) = corners[corner];
+                        polygon_f(
+                            &bunny.positions[p],
+                            t.map(|t| &bunny.tex_coords[t]),
+                            n.map(|n| &bunny.normals[n]),
+                            gid
+                        )
+                    }));
                 }
 
-                vertices.append(&mut polygon);
-
             }
+        }
+
+        // Textures are per-material groups, see `build_obj_indexed`
+
+    }
+
+    Ok(vertices)
 
+}
+
+/// A model with deduplicated vertices, and a triangle list of indices into them.
+pub struct IndexedModel<V> {
+    pub vertices: Vec<V>,
+    pub indices: Vec<u32>,
+    /// The range of indices drawn with each material, in the order of `ModelData::groups`.
+    pub groups: Vec<MaterialGroup>,
+    /// The materials of the OBJ file's libraries, if they were loaded with `build_obj_file`.
+    pub materials: MaterialTable,
+}
+
+/// The polygons of an OBJ file that share a `usemtl` material, or a primitive of a glTF mesh.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaterialGroup {
+    /// The material name, or `None` for polygons before any `usemtl`.
+    pub material: Option<String>,
+    /// The index of the material in `IndexedModel::materials`, if it was found in a library.\
+    /// For glTF meshes, this is the index in `GltfModel::materials` instead.
+    pub material_index: Option<usize>,
+    pub indices: Range<u32>,
+}
+
+impl<V> IndexedModel<V> {
+    /// The index ranges of the groups, for `ModelData::new`.
+    pub fn group_ranges(&self) -> Vec<Range<u32>> {
+        self.groups.iter().map(|group| group.indices.clone()).collect()
+    }
+
+    /// The `GroupMaterial` of each group's MTL material, for `ModelData::with_group_materials`.\
+    /// Groups whose material isn't in `materials` get `None`, and are drawn with their object's material. glTF groups refer to `GltfModel::materials` instead, so they all get `None`.
+    pub fn group_materials(&self, state: &gpu::State, sampler: &Rc<TextureSampler>) -> SafehouseResult<Vec<Option<GroupMaterial>>> {
+        self.groups.iter()
+            .map(|group| group.material_index
+                .and_then(|i| self.materials.materials.get(i))
+                .map(|material| material.group_material(state, sampler))
+                .transpose())
+            .collect()
+    }
+
+    /// The vertex of each index, for drawing without an index buffer. The groups' ranges still apply.
+    pub fn expand(&self) -> Vec<V> where V: Clone {
+        self.indices.iter().map(|&index| self.vertices[index as usize].clone()).collect()
+    }
+
+    /// Convert each vertex, keeping the indices, groups and materials.
+    pub fn map<U>(self, vertex_f: impl Fn(&V) -> U) -> IndexedModel<U> {
+        IndexedModel {
+            vertices: self.vertices.iter().map(vertex_f).collect(),
+            indices: self.indices,
+            groups: self.groups,
+            materials: self.materials,
+        }
+    }
+}
+
+/// The attributes of an OBJ vertex, for processing with `mesh::process` before building the final vertex type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObjVertex {
+    pub position: [f32; 4],
+    pub tex_coord: Option<[f32; 3]>,
+    pub normal: Option<[f32; 3]>,
+    /// Only set by `mesh::process`.
+    pub tangent: Option<[f32; 4]>,
+    /// The index of the vertex's `g` group.
+    pub group: usize,
+}
+
+impl ObjVertex {
+    pub fn new(position: &(f32,f32,f32,f32), tex_coord: Option<&(f32,f32,f32)>, normal: Option<&(f32,f32,f32)>, group: usize) -> Self {
+        Self {
+            position: (*position).into(),
+            tex_coord: tex_coord.map(|t| (*t).into()),
+            normal: normal.map(|n| (*n).into()),
+            tangent: None,
+            group,
         }
+    }
 
-        // Pack textures for this group
+    pub fn adv(&self) -> AdvVertex {
+        AdvVertex {
+            pos: self.position,
+            texcoord: self.tex_coord.unwrap_or_default(),
+            normal: self.normal.unwrap_or_default(),
+            group_id: self.group as u32,
+            bone_id: 0,
+        }
     }
 
-    vertices
+    /// A `TangentVertex`. Load the model with tangents first, or the tangent is +X.
+    pub fn tangent_vertex(&self) -> TangentVertex {
+        TangentVertex {
+            pos: self.position,
+            normal: self.normal.unwrap_or_default(),
+            tex_coord: self.tex_coord.map_or([0.0; 2], |t| [t[0], t[1]]),
+            tangent: self.tangent.unwrap_or([1.0, 0.0, 0.0, 1.0]),
+        }
+    }
+}
+
+/// The polygons of each `usemtl` material, sorted by material name.\
+/// Polygons that no material covers are put in a trailing group without a material.
+fn material_polygons(obj: &obj::raw::RawObj) -> Vec<(Option<String>, Vec<usize>)> {
+    let mut covered = vec![false; obj.polygons.len()];
+    let mut meshes: Vec<_> = obj.meshes.iter().collect();
+    meshes.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut groups = vec![];
+    for (name, mesh) in meshes {
+        let polygons: Vec<usize> = mesh.polygons.iter()
+            .flat_map(|range| range.start..range.end)
+            .filter(|&i| !std::mem::replace(&mut covered[i], true))
+            .collect();
+        if !polygons.is_empty() {
+            groups.push(((!name.is_empty()).then(|| name.clone()), polygons));
+        }
+    }
 
+    let rest: Vec<usize> = (0..obj.polygons.len()).filter(|&i| !covered[i]).collect();
+    if !rest.is_empty() {
+        groups.push((None, rest));
+    }
+    groups
 }
 
-pub fn build_obj<V>(
+/// The position, texture coordinate and normal indices of a polygon's vertex.
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// The corners of a polygon, checked against the file's lists.
+fn polygon_corners(obj: &obj::raw::RawObj, polygon: usize) -> Result<Vec<Corner>, ObjLoadError> {
+    let corners: Vec<Corner> = match &obj.polygons[polygon] {
+        obj::raw::object::Polygon::P(p) => p.iter().map(|pi| (*pi, None, None)).collect(),
+        obj::raw::object::Polygon::PT(p) => p.iter().map(|pi| (pi.0, Some(pi.1), None)).collect(),
+        obj::raw::object::Polygon::PN(p) => p.iter().map(|pi| (pi.0, None, Some(pi.1))).collect(),
+        obj::raw::object::Polygon::PTN(p) => p.iter().map(|pi| (pi.0, Some(pi.1), Some(pi.2))).collect(),
+    };
+
+    if corners.len() < 3 {
+        return Err(ObjLoadError::TooFewVertices { polygon, vertices: corners.len() });
+    }
+
+    let check = |attribute: &'static str, index: Option<usize>, len: usize| match index {
+        Some(index) if index >= len => Err(ObjLoadError::IndexOutOfRange { polygon, attribute, index, len }),
+        _ => Ok(()),
+    };
+    for &(p, t, n) in &corners {
+        check("position", Some(p), obj.positions.len())?;
+        check("texture coordinate", t, obj.tex_coords.len())?;
+        check("normal", n, obj.normals.len())?;
+    }
+
+    Ok(corners)
+}
+
+/// Triangulate a polygon by it's positions, see `mesh::triangulate`.
+fn polygon_triangles(obj: &obj::raw::RawObj, corners: &[Corner]) -> Vec<[usize; 3]> {
+    let points: Vec<[f32; 3]> = corners.iter().map(|&(p, _, _)| {
+        let (x, y, z, _) = obj.positions[p];
+        [x, y, z]
+    }).collect();
+    triangulate(&points)
+}
+
+/// Like `build_obj`, but vertices that share the same position, texture coordinate, normal and group are only emitted once.
+pub fn build_obj_indexed<V>(
     objfile_data: &'static [u8],
     polygon_f: &dyn Fn(
         &(f32,f32,f32,f32),
@@ -103,92 +268,225 @@ pub fn build_obj<V>(
         Option<&(f32,f32,f32)>,
         usize,
     ) -> V
-) -> Vec<V> {
+) -> Result<IndexedModel<V>, ObjLoadError> {
 
-    let bunny = parse_obj(objfile_data).unwrap();
+    let obj = parse_obj(objfile_data).map_err(ObjLoadError::Parse)?;
 
-    bunny.material_libraries.iter().for_each(|x| println!("\tMaterial Library: \"{}\"", x));
+    obj.material_libraries.iter().for_each(|x| println!("\tMaterial Library: \"{}\"", x));
 
-    let size = 0usize; 
+    index_obj(&obj, polygon_f)
 
-    let posns = &bunny.positions;
-    let tcoords = &bunny.tex_coords;
-    let normals = &bunny.normals;
+}
 
-    let mut vertices = vec![];
+/// Like `build_obj_indexed`, but reads the OBJ file at `obj_path` and loads it's material libraries next to it.\
+/// Each group refers to it's material in `IndexedModel::materials`, with texture paths relative to the MTL file.
+pub fn build_obj_file<V>(
+    obj_path: impl AsRef<Path>,
+    polygon_f: &dyn Fn(
+        &(f32,f32,f32,f32),
+        Option<&(f32,f32,f32)>,
+        Option<&(f32,f32,f32)>,
+        usize,
+    ) -> V
+) -> Result<IndexedModel<V>, ObjLoadError> {
 
-    for (gid, (group_name, group)) in bunny.groups.iter().enumerate() {
-        for range in &group.polygons {
+    let obj_path = obj_path.as_ref();
+    let obj = read_obj(obj_path)?;
 
-            for poly in &bunny.polygons[range.start..range.end] {
-
-                let mut polygon = vec![];
-
-                match poly {
-                    obj::raw::object::Polygon::P(p) => {
-                        for pi in p.as_slice() {
-                            let pos = &posns[pi.clone()];
-                            polygon.push(polygon_f(
-                                pos,
-                                None,
-                                None,
-                                gid
-                            ));
-                        }                    
-                    },
-                    obj::raw::object::Polygon::PT(p) => {
-                        for pi in p.as_slice() {
-                            let pos = &posns[pi.0];
-                            let tex = &tcoords[pi.1];
-                            polygon.push(polygon_f(
-                                pos,
-                                Some(tex),
-                                None,
-                                gid
-                            ));
-                        }   
-                    },
-                    obj::raw::object::Polygon::PN(p) => {
-                        for pi in p.as_slice() {
-                            let pos = &posns[pi.0];
-                            let nor = &normals[pi.1];
-                            polygon.push(polygon_f(
-                                pos,
-                                None,
-                                Some(nor),
-                                gid
-                            ));
-                        }   
-
-                    },
-                    obj::raw::object::Polygon::PTN(p) => {
-                        for pi in p.as_slice() {
-                            let pos = &posns[pi.0];
-                            let tex = &tcoords[pi.1];
-                            let nor = &normals[pi.2];
-                            polygon.push(polygon_f(
-                                pos,
-                                Some(tex),
-                                Some(nor),
-                                gid
-                            ));
-                        }   
-
-
-                    },
-                }
+    with_materials(obj_path, &obj, index_obj(&obj, polygon_f)?)
 
-                vertices.append(&mut polygon);
+}
 
-            }
+/// Read an OBJ file into `ObjVertex`es and run `mesh::process` on it, e.g. to generate normals and tangents.
+pub fn load_obj(objfile_data: &[u8], options: &MeshOptions) -> Result<IndexedModel<ObjVertex>, ObjLoadError> {
+
+    let obj = parse_obj(objfile_data).map_err(ObjLoadError::Parse)?;
+
+    obj.material_libraries.iter().for_each(|x| println!("\tMaterial Library: \"{}\"", x));
+
+    Ok(process(index_obj(&obj, &ObjVertex::new)?, options))
+
+}
+
+/// Like `load_obj`, but reads the OBJ file at `obj_path` and loads it's material libraries like `build_obj_file`.
+pub fn load_obj_file(obj_path: impl AsRef<Path>, options: &MeshOptions) -> Result<IndexedModel<ObjVertex>, ObjLoadError> {
+
+    let obj_path = obj_path.as_ref();
+    let obj = read_obj(obj_path)?;
+
+    let model = with_materials(obj_path, &obj, index_obj(&obj, &ObjVertex::new)?)?;
+    Ok(process(model, options))
+
+}
+
+fn read_obj(obj_path: &Path) -> Result<obj::raw::RawObj, ObjLoadError> {
+    let objfile_data = fs::read(obj_path).map_err(|error| ObjLoadError::Io { path: obj_path.to_path_buf(), error })?;
+    parse_obj(objfile_data.as_slice()).map_err(ObjLoadError::Parse)
+}
+
+/// Load the material libraries of an OBJ file, and point each group at it's material.
+fn with_materials<V>(obj_path: &Path, obj: &obj::raw::RawObj, mut model: IndexedModel<V>) -> Result<IndexedModel<V>, ObjLoadError> {
+
+    let materials = MaterialTable::load(obj_path, &obj.material_libraries)?;
+
+    for group in &mut model.groups {
+        group.material_index = group.material.as_deref().and_then(|name| materials.index_of(name));
+        if let (Some(name), None) = (&group.material, group.material_index) {
+            println!("\tMaterial \"{}\" is not in any library", name);
+        }
+    }
+    model.materials = materials;
 
+    Ok(model)
+
+}
+
+fn index_obj<V>(
+    obj: &obj::raw::RawObj,
+    polygon_f: &dyn Fn(
+        &(f32,f32,f32,f32),
+        Option<&(f32,f32,f32)>,
+        Option<&(f32,f32,f32)>,
+        usize,
+    ) -> V
+) -> Result<IndexedModel<V>, ObjLoadError> {
+
+    let posns = &obj.positions;
+    let tcoords = &obj.tex_coords;
+    let normals = &obj.normals;
+
+    // The `g` group of each polygon, which is passed on as the group id
+    let mut polygon_gids = vec![0usize; obj.polygons.len()];
+    for (gid, (_group_name, group)) in obj.groups.iter().enumerate() {
+        for range in &group.polygons {
+            polygon_gids[range.start..range.end].fill(gid);
+        }
+    }
+
+    let mut vertices = vec![];
+    let mut indices = vec![];
+    let mut groups = vec![];
+    let mut lookup: HashMap<(usize, Option<usize>, Option<usize>, usize), u32> = HashMap::new();
+
+    for (material, polygons) in material_polygons(obj) {
+        let start = indices.len() as u32;
+        for i in polygons {
+            let gid = polygon_gids[i];
+            let corners = polygon_corners(obj, i)?;
+            let corner_indices: Vec<u32> = corners.iter().map(|&(p, t, n)| {
+                *lookup.entry((p, t, n, gid)).or_insert_with(|| {
+                    vertices.push(polygon_f(
+                        &posns[p],
+                        t.map(|t| &tcoords[t]),
+                        n.map(|n| &normals[n]),
+                        gid
+                    ));
+                    (vertices.len() - 1) as u32
+                })
+            }).collect();
+            for triangle in polygon_triangles(obj, &corners) {
+                indices.extend(triangle.map(|corner| corner_indices[corner]));
+            }
         }
+        groups.push(MaterialGroup {
+            material,
+            material_index: None,
+            indices: start..indices.len() as u32,
+        });
+    }
+
+    Ok(IndexedModel {
+        vertices,
+        indices,
+        groups,
+        materials: MaterialTable::default(),
+    })
 
-        // Pack textures for this group
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mtl::{MtlParams, ObjMaterial};
 
+    const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\n";
+
+    fn load(faces: &str) -> Result<IndexedModel<ObjVertex>, ObjLoadError> {
+        load_obj(format!("{}{}", TRIANGLE, faces).as_bytes(), &MeshOptions::default())
     }
 
-    vertices
+    #[test]
+    fn polygons_are_triangulated() {
+        let model = load_obj(b"v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n", &MeshOptions::default()).unwrap();
+        assert_eq!(model.indices.len(), 6);
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.groups.len(), 1);
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn out_of_range_indices_are_errors() {
+        assert!(load("f 1 2 3\n").is_ok());
+
+        match load("f 1 2 4\n") {
+            Err(ObjLoadError::IndexOutOfRange { polygon, attribute, index, len }) => assert_eq!((polygon, attribute, index, len), (0, "position", 3, 3)),
+            other => panic!("Expected a position out of range, got {:?}", other.err()),
+        }
+        match load("f 1/1 2/1 3/1\nf 1/1 2/2 3/1\n") {
+            Err(ObjLoadError::IndexOutOfRange { polygon, attribute, index, len }) => assert_eq!((polygon, attribute, index, len), (1, "texture coordinate", 1, 1)),
+            other => panic!("Expected a texture coordinate out of range, got {:?}", other.err()),
+        }
+        match load("f 1//1 2//1 3//2\n") {
+            Err(ObjLoadError::IndexOutOfRange { attribute, .. }) => assert_eq!(attribute, "normal"),
+            other => panic!("Expected a normal out of range, got {:?}", other.err()),
+        }
+
+        let message = load("f 1 2 4\n").err().unwrap().to_string();
+        assert_eq!(message, "Polygon 0 uses position 4 but there are 3");
+    }
+
+    #[test]
+    fn mtl_materials_become_group_materials() {
+        let rm = match safehouse_render::RenderManager::try_new_headless(4, 4) {
+            Ok(rm) => rm,
+            Err(e) => {
+                println!("Skipping group material test, no adapter is available: {}", e);
+                return;
+            },
+        };
+
+        let dir = std::env::temp_dir().join(format!("safehouse_obj_materials_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        gpu::image::RgbaImage::from_pixel(2, 2, gpu::image::Rgba([0, 255, 0, 255])).save(dir.join("green.png")).unwrap();
+        fs::write(dir.join("model.mtl"), "newmtl plain\nKd 1 0 0\nnewmtl textured\nmap_Kd green.png\n").unwrap();
+        fs::write(dir.join("model.obj"), format!("mtllib model.mtl\n{}usemtl plain\nf 1 2 3\nusemtl textured\nf 1 3 2\nusemtl unknown\nf 2 1 3\n", TRIANGLE)).unwrap();
+
+        let model = load_obj_file(dir.join("model.obj"), &MeshOptions::default()).unwrap();
+        let sampler = rm.gpu_state.get_sampler("default").unwrap();
+        let materials = model.group_materials(&rm.gpu_state, sampler).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Every OBJ material has the same layout, so one pipeline draws them all
+        let layout = safehouse_render::material::MaterialLayout::new::<MtlParams>(1);
+        let names: Vec<_> = materials.iter().map(|material| material.as_ref().map(|m| (m.name.as_str(), m.layout()))).collect();
+        assert_eq!(names, [Some(("plain", layout)), Some(("textured", layout)), None]);
+    }
+
+    #[test]
+    fn missing_textures_are_errors() {
+        let rm = match safehouse_render::RenderManager::try_new_headless(4, 4) {
+            Ok(rm) => rm,
+            Err(e) => {
+                println!("Skipping group material test, no adapter is available: {}", e);
+                return;
+            },
+        };
+
+        let mut material = ObjMaterial::new("missing");
+        material.diffuse_map = Some(PathBuf::from("/nonexistent/missing.png"));
+        let sampler = rm.gpu_state.get_sampler("default").unwrap();
+        match material.group_material(&rm.gpu_state, sampler) {
+            Err(safehouse_render::SafehouseError::Decode(message)) => assert!(message.contains("missing.png"), "{}", message),
+            other => panic!("Expected a decode error, got {:?}", other.err()),
+        }
+    }
+}
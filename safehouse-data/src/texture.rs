@@ -0,0 +1,25 @@
+use safehouse_render::gpu::image::{imageops::{self, FilterType}, RgbaImage};
+use safehouse_shared::texture::{mip_size, TextureFileFormat, TEXTURE_MAGIC, TEXTURE_VERSION};
+
+/// Bake an image into a texture for `Texture::from_baked`, with a full mip chain if `mips` is set.\
+/// See `safehouse_shared::texture` for the format.
+pub fn bake_texture(image: &RgbaImage, format: TextureFileFormat, mips: bool) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let level_count = if mips { 32 - width.max(height).leading_zeros() } else { 1 };
+
+    let mut out = vec![];
+    out.extend_from_slice(&TEXTURE_MAGIC);
+    out.extend_from_slice(&TEXTURE_VERSION.to_le_bytes());
+    out.extend_from_slice(&(format as u16).to_le_bytes());
+    for value in [width, height, level_count] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    out.extend_from_slice(image.as_raw());
+    for level in 1..level_count {
+        let (w, h) = mip_size(width, height, level);
+        out.extend_from_slice(imageops::resize(image, w, h, FilterType::Triangle).as_raw());
+    }
+
+    out
+}
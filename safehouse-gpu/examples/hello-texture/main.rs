@@ -72,7 +72,7 @@ impl WinitApp for HelloTexture {
         ]);
 
         let sampler = state.add_sampler("texture_sampler", &wgpu::SamplerDescriptor::default());
-        let texture = Texture::load_encoded(&state, include_bytes!("vulpix.png"), image::ImageFormat::Png);
+        let texture = Texture::load_encoded(&state, include_bytes!("vulpix.png"), image::ImageFormat::Png).expect("Could not load texture!");
 
         let prog = state.add_shader("texture_shader", program!(
             &state,
@@ -190,9 +190,9 @@ impl WinitApp for HelloTexture {
                     self.wobble.update(&self.state);
 
                     let mut cmd = self.state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
-                    let surfacetexture = self.state.surface.get_current_texture().unwrap();
+                    let surfacetexture = self.state.get_current_frame();
                     {
-                        let view = surfacetexture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+                        let view = surfacetexture.texture().create_view(&wgpu::TextureViewDescriptor::default());
                         let mut renderpass = cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
                             label: None,
                             color_attachments: &[
@@ -86,9 +86,9 @@ impl WinitApp for TextExample {
                     self.text_rend.prepare(&self.state);
 
                     let mut cmd = self.state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
-                    let surfacetexture = self.state.surface.get_current_texture().unwrap();
+                    let surfacetexture = self.state.get_current_frame();
                     {
-                        let view = surfacetexture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+                        let view = surfacetexture.texture().create_view(&wgpu::TextureViewDescriptor::default());
                         let mut renderpass = cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
                             label: None,
                             color_attachments: &[
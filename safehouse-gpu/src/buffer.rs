@@ -49,6 +49,19 @@ impl Buffer for VertexBuffer {
 
 }
 
+/// An integer type that can be used for vertex indices.
+pub trait IndexType {
+    const FORMAT: wgpu::IndexFormat;
+}
+
+impl IndexType for u16 {
+    const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint16;
+}
+
+impl IndexType for u32 {
+    const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32;
+}
+
 pub struct IndexBuffer<T> {
     pub buffer: wgpu::Buffer,
     p: std::marker::PhantomData<T>,
@@ -67,6 +80,24 @@ impl<T> IndexBuffer<T> {
             p: std::marker::PhantomData,
         }
     }
+
+    pub fn new_from_raw(display: &State, data: &[u8]) -> Self {
+        let buffer = display.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: data,
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        IndexBuffer {
+            buffer,
+            p: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: IndexType> IndexBuffer<T> {
+    pub fn format(&self) -> wgpu::IndexFormat {
+        T::FORMAT
+    }
 }
 
 impl<T> Buffer for IndexBuffer<T> {
@@ -0,0 +1,220 @@
+// Options for creating a `State`, and a report of what was chosen.
+
+use crate::error::{SafehouseError, SafehouseResult};
+
+/// Options for how a `State` picks it's adapter, device and surface configuration.\
+/// The defaults match WebGL2 limits with vsync, so they run everywhere.
+#[derive(Debug, Clone)]
+pub struct StateDescriptor {
+    /// The graphics APIs that adapters may use.
+    pub backends: wgpu::Backends,
+    pub power_preference: wgpu::PowerPreference,
+    /// Use a software adapter, e.g. for CI without a GPU.
+    pub force_fallback_adapter: bool,
+    /// Features the device must support. Creating the `State` fails if the adapter lacks any of them.
+    pub required_features: wgpu::Features,
+    /// Limits the device must support. Creating the `State` fails if the adapter can't meet them.
+    pub required_limits: wgpu::Limits,
+    /// The present mode to use, falling back to `Fifo` if the surface doesn't support it.
+    pub present_mode: wgpu::PresentMode,
+    /// Surface formats in order of preference. The first one the surface supports is used.
+    pub surface_formats: Vec<wgpu::TextureFormat>,
+    pub desired_maximum_frame_latency: u32,
+}
+
+impl Default for StateDescriptor {
+    fn default() -> Self {
+        Self {
+            backends: wgpu::Backends::all(),
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::downlevel_webgl2_defaults(),
+            present_mode: wgpu::PresentMode::Fifo,
+            surface_formats: vec![wgpu::TextureFormat::Rgba8UnormSrgb],
+            desired_maximum_frame_latency: 2,
+        }
+    }
+}
+
+impl StateDescriptor {
+    pub fn with_backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    pub fn with_power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    pub fn with_fallback_adapter(mut self, force_fallback_adapter: bool) -> Self {
+        self.force_fallback_adapter = force_fallback_adapter;
+        self
+    }
+
+    pub fn with_features(mut self, features: wgpu::Features) -> Self {
+        self.required_features = features;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: wgpu::Limits) -> Self {
+        self.required_limits = limits;
+        self
+    }
+
+    pub fn with_present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    /// Use `AutoVsync` or `AutoNoVsync`, which are supported on every surface.
+    pub fn with_vsync(self, vsync: bool) -> Self {
+        self.with_present_mode(if vsync { wgpu::PresentMode::AutoVsync } else { wgpu::PresentMode::AutoNoVsync })
+    }
+
+    pub fn with_surface_formats(mut self, formats: &[wgpu::TextureFormat]) -> Self {
+        self.surface_formats = formats.to_vec();
+        self
+    }
+
+    pub fn with_frame_latency(mut self, desired_maximum_frame_latency: u32) -> Self {
+        self.desired_maximum_frame_latency = desired_maximum_frame_latency;
+        self
+    }
+
+    pub(crate) fn create_instance(&self) -> wgpu::Instance {
+        wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: self.backends,
+            ..Default::default()
+        })
+    }
+
+    /// Request an adapter matching these options, checking that it supports the required features and limits.
+    pub(crate) fn request_adapter(&self, instance: &wgpu::Instance, compatible_surface: Option<&wgpu::Surface>) -> SafehouseResult<wgpu::Adapter> {
+        let adapter = futures::executor::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: self.power_preference,
+            force_fallback_adapter: self.force_fallback_adapter,
+            compatible_surface,
+        }))
+        .ok_or_else(|| SafehouseError::AdapterRequest(format!("No adapter matches backends {:?}.", self.backends)))?;
+
+        let missing = self.required_features - adapter.features();
+        if !missing.is_empty() {
+            return Err(SafehouseError::AdapterRequest(format!("Adapter '{}' is missing features {:?}.", adapter.get_info().name, missing)));
+        }
+
+        let mut failed = vec![];
+        self.required_limits.check_limits_with_fail_fn(&adapter.limits(), false, |name, requested, allowed| {
+            failed.push(format!("{} (requested {}, allowed {})", name, requested, allowed));
+        });
+        if !failed.is_empty() {
+            return Err(SafehouseError::AdapterRequest(format!("Adapter '{}' does not meet limits: {}.", adapter.get_info().name, failed.join(", "))));
+        }
+
+        Ok(adapter)
+    }
+
+    pub(crate) fn request_device(&self, adapter: &wgpu::Adapter) -> SafehouseResult<(wgpu::Device, wgpu::Queue)> {
+        Ok(futures::executor::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: self.required_features,
+                required_limits: self.required_limits.clone(),
+                memory_hints: wgpu::MemoryHints::default(),
+            },
+            None,
+        ))?)
+    }
+
+    /// The first preferred format the surface supports, or the surface's own preference.
+    pub(crate) fn choose_format(&self, caps: &wgpu::SurfaceCapabilities) -> SafehouseResult<wgpu::TextureFormat> {
+        self.surface_formats.iter()
+            .find(|format| caps.formats.contains(format))
+            .or(caps.formats.first())
+            .copied()
+            .ok_or(SafehouseError::Surface(String::from("Surface is not supported by the adapter.")))
+    }
+
+    /// The requested present mode if the surface supports it, otherwise `Fifo`.
+    pub(crate) fn choose_present_mode(&self, caps: &wgpu::SurfaceCapabilities) -> wgpu::PresentMode {
+        match self.present_mode {
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync => self.present_mode,
+            mode if caps.present_modes.contains(&mode) => mode,
+            mode => {
+                println!("Present mode {:?} is not supported, falling back to Fifo.", mode);
+                wgpu::PresentMode::Fifo
+            },
+        }
+    }
+}
+
+/// What a `State` ended up using, from `State::report`.
+#[derive(Debug, Clone)]
+pub struct StateReport {
+    pub adapter: wgpu::AdapterInfo,
+    pub features: wgpu::Features,
+    pub limits: wgpu::Limits,
+    pub format: wgpu::TextureFormat,
+    pub present_mode: wgpu::PresentMode,
+    pub size: (u32, u32),
+    pub headless: bool,
+}
+
+impl std::fmt::Display for StateReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Adapter: {} ({:?}, {:?})", self.adapter.name, self.adapter.backend, self.adapter.device_type)?;
+        writeln!(f, "Driver: {} {}", self.adapter.driver, self.adapter.driver_info)?;
+        writeln!(f, "Target: {} {}x{}, {:?}", if self.headless { "offscreen" } else { "surface" }, self.size.0, self.size.1, self.format)?;
+        writeln!(f, "Present mode: {:?}", self.present_mode)?;
+        writeln!(f, "Features: {:?}", self.features)?;
+        write!(f, "Max texture size: {}, max bind groups: {}", self.limits.max_texture_dimension_2d, self.limits.max_bind_groups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::{PresentMode, TextureFormat};
+
+    fn surface_caps(formats: &[TextureFormat], present_modes: &[PresentMode]) -> wgpu::SurfaceCapabilities {
+        wgpu::SurfaceCapabilities {
+            formats: formats.to_vec(),
+            present_modes: present_modes.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_descriptor_prefers_srgb_over_the_surface_preference() {
+        let caps = surface_caps(&[TextureFormat::Bgra8Unorm, TextureFormat::Rgba8Unorm, TextureFormat::Rgba8UnormSrgb], &[]);
+        assert_eq!(StateDescriptor::default().choose_format(&caps).unwrap(), TextureFormat::Rgba8UnormSrgb);
+    }
+
+    #[test]
+    fn preferred_formats_are_tried_in_order() {
+        let desc = StateDescriptor::default().with_surface_formats(&[TextureFormat::Rgba16Float, TextureFormat::Bgra8UnormSrgb, TextureFormat::Bgra8Unorm]);
+        let caps = surface_caps(&[TextureFormat::Bgra8Unorm, TextureFormat::Bgra8UnormSrgb], &[]);
+        assert_eq!(desc.choose_format(&caps).unwrap(), TextureFormat::Bgra8UnormSrgb);
+    }
+
+    #[test]
+    fn unoffered_formats_fall_back_to_the_surface_preference() {
+        let desc = StateDescriptor::default().with_surface_formats(&[TextureFormat::Rgba16Float]);
+        let caps = surface_caps(&[TextureFormat::Bgra8Unorm, TextureFormat::Bgra8UnormSrgb], &[]);
+        assert_eq!(desc.choose_format(&caps).unwrap(), TextureFormat::Bgra8Unorm);
+
+        // A surface with no formats isn't supported by the adapter at all
+        assert!(matches!(desc.choose_format(&surface_caps(&[], &[])), Err(SafehouseError::Surface(_))));
+    }
+
+    #[test]
+    fn unsupported_present_modes_fall_back_to_fifo() {
+        let caps = surface_caps(&[], &[PresentMode::Fifo, PresentMode::Immediate]);
+        assert_eq!(StateDescriptor::default().with_present_mode(PresentMode::Immediate).choose_present_mode(&caps), PresentMode::Immediate);
+        assert_eq!(StateDescriptor::default().with_present_mode(PresentMode::Mailbox).choose_present_mode(&caps), PresentMode::Fifo);
+
+        // The automatic modes are supported everywhere, and are passed through for wgpu to resolve
+        assert_eq!(StateDescriptor::default().with_vsync(false).choose_present_mode(&surface_caps(&[], &[])), PresentMode::AutoNoVsync);
+    }
+}
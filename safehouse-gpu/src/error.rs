@@ -0,0 +1,102 @@
+// Errors returned by the GPU and rendering layers.
+
+/// The kind of resource that a `SafehouseError::MissingResource` was looked up as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    Pipeline,
+    Shader,
+    Sampler,
+    Model,
+    BindGroupLayout,
+}
+
+#[derive(Debug)]
+pub enum SafehouseError {
+    /// No resource of this kind was loaded with this name.
+    MissingResource { kind: ResourceKind, name: String },
+
+    /// Image or model data could not be decoded.
+    Decode(String),
+
+    /// A shader module failed to compile or validate.
+    ShaderCompile { label: Option<String>, message: String },
+
+    /// A shader's interface does not match the bindings or vertex layout it is used with.
+    ShaderMismatch { label: String, message: String },
+
+    /// A model's data does not match the pipeline it is drawn with, e.g. a group material with the wrong layout.
+    ModelMismatch { model: String, message: String },
+
+    /// A SceneObject handle does not refer to a live object, e.g. it's object was removed.
+    StaleHandle { tag: usize, generation: u64 },
+
+    /// A material's layout doesn't match the one the SceneObject's pipeline was built for.
+    MaterialMismatch { object: String },
+
+    /// wgpu rejected an entity's pipeline, e.g. a blend state or vertex format the device doesn't support.
+    PipelineCreation { pipeline: String, message: String },
+
+    /// The device can't render with this many samples per pixel.
+    UnsupportedSampleCount(u32),
+
+    /// No adapter matched the requested options.
+    AdapterRequest(String),
+
+    /// The adapter could not create a device.
+    DeviceRequest(wgpu::RequestDeviceError),
+
+    /// The window surface could not be created or configured.
+    Surface(String),
+
+    /// A texture could not be copied back to the CPU, e.g. because of it's format or usage.
+    Readback(String),
+}
+
+pub type SafehouseResult<T> = Result<T, SafehouseError>;
+
+impl SafehouseError {
+    pub fn missing(kind: ResourceKind, name: &str) -> Self {
+        Self::MissingResource { kind, name: String::from(name) }
+    }
+}
+
+impl std::fmt::Display for SafehouseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SafehouseError::MissingResource { kind, name } => write!(f, "{:?} '{}' not found", kind, name),
+            SafehouseError::Decode(message) => write!(f, "Could not decode data: {}", message),
+            SafehouseError::ShaderCompile { label, message } => write!(f, "Shader '{}' failed to compile: {}", label.as_deref().unwrap_or("?"), message),
+            SafehouseError::ShaderMismatch { label, message } => write!(f, "Shader '{}' does not match it's entity: {}", label, message),
+            SafehouseError::ModelMismatch { model, message } => write!(f, "Model '{}' does not match it's entity: {}", model, message),
+            SafehouseError::StaleHandle { tag, generation } => write!(f, "SceneObject handle {}:{} is stale or invalid", tag, generation),
+            SafehouseError::MaterialMismatch { object } => write!(f, "The material does not match the material layout of SceneObject '{}'s pipeline", object),
+            SafehouseError::PipelineCreation { pipeline, message } => write!(f, "Pipeline '{}' could not be created: {}", pipeline, message),
+            SafehouseError::UnsupportedSampleCount(count) => write!(f, "A sample count of {} is not supported by this device", count),
+            SafehouseError::AdapterRequest(message) => write!(f, "No adapter available: {}", message),
+            SafehouseError::DeviceRequest(e) => write!(f, "Could not request device: {}", e),
+            SafehouseError::Surface(message) => write!(f, "Surface error: {}", message),
+            SafehouseError::Readback(message) => write!(f, "Could not read back texture: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SafehouseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SafehouseError::DeviceRequest(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for SafehouseError {
+    fn from(value: image::ImageError) -> Self {
+        Self::Decode(value.to_string())
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for SafehouseError {
+    fn from(value: wgpu::RequestDeviceError) -> Self {
+        Self::DeviceRequest(value)
+    }
+}
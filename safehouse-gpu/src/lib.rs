@@ -4,11 +4,19 @@ pub mod texture;
 pub mod dataunit;
 pub mod vertex;
 pub mod binding;
+pub mod target;
+pub mod readback;
+pub mod error;
+pub mod descriptor;
 use std::{collections::HashMap, rc::Rc, sync::Arc };
+use descriptor::{StateDescriptor, StateReport};
+use error::{ResourceKind, SafehouseError, SafehouseResult};
+use target::{Frame, RenderTarget};
 use texture::sampler::TextureSampler;
-use wgpu::{BackendOptions, Backends, InstanceFlags, TextureFormat, TextureUsages};
+use wgpu::{BackendOptions, InstanceFlags, TextureFormat, TextureUsages};
 pub use wgpu;
 pub use winit;
+pub use image;
 
 #[cfg(feature="text")]
 pub mod text;
@@ -20,7 +28,8 @@ use winit::{window::{Window}};
 
 pub struct State {
     // GPU Context 
-    pub surface: wgpu::Surface<'static>,
+    pub target: RenderTarget,
+    pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
@@ -36,127 +45,225 @@ pub struct State {
 impl State {
 
     pub fn new<'window_ref>(window: &'window_ref Arc<Window>) -> Self {
+        Self::try_new(window).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Create a `State` for a window, returning an error if no adapter, device or surface is available.
+    pub fn try_new<'window_ref>(window: &'window_ref Arc<Window>) -> SafehouseResult<Self> {
+        Self::with_descriptor(window, &StateDescriptor::default())
+    }
 
-        // TODO: Set backend and limits as optional parameters
+    /// Create a `State` for a window with the adapter, device and surface options in `desc`.
+    pub fn with_descriptor<'window_ref>(window: &'window_ref Arc<Window>, desc: &StateDescriptor) -> SafehouseResult<Self> {
 
         let size = window.inner_size();
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor { 
-            backends: Backends::all(),
-            ..Default::default()
-        });
-
-        let surface = instance.create_surface(Arc::clone(window)).unwrap();
-
-        let adapter = instance
-        .enumerate_adapters(Backends::all())
-        .into_iter()
-        .filter(|a|{
-            (a.get_texture_format_features(TextureFormat::Rgba8UnormSrgb).allowed_usages != TextureUsages::empty())
-            && a.is_surface_supported(&surface)
-        })
-        .next()
-        .expect("RGBA8 format not supported!");
-
-        let (device, queue) = futures::executor::block_on(adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                required_limits: wgpu::Limits::downlevel_webgl2_defaults(),
-                ..Default::default()
-            },
-            None,
-        )).unwrap();
-
-        let mut config = surface.get_default_config(&adapter, size.width, size.height).unwrap();
-        config.format = TextureFormat::Rgba8UnormSrgb;
+        let instance = desc.create_instance();
+
+        let surface = instance.create_surface(Arc::clone(window))
+            .map_err(|e| SafehouseError::Surface(e.to_string()))?;
+
+        let adapter = desc.request_adapter(&instance, Some(&surface))?;
+        let (device, queue) = desc.request_device(&adapter)?;
+
+        let caps = surface.get_capabilities(&adapter);
+        let mut config = surface.get_default_config(&adapter, size.width, size.height)
+            .ok_or(SafehouseError::Surface(String::from("Surface is not supported by the adapter.")))?;
+        config.format = desc.choose_format(&caps)?;
+        config.present_mode = desc.choose_present_mode(&caps);
+        config.desired_maximum_frame_latency = desc.desired_maximum_frame_latency;
+
+        // Allow frames to be copied back for screenshots, where supported.
+        if caps.usages.contains(TextureUsages::COPY_SRC) {
+            config.usage |= TextureUsages::COPY_SRC;
+        }
     
         surface.configure(&device, &config);
 
-        let mut shader_programs = HashMap::new();
+        Ok(Self::from_parts(RenderTarget::Surface(surface), adapter, device, queue, config, size))
+    }
 
-        let mut render_pipelines = HashMap::new();
+    /// Create a `State` that renders into an offscreen texture instead of a window surface.\
+    /// A software/fallback adapter is used if no hardware adapter is available.
+    pub fn new_headless(width: u32, height: u32) -> Self {
+        Self::try_new_headless(width, height).unwrap_or_else(|e| panic!("{}", e))
+    }
 
+    /// Create a headless `State`, returning an error if no adapter or device is available.
+    pub fn try_new_headless(width: u32, height: u32) -> SafehouseResult<Self> {
+        let desc = StateDescriptor::default();
+        Self::headless_with_descriptor(width, height, &desc)
+            .or_else(|_| Self::headless_with_descriptor(width, height, &desc.with_fallback_adapter(true)))
+    }
+
+    /// Create a headless `State` with the adapter and device options in `desc`.\
+    /// The first of `desc.surface_formats` is used for the offscreen texture.
+    pub fn headless_with_descriptor(width: u32, height: u32, desc: &StateDescriptor) -> SafehouseResult<Self> {
+
+        let instance = desc.create_instance();
+        let adapter = desc.request_adapter(&instance, None)?;
+        let (device, queue) = desc.request_device(&adapter)?;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC | TextureUsages::TEXTURE_BINDING,
+            format: desc.surface_formats.first().copied().unwrap_or(TextureFormat::Rgba8UnormSrgb),
+            width,
+            height,
+            present_mode: wgpu::PresentMode::AutoNoVsync,
+            desired_maximum_frame_latency: desc.desired_maximum_frame_latency,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+        };
+
+        let texture = RenderTarget::create_offscreen_texture(&device, &config);
+
+        Ok(Self::from_parts(RenderTarget::Offscreen(texture), adapter, device, queue, config, winit::dpi::PhysicalSize::new(width, height)))
+    }
+
+    fn from_parts(target: RenderTarget, adapter: wgpu::Adapter, device: wgpu::Device, queue: wgpu::Queue, config: wgpu::SurfaceConfiguration, size: winit::dpi::PhysicalSize<u32>) -> Self {
         State {
-            surface,
+            target,
+            adapter,
             device,
             queue,
             config,
             size,
-            render_pipelines,
-            shader_programs,
+            render_pipelines: HashMap::new(),
+            shader_programs: HashMap::new(),
             texture_samplers: HashMap::new(),
             resized: false,
         }
     }
+
+    /// Whether this state renders without a window.
+    pub fn is_headless(&self) -> bool {
+        self.target.is_headless()
+    }
+
+    /// Whether textures of `format` can be multisampled with `sample_count` samples on this device.\
+    /// Counts of 1 and 4 are always supported for renderable formats, others need `TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES`.
+    pub fn supports_sample_count(&self, format: TextureFormat, sample_count: u32) -> bool {
+        match sample_count {
+            1 | 4 => true,
+            _ => self.device.features().contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+                && self.adapter.get_texture_format_features(format).flags.sample_count_supported(sample_count),
+        }
+    }
+
+    /// Get the next frame to render into.
+    pub fn get_current_frame(&self) -> Frame {
+        match &self.target {
+            RenderTarget::Surface(surface) => Frame::Surface(surface.get_current_texture().unwrap()),
+            RenderTarget::Offscreen(texture) => Frame::Offscreen(texture.clone()),
+        }
+    }
     
+    /// A report of the adapter, features, limits and surface configuration in use.
+    pub fn report(&self) -> StateReport {
+        StateReport {
+            adapter: self.adapter.get_info(),
+            features: self.device.features(),
+            limits: self.device.limits(),
+            format: self.config.format,
+            present_mode: self.config.present_mode,
+            size: (self.config.width, self.config.height),
+            headless: self.is_headless(),
+        }
+    }
+
     pub fn print_info(&self) {
         println!("=== safehouse-gpu ===");
-        println!("Config: {:?}", self.config);
+        println!("{}", self.report());
         println!("=====================");
     }
 
+    /// Copy a texture back to the CPU as an image, blocking until the copy is done.
+    pub fn read_texture(&self, texture: &wgpu::Texture) -> SafehouseResult<image::RgbaImage> {
+        let readback = readback::TextureReadback::new(self, texture)?;
+        let mut cmd = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        readback.copy(&mut cmd, texture);
+        self.queue.submit(std::iter::once(cmd.finish()));
+        readback.read(self)
+    }
+
     pub fn set_resize(&mut self, width: u32, height: u32) {
         self.config.width = width;
         self.config.height = height;
         self.resized = true;
     }
 
-    pub fn update_resize(&mut self) {
+    /// Apply a pending resize to the render target. Returns `true` if a resize happened.
+    pub fn update_resize(&mut self) -> bool {
         if self.resized {
             println!("Resizing!");
-            self.surface.configure(&self.device, &self.config);
+            match &mut self.target {
+                RenderTarget::Surface(surface) => surface.configure(&self.device, &self.config),
+                RenderTarget::Offscreen(texture) => *texture = RenderTarget::create_offscreen_texture(&self.device, &self.config),
+            }
             self.resized = false;
+            true
+        } else {
+            false
         }
     }
 
-    // TODO: log replacements when adding items 
-
     pub fn add_render_pipeline(&mut self, pipeline_name: &str, desc: &wgpu::RenderPipelineDescriptor) -> Rc<wgpu::RenderPipeline> {
         let rp = Rc::new(self.device.create_render_pipeline(desc));
-        self.render_pipelines.insert(String::from(pipeline_name), Rc::clone(&rp));
+        self.insert_render_pipeline(pipeline_name, Rc::clone(&rp));
         rp
     }
 
-    pub fn get_render_pipeline(&self, pipeline_name: &str) -> Option<Rc<wgpu::RenderPipeline>> {
-        // TODO: why is this a redundant `Some`? fix this
-        Some(Rc::clone(
-            self.render_pipelines
-                .get(pipeline_name)
-                .expect(&format!("Fatal Error: Pipeline: '{}' not found.", pipeline_name))
-        ))
+    /// Add an existing pipeline under `pipeline_name`, returning the pipeline it replaced.\
+    /// Replacing a different pipeline is logged, since the name may belong to another entity.
+    pub fn insert_render_pipeline(&mut self, pipeline_name: &str, pipeline: Rc<wgpu::RenderPipeline>) -> Option<Rc<wgpu::RenderPipeline>> {
+        let old = self.render_pipelines.insert(String::from(pipeline_name), Rc::clone(&pipeline));
+        if old.as_ref().is_some_and(|old| !Rc::ptr_eq(old, &pipeline)) {
+            println!("Replaced render pipeline '{}'.", pipeline_name);
+        }
+        old
+    }
+
+    pub fn get_render_pipeline(&self, pipeline_name: &str) -> SafehouseResult<Rc<wgpu::RenderPipeline>> {
+        self.render_pipelines
+            .get(pipeline_name)
+            .map(Rc::clone)
+            .ok_or_else(|| SafehouseError::missing(ResourceKind::Pipeline, pipeline_name))
     }
 
     pub fn add_shader(&mut self, shader_name: &str, program: shaderprogram::Program) -> Rc<shaderprogram::Program>{
 
         let shader_ref = Rc::new(program);
 
-        self.shader_programs
-            .insert(shader_name.to_string(), Rc::clone(&shader_ref));
+        if self.shader_programs.insert(shader_name.to_string(), Rc::clone(&shader_ref)).is_some() {
+            println!("Replaced shader '{}'.", shader_name);
+        }
 
         shader_ref
     }
 
-    pub fn get_shader<'a>(&'a self, shader_name: &str) -> &'a Rc<shaderprogram::Program> {
+    pub fn get_shader<'a>(&'a self, shader_name: &str) -> SafehouseResult<&'a Rc<shaderprogram::Program>> {
         self.shader_programs
             .get(shader_name)
-            .expect(&format!("Fatal Error: Shader: '{}' not found.", shader_name))
-        
+            .ok_or_else(|| SafehouseError::missing(ResourceKind::Shader, shader_name))
     }
 
     pub fn add_sampler(&mut self, sampler_name: &str, sampler: &wgpu::SamplerDescriptor) -> Rc<texture::sampler::TextureSampler> {
         let sampler_rc = Rc::new(TextureSampler::new(&self, sampler));
-        self.texture_samplers.insert(String::from(sampler_name), Rc::clone(&sampler_rc));
+        if self.texture_samplers.insert(String::from(sampler_name), Rc::clone(&sampler_rc)).is_some() {
+            println!("Replaced sampler '{}'.", sampler_name);
+        }
         sampler_rc
     }
 
-    pub fn get_sampler<'a>(&'a self, sampler_name: &str) -> &'a Rc<TextureSampler> {
+    pub fn get_sampler<'a>(&'a self, sampler_name: &str) -> SafehouseResult<&'a Rc<TextureSampler>> {
         self.texture_samplers
-        .get(sampler_name)
-        .expect(&format!("Fatal Error: Sampler: '{}' not found.", sampler_name))
+            .get(sampler_name)
+            .ok_or_else(|| SafehouseError::missing(ResourceKind::Sampler, sampler_name))
     }
 
-    pub fn init_bindgroup_from_pipeline(&self, pipeline_name: &str, bindgroup_index: u32, entries: &[wgpu::BindGroupEntry]) -> Option<(Rc<wgpu::BindGroup>, Rc<wgpu::BindGroupLayout>)> {
+    pub fn init_bindgroup_from_pipeline(&self, pipeline_name: &str, bindgroup_index: u32, entries: &[wgpu::BindGroupEntry]) -> SafehouseResult<(Rc<wgpu::BindGroup>, Rc<wgpu::BindGroupLayout>)> {
         let pipeline_ref = self.get_render_pipeline(pipeline_name)?;
         let bglayout = Rc::new(pipeline_ref.get_bind_group_layout(bindgroup_index));
-        Some((Rc::new(self.device.create_bind_group(&wgpu::BindGroupDescriptor{
+        Ok((Rc::new(self.device.create_bind_group(&wgpu::BindGroupDescriptor{
             label: None,
             layout: &bglayout,
             entries
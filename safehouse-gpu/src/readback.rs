@@ -0,0 +1,115 @@
+// Copying rendered textures back to the CPU.
+
+use std::sync::mpsc;
+
+use crate::error::{SafehouseError, SafehouseResult};
+
+/// A mappable buffer that a texture's contents are copied into for reading on the CPU.\
+/// Rows are padded to `wgpu::COPY_BYTES_PER_ROW_ALIGNMENT` on the GPU side, and unpadded when read.
+pub struct TextureReadback {
+    buffer: wgpu::Buffer,
+    size: wgpu::Extent3d,
+    format: wgpu::TextureFormat,
+    padded_bytes_per_row: u32,
+    unpadded_bytes_per_row: u32,
+}
+
+impl TextureReadback {
+
+    /// Whether textures of `format` can be read back into an `RgbaImage`. Only 8 bit RGBA/BGRA formats are supported.
+    pub fn supports_format(format: wgpu::TextureFormat) -> bool {
+        matches!(format,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb
+            | wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        )
+    }
+
+    /// Create a readback buffer sized for `texture`.\
+    /// Returns an error if the texture's format isn't supported or it wasn't created with `TextureUsages::COPY_SRC`.
+    pub fn new(state: &crate::State, texture: &wgpu::Texture) -> SafehouseResult<Self> {
+        let format = texture.format();
+        if !Self::supports_format(format) {
+            return Err(SafehouseError::Readback(format!("texture format {:?} is not supported", format)));
+        }
+        if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            return Err(SafehouseError::Readback(String::from("the texture does not allow COPY_SRC")));
+        }
+        let bytes_per_pixel = 4;
+
+        let size = texture.size();
+        let unpadded_bytes_per_row = size.width * bytes_per_pixel;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("texture_readback"),
+            size: (padded_bytes_per_row * size.height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Ok(Self {
+            buffer,
+            size,
+            format,
+            padded_bytes_per_row,
+            unpadded_bytes_per_row,
+        })
+    }
+
+    /// Record a copy of `texture` into the readback buffer.
+    pub fn copy(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.size.height),
+                },
+            },
+            wgpu::Extent3d {
+                depth_or_array_layers: 1,
+                ..self.size
+            },
+        );
+    }
+
+    /// Wait for the copy to finish and read the pixels into an image.\
+    /// The copy must have been submitted before calling this.
+    pub fn read(self, state: &crate::State) -> SafehouseResult<image::RgbaImage> {
+        let slice = self.buffer.slice(..);
+        let (tx, rx) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        state.device.poll(wgpu::Maintain::Wait);
+        rx.recv()
+            .map_err(|_| SafehouseError::Readback(String::from("the buffer was dropped before mapping")))?
+            .map_err(|e| SafehouseError::Readback(format!("could not map the buffer: {}", e)))?;
+
+        let mut pixels = Vec::with_capacity((self.unpadded_bytes_per_row * self.size.height) as usize);
+        {
+            let mapped = slice.get_mapped_range();
+            for row in mapped.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..self.unpadded_bytes_per_row as usize]);
+            }
+        }
+        self.buffer.unmap();
+
+        if matches!(self.format, wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb) {
+            for px in pixels.chunks_mut(4) {
+                px.swap(0, 2);
+            }
+        }
+
+        image::RgbaImage::from_raw(self.size.width, self.size.height, pixels)
+            .ok_or_else(|| SafehouseError::Readback(String::from("the buffer size did not match the texture size")))
+    }
+}
@@ -16,10 +16,23 @@ macro_rules! program {
 
 pub struct Program {
     pub module: Rc<wgpu::ShaderModule>,
+    /// The WGSL this program was created from, kept for reflection. `None` for other source types.
+    pub source: Option<String>,
+    /// The label the program was created with, used as it's name when it's added to a manager.
+    pub label: Option<String>,
+}
+
+/// The WGSL text of a shader source, if it is WGSL.
+fn wgsl_source(module: &wgpu::ShaderSource<'_>) -> Option<String> {
+    match module {
+        wgpu::ShaderSource::Wgsl(source) => Some(source.to_string()),
+        _ => None,
+    }
 }
 
 impl Program {
     pub fn new(display: &crate::State, module: wgpu::ShaderSource<'_>) -> Self {
+        let source = wgsl_source(&module);
         let module = display.device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
             source: module,
@@ -27,6 +40,30 @@ impl Program {
 
         Self {
             module: Rc::new(module),
+            source,
+            label: None,
+        }
+    }
+
+    /// Create a shader program, returning the compile or validation error instead of panicking.
+    pub fn try_new(display: &crate::State, label: Option<&str>, module: wgpu::ShaderSource<'_>) -> crate::error::SafehouseResult<Self> {
+        let source = wgsl_source(&module);
+        display.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = display.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label,
+            source: module,
+        });
+
+        match futures::executor::block_on(display.device.pop_error_scope()) {
+            Some(e) => Err(crate::error::SafehouseError::ShaderCompile {
+                label: label.map(String::from),
+                message: e.to_string(),
+            }),
+            None => Ok(Self {
+                module: Rc::new(module),
+                source,
+                label: label.map(String::from),
+            }),
         }
     }
 
@@ -0,0 +1,62 @@
+// Render targets that a `State` can present frames to.
+
+/// Where the `State` renders its frames.
+pub enum RenderTarget {
+    /// A window surface, presented through the swapchain.
+    Surface(wgpu::Surface<'static>),
+
+    /// An offscreen texture with no window attached, e.g. for CI or server-side rendering.
+    Offscreen(wgpu::Texture),
+}
+
+impl RenderTarget {
+
+    /// Creates the offscreen color texture described by `config`.
+    pub(crate) fn create_offscreen_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen_target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format.clone(),
+            usage: config.usage,
+            view_formats: &[],
+        })
+    }
+
+    pub fn is_headless(&self) -> bool {
+        matches!(self, Self::Offscreen(_))
+    }
+}
+
+/// A frame acquired from a `RenderTarget`, to be presented once rendering is submitted.
+pub enum Frame {
+    Surface(wgpu::SurfaceTexture),
+    Offscreen(wgpu::Texture),
+}
+
+impl Frame {
+    pub fn texture(&self) -> &wgpu::Texture {
+        match self {
+            Frame::Surface(st) => &st.texture,
+            Frame::Offscreen(t) => t,
+        }
+    }
+
+    pub fn create_view(&self) -> wgpu::TextureView {
+        self.texture().create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    /// Present the frame to the window. Offscreen frames stay in their texture.
+    pub fn present(self) {
+        match self {
+            Frame::Surface(st) => st.present(),
+            Frame::Offscreen(_) => (),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+/// The depth format used by all depth textures and pipelines.
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// A depth buffer sized to match the render target.
+pub struct DepthTexture {
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl DepthTexture {
+    pub fn new(state: &crate::State) -> Self {
+        Self::new_multisampled(state, 1)
+    }
+
+    /// Create a depth buffer for a multisampled render pass, with a `sample_count` matching it's color target.
+    pub fn new_multisampled(state: &crate::State, sample_count: u32) -> Self {
+        // Multisampled depth can't be sampled like a regular texture anyway, and on GL backends
+        // a sampleable attachment next to the multisampled color renderbuffer leaves the framebuffer incomplete
+        let usage = if sample_count > 1 {
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+        } else {
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+        };
+
+        let texture = state.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth_texture"),
+            size: wgpu::Extent3d {
+                width: state.config.width.max(1),
+                height: state.config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage,
+            view_formats: &[],
+        });
+
+        Self {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            texture,
+        }
+    }
+
+    /// Whether this texture no longer matches the size of the render target.
+    pub fn is_outdated(&self, state: &crate::State) -> bool {
+        self.texture.width() != state.config.width.max(1) || self.texture.height() != state.config.height.max(1)
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.texture.sample_count()
+    }
+
+    /// The depth/stencil state used when a pipeline doesn't specify one.
+    pub fn default_state() -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+}
@@ -2,6 +2,10 @@
 
 mod single;
 mod array;
+mod depth;
+mod multisample;
 pub mod sampler;
 pub use single::*;
-pub use array::*;
\ No newline at end of file
+pub use array::*;
+pub use depth::*;
+pub use multisample::*;
\ No newline at end of file
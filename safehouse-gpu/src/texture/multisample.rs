@@ -0,0 +1,40 @@
+/// A multisampled color target matching the render target, which is resolved into the frame at the end of a render pass.
+pub struct MultisampleTexture {
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl MultisampleTexture {
+    pub fn new(state: &crate::State, sample_count: u32) -> Self {
+        let texture = state.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("multisample_texture"),
+            size: wgpu::Extent3d {
+                width: state.config.width.max(1),
+                height: state.config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: state.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        Self {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            texture,
+        }
+    }
+
+    /// Whether this texture no longer matches the size or format of the render target.
+    pub fn is_outdated(&self, state: &crate::State) -> bool {
+        self.texture.width() != state.config.width.max(1)
+            || self.texture.height() != state.config.height.max(1)
+            || self.texture.format() != state.config.format
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.texture.sample_count()
+    }
+}
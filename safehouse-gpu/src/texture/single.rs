@@ -1,7 +1,7 @@
-use image::GenericImageView;
 use wgpu::Extent3d;
 
-use crate::{binding::{Bindable, BindableType}, dataunit::*};
+use crate::{binding::{Bindable, BindableType}, dataunit::*, error::{SafehouseError, SafehouseResult}};
+use safehouse_shared::texture::{mip_size, TextureFile};
 use std::rc::Rc;
 
 pub struct Texture {
@@ -15,11 +15,15 @@ impl Texture {
         display: &crate::State,
         data: &'image [u8],
         encoding_format: ImageFormat
-    ) -> Texture {
+    ) -> SafehouseResult<Texture> {
 
-        let image_loaded = image::load_from_memory_with_format(data, encoding_format).unwrap();
-        let image_rgba = image_loaded.to_rgba8();
-        let (width, height) = image_loaded.dimensions();
+        let image_loaded = image::load_from_memory_with_format(data, encoding_format)?;
+        Ok(Self::from_rgba8(display, &image_loaded.to_rgba8()))
+    }
+
+    /// Create a texture from decoded RGBA pixels.
+    pub fn from_rgba8(display: &crate::State, image_rgba: &image::RgbaImage) -> Texture {
+        let (width, height) = image_rgba.dimensions();
         let size = Extent3d {
             width,
             height,
@@ -48,7 +52,7 @@ impl Texture {
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             }, 
-            &image_rgba, 
+            image_rgba, 
             wgpu::TexelCopyBufferLayout {
                 offset: 0,
                 bytes_per_row: Some(4 * width),
@@ -73,16 +77,71 @@ impl Texture {
         }
     }
 
+    /// Create a texture with all of it's mip levels from a texture baked by `safehouse-data`.\
+    /// Unlike `from_rgba8`, the texture keeps the baked format instead of matching the surface.
+    pub fn from_baked(display: &crate::State, data: &[u8]) -> SafehouseResult<Texture> {
+        let file = TextureFile::parse(data).map_err(|e| SafehouseError::Decode(e.to_string()))?;
+        let texture_format = file.format.wgpu_format();
+
+        let texture = display.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: file.width,
+                height: file.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: file.levels.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture_format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        for (level, pixels) in file.levels.iter().enumerate() {
+            let (width, height) = mip_size(file.width, file.height, level as u32);
+            display.queue.write_texture(
+                wgpu::TexelCopyTextureInfoBase {
+                    texture: &texture,
+                    mip_level: level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                pixels,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(file.format.bytes_per_pixel() * width),
+                    rows_per_image: Some(height),
+                },
+                Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        Ok(Texture {
+            view: Rc::new(texture.create_view(&wgpu::TextureViewDescriptor::default())),
+            texture: Rc::new(texture),
+        })
+    }
+
+    /// A 1x1 texture of a single color, e.g. as a placeholder for a missing texture.
+    pub fn solid(display: &crate::State, rgba: [u8; 4]) -> Texture {
+        Self::from_rgba8(display, &image::RgbaImage::from_pixel(1, 1, image::Rgba(rgba)))
+    }
+
     pub fn load_hardcoded(
         display: &crate::State,
         block: &DataUnit,
-    ) -> Self {
+    ) -> SafehouseResult<Self> {
     
         match block.1 {
             UnitFormat::IMAGE(imgfmt) => {
                 Self::load_encoded(display, block.0, imgfmt)
             },
-            _ => {panic!("Trying to load invalid data block type")}
+            _ => Err(SafehouseError::Decode(String::from("Trying to load invalid data block type")))
         }
     
     }
@@ -38,7 +38,7 @@ impl WinitApp for HelloSceneObject {
             None
         ));
 
-        rm.add_scene_object("test triangle", "triangle_model", "default");
+        rm.add_scene_object("test triangle", "triangle_model", "default").expect("Could not add the triangle!");
 
         let mut last_rendered = Instant::now();
 
@@ -61,13 +61,13 @@ impl WinitApp for HelloSceneObject {
             winit::event::WindowEvent::RedrawRequested => {
                 if Instant::now().duration_since(self.last_rendered) >= Duration::from_millis(16) {
                     // println!("draw");
-                    self.rm.gpu_state.update_resize();
+                    self.rm.update_resize();
                     self.rm.update_time();
                     self.camera.update_vals(1.0*self.last_rendered.elapsed().as_secs_f32(), &self.controller);
                     self.rm.render(&self.camera);
                     self.last_rendered = Instant::now();
                 }
-                self.rm.window.request_redraw();
+                self.rm.request_redraw();
             },
 
             winit::event::WindowEvent::KeyboardInput { device_id, event, is_synthetic } => {
@@ -29,7 +29,7 @@ impl Entity for TextPane {
         Self {
             dyn_texture_handle,
             dyn_texture_ref,
-            text_texture_sampler: Rc::clone(rm.gpu_state.get_sampler("default")),
+            text_texture_sampler: Rc::clone(rm.gpu_state.get_sampler("default").expect("No default sampler!")),
         } 
     }
 
@@ -40,9 +40,9 @@ impl Entity for TextPane {
         ]
     }
 
-    fn load_model(state: &safehouse_gpu::State) -> ModelData {
+    fn load_model(state: &safehouse_gpu::State) -> render::SafehouseResult<ModelData> {
 
-        ModelData::new::<Self,()>(
+        Ok(ModelData::new::<Self,()>(
             state, 
             VertexBuffer::new(state, &[
                 
@@ -57,7 +57,7 @@ impl Entity for TextPane {
             ]),
             vec![0..6],
             None
-        )
+        ))
 
     }
 
@@ -69,10 +69,12 @@ impl Entity for TextPane {
                 ..Default::default()
             },
             depth_stencil: None,
+            color_target: safehouse_render::entity::EntityColorTarget::blended(safehouse_render::entity::BlendMode::Alpha),
+            ..Default::default()
         })
     }
 
-    fn load_shader(rm: &safehouse_render::RenderManager, group_model: u32, group_entity: u32) -> Option<safehouse_gpu::shaderprogram::Program> {
+    fn load_shader(rm: &safehouse_render::RenderManager, group_model: u32, group_entity: u32) -> Option<safehouse_render::entity::EntityShaderEntry> {
         Some(
             program!(
                 &rm.gpu_state,
@@ -108,7 +110,7 @@ impl Entity for TextPane {
                 }}
                 
                 ")
-            )
+            ).into()
         )
     }
 
@@ -132,8 +134,8 @@ impl WinitApp for TextTextureExample {
 
         let mut camera = Camera::new(800f32, 600f32);
 
-        rm.load_entity::<TextPane>();
-        let mut pane = rm.spawn_sceneobject_entity::<TextPane>("TextPane");
+        rm.load_entity::<TextPane>().expect("Could not load TextPane!");
+        let mut pane = rm.spawn_sceneobject_entity::<TextPane>("TextPane").expect("Could not spawn TextPane!");
 
         let mut last_rendered = Instant::now();
 
@@ -160,7 +162,7 @@ impl WinitApp for TextTextureExample {
             winit::event::WindowEvent::RedrawRequested => {
                 if Instant::now().duration_since(self.last_rendered) >= Duration::from_millis(16) {
                     // println!("draw");
-                    self.rm.gpu_state.update_resize();
+                    self.rm.update_resize();
                     self.rm.update_time();
                     self.rm.render(&self.camera);
 
@@ -1 +1,5 @@
-pub const TIME: u32 = 0;
\ No newline at end of file
+/// The `CameraUniform`: projection * view, it's inverse, and the camera position.
+pub const CAMERA: u32 = 0;
+
+/// The time in seconds since the renderer started.
+pub const TIME: u32 = 1;
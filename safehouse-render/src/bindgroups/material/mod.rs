@@ -0,0 +1,12 @@
+/// The material's parameters uniform block, when it has parameters.
+pub const PARAMS: u32 = 0;
+
+/// The texture of a material texture slot.
+pub const fn texture(slot: u32) -> u32 {
+    1 + slot * 2
+}
+
+/// The sampler of a material texture slot.
+pub const fn sampler(slot: u32) -> u32 {
+    2 + slot * 2
+}
@@ -1,3 +1,7 @@
+pub mod global;
+pub mod sceneobject;
+pub mod material;
+
 /// Bindings used by all shaders, governed by the render manager.\
 /// E.g: time, camera, debug flags, etc.
 pub const BINDGROUP_GLOBAL: u32 = 0;
@@ -5,4 +9,9 @@ pub const BINDGROUP_GLOBAL: u32 = 0;
 
 /// Bindings for the SceneObject.
 /// E.g: Model matrix
-pub const BINDGROUP_SCENEOBJECT: u32 = 1;
\ No newline at end of file
+pub const BINDGROUP_SCENEOBJECT: u32 = 1;
+
+
+/// Bindings for the object's material, in pipelines with an `EntityPipeline::material` layout.\
+/// E.g: Color parameters, textures
+pub const BINDGROUP_MATERIAL: u32 = 2;
\ No newline at end of file
@@ -0,0 +1,2 @@
+/// The SceneObject's model matrix.
+pub const MODEL_MATRIX: u32 = 0;
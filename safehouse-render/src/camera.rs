@@ -5,6 +5,32 @@ use std::f32::consts::PI;
 use std::ops::Add;
 use std::sync::atomic::Ordering;
 
+/// The camera data in the global bindgroup, laid out to match the WGSL struct:
+/// ```wgsl
+/// struct Camera {
+///     view_proj: mat4x4<f32>,
+///     inv_view_proj: mat4x4<f32>,
+///     position: vec4<f32>,
+/// }
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CameraUniform {
+    pub view_proj: glam::Mat4,
+    pub inv_view_proj: glam::Mat4,
+    pub position: glam::Vec4,
+}
+
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self {
+            view_proj: glam::Mat4::IDENTITY,
+            inv_view_proj: glam::Mat4::IDENTITY,
+            position: glam::Vec4::W,
+        }
+    }
+}
+
 pub struct Camera {
     pub aspect_ratio: f32,
     pub position: (f32, f32, f32),
@@ -32,7 +58,8 @@ impl Camera {
     pub fn new(width: f32, height: f32) -> Camera {
         // let mut model = glam::Mat4::IDENTITY;
         let mut view = glam::Mat4::IDENTITY;
-        let mut projection = glam::Mat4::perspective_infinite_lh(90f32.to_radians(), (width / height) as f32, 0.0);
+        // A near plane of zero would put every fragment at the same depth.
+        let mut projection = glam::Mat4::perspective_infinite_lh(90f32.to_radians(), (width / height) as f32, 0.01);
         // let mut PV = projection * view;
         // let mut PVM = PV * model;
         Camera {
@@ -182,6 +209,20 @@ impl Camera {
         self.projection * self.view * *model
     }
 
+    pub fn calc_pv(&self) -> glam::Mat4 {
+        self.projection * self.view
+    }
+
+    /// The camera data to upload to the global bindgroup.
+    pub fn uniform(&self) -> CameraUniform {
+        let view_proj = self.calc_pv();
+        CameraUniform {
+            view_proj,
+            inv_view_proj: view_proj.inverse(),
+            position: self.view.inverse().w_axis,
+        }
+    }
+
     pub fn lookat_upd8(&mut self) -> Mat4 {
         let res = glam::Mat4::look_at_lh(
             glam::Vec3::new(0.0, 0.0, 0.0),
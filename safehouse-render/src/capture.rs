@@ -0,0 +1,10 @@
+use std::path::PathBuf;
+
+/// What to do with a captured frame once it has been read back from the GPU.
+pub enum CaptureRequest {
+    /// Keep the frame in the `RenderManager` until it is taken with `take_captured_frame`.
+    Keep,
+
+    /// Save the frame as a PNG file.
+    SavePng(PathBuf),
+}
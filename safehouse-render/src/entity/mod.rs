@@ -1,5 +1,5 @@
-use std::{marker::PhantomData, num::NonZeroU32, rc::Rc};
-use crate::{gpu, model::{ModelData, ModelDataRes}, resource::ManagerResource, scene::{SceneObject, SceneObjectHandle}};
+use std::{marker::PhantomData, rc::Rc};
+use crate::{gpu, material::MaterialLayout, model::{ModelData, ModelDataRes}, resource::ManagerResource, scene::{RenderOrder, SceneObject, SceneObjectHandle}};
 use gpu::wgpu;
 use safehouse_gpu::{binding::{Bindable, Binder}, shaderprogram::Program};
 use constcat::*;
@@ -13,18 +13,151 @@ pub const SHADER_STR: &'static str = "_shader";
 
 pub struct EntityPipeline {
             pub primitive: wgpu::PrimitiveState,
+            /// The depth/stencil state, or `None` to use `DepthTexture::default_state()`.\
+            /// The format is always set to the manager's `DEPTH_FORMAT`.
             pub depth_stencil: Option<wgpu::DepthStencilState>,
-            // TODO: Multisample and Multiview support
-            // pub multisample: wgpu::MultisampleState,
-            // pub multiview: Option<NonZeroU32>
+            /// Draw all SceneObjects of this entity that share a model in one instanced draw call.\
+            /// The pipeline gets an `InstanceTransform` vertex buffer in slot 1, and the entity bindgroup of the first object is used for the whole batch.
+            pub instanced: bool,
+            /// The layer and transparency given to SceneObjects spawned with this pipeline.
+            pub order: RenderOrder,
+            /// How the fragment shader's `@location(0)` output is written to the render target.\
+            /// The sample count is the manager's, see `RenderManager::set_sample_count`.
+            pub color_target: EntityColorTarget,
+            /// The entry point of the vertex program.
+            pub vertex_entry: &'static str,
+            /// The entry point of the fragment program.
+            pub fragment_entry: &'static str,
+            /// The layout of the materials SceneObjects of this pipeline are drawn with, bound at `BINDGROUP_MATERIAL`.
+            pub material: Option<MaterialLayout>,
 }
 
+impl Default for EntityPipeline {
+    fn default() -> Self {
+        Self {
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            instanced: false,
+            order: RenderOrder::default(),
+            color_target: EntityColorTarget::default(),
+            vertex_entry: "vs_main",
+            fragment_entry: "fs_main",
+            material: None,
+        }
+    }
+}
+
+/// How a fragment's color is combined with the color already in the target.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Replace the target color.
+    #[default]
+    Opaque,
+
+    /// Blend by the fragment's alpha, e.g. for text panes.
+    Alpha,
+
+    /// Add the fragment's color, scaled by it's alpha, e.g. for particles and glow.
+    Additive,
+
+    /// Blend a color that has already been multiplied by it's alpha.
+    Premultiplied,
+}
+
+impl BlendMode {
+    pub fn blend_state(&self) -> Option<wgpu::BlendState> {
+        match self {
+            BlendMode::Opaque => None,
+            BlendMode::Alpha => Some(wgpu::BlendState::ALPHA_BLENDING),
+            BlendMode::Additive => Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            }),
+            BlendMode::Premultiplied => Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+        }
+    }
+}
+
+/// The blend mode and write mask of an `EntityPipeline`, in the render target's format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EntityColorTarget {
+    pub blend: BlendMode,
+    pub write_mask: wgpu::ColorWrites,
+}
+
+impl Default for EntityColorTarget {
+    fn default() -> Self {
+        Self {
+            blend: BlendMode::Opaque,
+            write_mask: wgpu::ColorWrites::ALL,
+        }
+    }
+}
+
+impl EntityColorTarget {
+    /// A target with the given blend mode, writing every channel.
+    pub fn blended(blend: BlendMode) -> Self {
+        Self {
+            blend,
+            ..Default::default()
+        }
+    }
+
+    pub fn target_state(&self, surface_format: wgpu::TextureFormat) -> wgpu::ColorTargetState {
+        wgpu::ColorTargetState {
+            format: surface_format,
+            blend: self.blend.blend_state(),
+            write_mask: self.write_mask,
+        }
+    }
+}
+
+impl EntityPipeline {
+    /// The depth/stencil state to build this pipeline with, matching the main render pass.
+    pub fn depth_stencil_or_default(&self) -> wgpu::DepthStencilState {
+        match &self.depth_stencil {
+            Some(state) => wgpu::DepthStencilState {
+                format: gpu::texture::DEPTH_FORMAT,
+                ..state.clone()
+            },
+            None => gpu::texture::DepthTexture::default_state(),
+        }
+    }
+}
+
+/// The shader programs an entity's pipeline is built with, returned by `Entity::load_shader`.\
+/// New programs are added to the manager under their label, or the entity's shader name if they have none.
 pub enum EntityShaderEntry {
+    /// Separate vertex and fragment programs, e.g. a shared vertex program with a fragment program per entity.\
+    /// New programs without a label are named with a `_vertex` or `_fragment` suffix.
     Separate { vertex: LoadTimeResource<Program>, fragment: LoadTimeResource<Program> },
+
+    /// One program with both entry points.
     Combined(LoadTimeResource<Program>)
 }
 
-// TODO: implement resource load error type
+impl EntityShaderEntry {
+    /// Use a newly built program for both entry points.
+    pub fn new(program: Program) -> Self {
+        Self::Combined(LoadTimeResource::LoadNew(program))
+    }
+}
+
+impl From<Program> for EntityShaderEntry {
+    fn from(program: Program) -> Self {
+        Self::new(program)
+    }
+}
+
+/// A resource given to the manager at load time. Looking it up returns a `SafehouseError` if it is missing.
 pub enum LoadTimeResource<T: ManagerResource> {
     /// Attempt to load the default for this type, if there is one.
     UseDefault,
@@ -106,13 +239,18 @@ pub trait Entity {
     /// Therefore this call only initializes the Entity on the CPU-side, although buffers can be written to at this point anyway. 
     fn on_instantiate(rm: &mut crate::RenderManager, handle: SceneObjectHandle) -> Self;
 
+    /// Functionality for when an Entity instance is despawned, before it's SceneObject is removed.
+    fn on_despawn(self, rm: &mut crate::RenderManager) where Self: Sized {
+        let _ = rm;
+    }
+
     // Data Loading
     fn load_bindings<'a>() -> Vec<Binder<Self>> where Self: Sized;
-    fn load_model(state: &gpu::State) -> ModelData;
+    fn load_model(state: &gpu::State) -> crate::SafehouseResult<ModelData>;
     fn load_pipeline(rm: &crate::RenderManager) -> Option<EntityPipeline>;
 
-    // TODO: return an array of specifically Vertex and Fragment references
-    fn load_shader(rm: &crate::RenderManager, group_model: u32, group_entity: u32) -> Option<gpu::shaderprogram::Program>;
+    /// The programs to build the entity's pipeline with, or `None` to use a program already added under `shader_name()`.
+    fn load_shader(rm: &crate::RenderManager, group_model: u32, group_entity: u32) -> Option<EntityShaderEntry>;
 
     // TODO: use ENTITY_TYPE_NAME to generate these strings, use an external const fn?
 }
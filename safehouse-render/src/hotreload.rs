@@ -0,0 +1,93 @@
+// Reloading shaders from disk while developing.
+
+use std::{cell::RefCell, collections::HashMap, path::{Path, PathBuf}, time::SystemTime};
+
+/// A shader source file that is rebuilt when it changes.
+pub(crate) struct WatchedShader {
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+    pub group_model: u32,
+    pub group_entity: u32,
+}
+
+/// Watches shader source files by polling their modification times.\
+/// Shaders are registered while building them, which only borrows the manager, so the watch list is kept in a `RefCell`.
+#[derive(Default)]
+pub(crate) struct ShaderReloader {
+    pub enabled: bool,
+    shaders: RefCell<HashMap<String, WatchedShader>>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl ShaderReloader {
+    /// Start watching the shader `name` at `path`, replacing any previous file for that name.
+    pub fn watch(&self, name: &str, path: &Path, group_model: u32, group_entity: u32) {
+        self.shaders.borrow_mut().insert(String::from(name), WatchedShader {
+            path: path.to_path_buf(),
+            modified: modified(path),
+            group_model,
+            group_entity,
+        });
+    }
+
+    /// The names of shaders whose files were modified since the last poll.\
+    /// Files that can't be read are skipped, so a shader being saved is picked up on a later poll.
+    pub fn poll(&self) -> Vec<String> {
+        let mut changed = vec![];
+        for (name, shader) in self.shaders.borrow_mut().iter_mut() {
+            let current = modified(&shader.path);
+            if current.is_some() && current != shader.modified {
+                shader.modified = current;
+                changed.push(name.clone());
+            }
+        }
+        changed
+    }
+
+    /// Read the current source of a watched shader, along with it's bindgroup indices.
+    pub fn read(&self, name: &str) -> Option<std::io::Result<(String, u32, u32)>> {
+        let shaders = self.shaders.borrow();
+        let shader = shaders.get(name)?;
+        Some(std::fs::read_to_string(&shader.path).map(|source| (source, shader.group_model, shader.group_entity)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Write `source` to `path` and give it a modification time, so the test doesn't depend on the filesystem's timestamp resolution.
+    fn write(path: &Path, source: &str, modified: SystemTime) {
+        std::fs::write(path, source).unwrap();
+        std::fs::File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    }
+
+    #[test]
+    fn poll_reports_each_change_once() {
+        let path = std::env::temp_dir().join(format!("safehouse_hotreload_{}.wgsl", std::process::id()));
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        write(&path, "// first", start);
+
+        let reloader = ShaderReloader::default();
+        reloader.watch("shader", &path, 1, 3);
+        assert!(reloader.poll().is_empty(), "An unchanged file is not reported");
+
+        write(&path, "// second", start + Duration::from_secs(1));
+        assert_eq!(reloader.poll(), ["shader"]);
+        assert!(reloader.poll().is_empty(), "A change is only reported on the first poll after it");
+        assert_eq!(reloader.read("shader").unwrap().unwrap(), (String::from("// second"), 1, 3));
+        assert!(reloader.read("other").is_none());
+
+        // A missing file is skipped until it's back
+        std::fs::remove_file(&path).unwrap();
+        assert!(reloader.poll().is_empty());
+        write(&path, "// third", start + Duration::from_secs(2));
+        assert_eq!(reloader.poll(), ["shader"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
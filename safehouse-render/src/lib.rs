@@ -12,6 +12,11 @@ pub mod bindgroups;
 pub mod resource;
 pub mod texturetype;
 pub mod binding;
+pub mod capture;
+pub mod material;
+
+mod pipeline;
+mod hotreload;
 
 mod manager;
 
@@ -1,36 +1,76 @@
 pub use super::bindgroups::{
     BINDGROUP_GLOBAL,
     BINDGROUP_SCENEOBJECT,
+    BINDGROUP_MATERIAL,
 };
 use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::{collections::HashMap, num::NonZeroU64, rc::Rc, time::Instant};
+use crate::bindgroups::{global, sceneobject};
+use std::{collections::{HashMap, HashSet}, num::NonZeroU64, rc::Rc, time::Instant};
 
+use crate::capture::CaptureRequest;
+use crate::hotreload::ShaderReloader;
+use crate::pipeline::{PipelineCache, PipelineRecipe, RecipeStage};
+use crate::material::{Material, MaterialBinding, MaterialLayout, MaterialTexture};
 use crate::texturetype::{DynamicTexture, DynamicTextureHandle};
 // use crate::bindgroups::BINDGROUP_SHADER;
-use crate::{camera::Camera, resource::ManagerResource};
-use crate::entity::{Entity, NamedEntity};
-use gpu::{buffer::{Buffer, UniformPtr}, program, shaderprogram::Program, vertex::Vertex};
+use crate::{camera::{Camera, CameraUniform}, resource::ManagerResource};
+use crate::entity::{Entity, EntityShaderEntry, LoadTimeResource, NamedEntity};
+use gpu::{buffer::{Buffer, UniformPtr}, program, shaderprogram::Program, texture::{DepthTexture, MultisampleTexture}, vertex::Vertex};
 use safehouse_gpu::buffer::Uniform;
 use crate::model::ModelData;
 
 pub use safehouse_gpu as gpu;
+pub use gpu::error::{SafehouseError, SafehouseResult};
+pub use safehouse_shader::ShaderBuilder;
 pub use glam; 
-use crate::scene::{SceneObject, SceneObjectHandle};
+use crate::scene::{InstanceBatch, InstanceBatchKey, RenderLayer, RenderOrder, SceneObject, SceneObjectHandle};
+use crate::vertex_type::InstanceTransform;
 
 use gpu::wgpu;
 use tagmap::TagMap;
 
+/// A single draw in the main render pass.
+enum SceneDraw {
+    /// Draw one SceneObject.
+    Single(SceneObjectHandle),
+
+    /// Draw a batch of instanced SceneObjects, using the bindgroups of the first one.
+    Instanced(SceneObjectHandle, InstanceBatchKey),
+}
+
+impl SceneDraw {
+    fn handle(&self) -> SceneObjectHandle {
+        match self {
+            SceneDraw::Single(handle) | SceneDraw::Instanced(handle, _) => *handle,
+        }
+    }
+}
+
 pub struct RenderManager {
 
-    pub window: Arc<gpu::winit::window::Window>,
+    /// The window being rendered to, or `None` when rendering headless.
+    pub window: Option<Arc<gpu::winit::window::Window>>,
     
     /// The GPU backend state.
     pub gpu_state: gpu::State,
 
+    /// The depth buffer for the main render pass, sized to the render target.
+    depth_texture: DepthTexture,
+
+    /// The number of samples per pixel in the main render pass. 1 disables MSAA.
+    sample_count: u32,
+
+    /// The multisampled color target that is resolved into the frame, when MSAA is enabled.
+    msaa_texture: Option<MultisampleTexture>,
+
     /// The default fallback rendering pipeline.
     pub default_pipeline: Rc<wgpu::RenderPipeline>,
 
+    /// The layout of the default pipeline, kept to rebuild it when the sample count changes.
+    default_pipelayout: Rc<wgpu::PipelineLayout>,
+
     // TODO: The default fallback model data.
     // pub default_pipeline: Rc<ModelData>,
 
@@ -55,8 +95,47 @@ pub struct RenderManager {
     /// All objects to render.
     scene_objects: TagMap<SceneObject>,
 
+    /// The number of slots `scene_objects` has handed out, so handles can be bounds-checked before indexing.
+    scene_slot_count: usize,
+
+    /// Slots of removed SceneObjects, reused before the TagMap grows.
+    free_scene_slots: Vec<usize>,
+
     /// All objects to render.
-    scene_queue: Vec<usize>,
+    scene_queue: Vec<SceneObjectHandle>,
+
+    /// The generation given to the next SceneObject, used to detect stale handles.
+    next_generation: u64,
+
+    /// Names of pipelines that draw their SceneObjects with instancing.
+    instanced_pipelines: HashSet<String>,
+
+    /// The render order given to SceneObjects spawned with each pipeline.
+    pipeline_orders: HashMap<String, RenderOrder>,
+
+    /// Per-instance transform buffers, for each batch of instanced SceneObjects.
+    instance_batches: HashMap<InstanceBatchKey, InstanceBatch>,
+
+    /// Composes entity shaders from shared modules. Add modules here to make them includable by every entity.
+    pub shader_builder: ShaderBuilder,
+
+    /// Shader files watched for changes, when hot reload is enabled.
+    shader_reloader: ShaderReloader,
+
+    /// How each entity pipeline was built, so it can be rebuilt when it's shader changes.
+    pipeline_recipes: HashMap<String, PipelineRecipe>,
+
+    /// Entity pipelines and layouts, shared between entities with the same descriptor.
+    pipeline_cache: PipelineCache,
+
+    /// The material layout of each pipeline that has one.
+    pipeline_materials: HashMap<String, MaterialLayout>,
+
+    /// Bindgroup layouts for each material layout in use.
+    material_bglayouts: HashMap<MaterialLayout, Rc<wgpu::BindGroupLayout>>,
+
+    /// The material given to new SceneObjects of a pipeline with each material layout: zeroed parameters and white textures.
+    default_materials: HashMap<MaterialLayout, Rc<MaterialBinding>>,
 
     /// Cache for currently loaded shaders.
     /// TODO: Implement Shader functionalities
@@ -68,36 +147,65 @@ pub struct RenderManager {
 
     pub last_render_instant: Instant,
 
-    pub global_pvm: Rc<Uniform<glam::Mat4>>,
+    /// The camera's projection * view matrix, it's inverse and position, shared by all objects.
+    pub global_camera: Rc<Uniform<CameraUniform>>,
 
     pub dynamic_textures: TagMap<DynamicTexture>,
 
-    dyntexture_queue: VecDeque<DynamicTextureHandle>
+    dyntexture_queue: VecDeque<DynamicTextureHandle>,
+
+    /// A pending request to capture the next rendered frame.
+    capture_request: Option<CaptureRequest>,
+
+    /// The last frame captured with `CaptureRequest::Keep`, or why it couldn't be.
+    captured_frame: Option<SafehouseResult<gpu::image::RgbaImage>>,
 
 }
 
 impl RenderManager {
     pub fn new(window: &Arc<gpu::winit::window::Window>) -> Self {
-        let mut gpu_state = gpu::State::new(window);
+        Self::from_state(gpu::State::new(window), Some(Arc::clone(window)))
+    }
+
+    /// Create a `RenderManager` that renders into an offscreen texture, without a window or surface.
+    pub fn new_headless(width: u32, height: u32) -> Self {
+        Self::from_state(gpu::State::new_headless(width, height), None)
+    }
+
+    /// Create a headless `RenderManager`, returning an error if no adapter or device is available, e.g. to skip tests on machines without one.
+    pub fn try_new_headless(width: u32, height: u32) -> SafehouseResult<Self> {
+        Ok(Self::from_state(gpu::State::try_new_headless(width, height)?, None))
+    }
+
+    /// Create a `RenderManager` for a window, with the adapter, device and surface options in `desc`.
+    pub fn with_descriptor(window: &Arc<gpu::winit::window::Window>, desc: &gpu::descriptor::StateDescriptor) -> SafehouseResult<Self> {
+        Ok(Self::from_state(gpu::State::with_descriptor(window, desc)?, Some(Arc::clone(window))))
+    }
+
+    /// Create a headless `RenderManager` with the adapter and device options in `desc`.
+    pub fn headless_with_descriptor(width: u32, height: u32, desc: &gpu::descriptor::StateDescriptor) -> SafehouseResult<Self> {
+        Ok(Self::from_state(gpu::State::headless_with_descriptor(width, height, desc)?, None))
+    }
+
+    fn from_state(mut gpu_state: gpu::State, window: Option<Arc<gpu::winit::window::Window>>) -> Self {
 
-        // let global_pvm = UniformPtr::new(&gpu_state, glam::Mat4::IDENTITY);
-        let global_pvm = Uniform::new(&gpu_state, &[glam::Mat4::IDENTITY]);
+        let global_camera = Uniform::new(&gpu_state, &[CameraUniform::default()]);
 
         let global_bglayout = Rc::new(gpu_state.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
                 wgpu::BindGroupLayoutEntry {
-                    binding: 0,
+                    binding: global::CAMERA,
                     visibility: wgpu::ShaderStages::all(),
                     ty: wgpu::BindingType::Buffer { 
                         ty: wgpu::BufferBindingType::Uniform, 
                         has_dynamic_offset: false, 
-                        min_binding_size: Some(NonZeroU64::new(std::mem::size_of::<glam::Mat4>() as u64).unwrap())
+                        min_binding_size: Some(NonZeroU64::new(std::mem::size_of::<CameraUniform>() as u64).unwrap())
                     },
                     count: None,
                 },
                 wgpu::BindGroupLayoutEntry {
-                    binding: 1,
+                    binding: global::TIME,
                     visibility: wgpu::ShaderStages::all(),
                     ty: wgpu::BindingType::Buffer { 
                         ty: wgpu::BufferBindingType::Uniform, 
@@ -113,7 +221,7 @@ impl RenderManager {
             label: None,
             entries: &[
                 wgpu::BindGroupLayoutEntry {
-                    binding: 0,
+                    binding: sceneobject::MODEL_MATRIX,
                     visibility: wgpu::ShaderStages::all(),
                     ty: wgpu::BindingType::Buffer { 
                         ty: wgpu::BufferBindingType::Uniform, 
@@ -125,20 +233,26 @@ impl RenderManager {
             ],
         }));
 
-        let default_pipelayout = gpu_state.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { 
+        let default_pipelayout = Rc::new(gpu_state.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { 
             label: Some("default_pipelayout"), 
             bind_group_layouts: &[
                 global_bglayout.as_ref(),
                 sceneobj_bglayout.as_ref()
             ], 
             push_constant_ranges: &[] 
-        });
+        }));
 
-        let shader = gpu_state.add_shader("default", program!(
+        gpu_state.add_shader("default", program!(
             &gpu_state,
             source: format!("
+                struct Camera {{
+                    view_proj: mat4x4<f32>,
+                    inv_view_proj: mat4x4<f32>,
+                    position: vec4<f32>,
+                }}
+
                 @group({BINDGROUP_GLOBAL}) @binding(0)
-                var<uniform> pvm: mat4x4<f32>;
+                var<uniform> camera: Camera;
                 @group({BINDGROUP_GLOBAL}) @binding(1)
                 var<uniform> time: f32;
 
@@ -160,7 +274,7 @@ impl RenderManager {
                     var o: ColorVertexOutput;
                     o.color = i.color;
                     var t = time;
-                    o.pos = pvm * vec4<f32>(i.pos.x, i.pos.y+(sin(time)*0.1), i.pos.z, i.pos.w);
+                    o.pos = camera.view_proj * obj_mat * vec4<f32>(i.pos.x, i.pos.y+(sin(time)*0.1), i.pos.z, i.pos.w);
                     return o;
                 }}
 
@@ -171,35 +285,7 @@ impl RenderManager {
             ")
         ));
 
-        let default_pipeline = gpu_state.add_render_pipeline("default", &wgpu::RenderPipelineDescriptor { 
-            label: None, 
-            layout: Some(&default_pipelayout), 
-            vertex: wgpu::VertexState { 
-                module: &shader.module, 
-                entry_point: Some("vs_main"), 
-                buffers: &[crate::vertex_type::ColorVertex::desc().clone()],
-                compilation_options: Default::default(), 
-            }, 
-            primitive: wgpu::PrimitiveState { 
-                topology: wgpu::PrimitiveTopology::TriangleList, 
-                polygon_mode: wgpu::PolygonMode::Fill,
-                front_face: wgpu::FrontFace::Cw,
-                ..Default::default()
-            }, 
-
-            depth_stencil: None, 
-            multisample: wgpu::MultisampleState::default(), 
-            fragment: Some(wgpu::FragmentState { 
-                module: &shader.module, 
-                entry_point: Some("fs_main"), 
-                targets: &[
-                    Some(wgpu::ColorTargetState { format: gpu_state.config.format.clone(), blend: None, write_mask: wgpu::ColorWrites::ALL })
-                ],
-                compilation_options: Default::default(), 
-            }), 
-            multiview: None,
-            cache: None
-        });
+        let default_pipeline = Self::create_default_pipeline(&mut gpu_state, &default_pipelayout, 1);
 
         gpu_state.add_sampler("default", &wgpu::SamplerDescriptor { 
             label: Some("default"), 
@@ -213,11 +299,11 @@ impl RenderManager {
             layout: &global_bglayout,
             entries: &[
                 wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: global_pvm.get_buffer().as_entire_binding(),
+                    binding: global::CAMERA,
+                    resource: global_camera.get_buffer().as_entire_binding(),
                 },
                 wgpu::BindGroupEntry {
-                    binding: 1,
+                    binding: global::TIME,
                     resource: time.get_buffer().as_entire_binding(),
                 },
             ],
@@ -225,6 +311,8 @@ impl RenderManager {
 
         let start_instant = Instant::now();
 
+        let depth_texture = DepthTexture::new(&gpu_state);
+
 
         // let mut controllers = TagMap::new();
 
@@ -237,29 +325,83 @@ impl RenderManager {
             gpu_state,
             // context,
             scene_objects: TagMap::new(),
+            scene_slot_count: 0,
+            free_scene_slots: vec![],
             scene_queue: vec![],
+            next_generation: 1,
+            instanced_pipelines: HashSet::new(),
+            pipeline_orders: HashMap::new(),
+            instance_batches: HashMap::new(),
             // shader_cache: HashMap::new(),
             time,
             default_pipeline,
-            window: Arc::clone(window),
+            default_pipelayout,
+            depth_texture,
+            sample_count: 1,
+            msaa_texture: None,
+            window,
             start_instant,
             last_render_instant: Instant::now(),
             entity_bglayout_cache: HashMap::new(),
             dynamic_textures: TagMap::new(),
             dyntexture_queue: VecDeque::new(),
-            global_pvm,
+            global_camera,
+            capture_request: None,
+            captured_frame: None,
+            shader_builder: crate::shader::default_builder(),
+            shader_reloader: ShaderReloader::default(),
+            pipeline_recipes: HashMap::new(),
+            pipeline_cache: PipelineCache::default(),
+            pipeline_materials: HashMap::new(),
+            material_bglayouts: HashMap::new(),
+            default_materials: HashMap::new(),
         }
     }
 
+    /// Create the default fallback pipeline, for a render pass with `sample_count` samples.
+    fn create_default_pipeline(gpu_state: &mut gpu::State, pipe_layout: &wgpu::PipelineLayout, sample_count: u32) -> Rc<wgpu::RenderPipeline> {
+        let shader = Rc::clone(gpu_state.get_shader("default").expect("The default shader is added before the default pipeline."));
+        gpu_state.add_render_pipeline("default", &wgpu::RenderPipelineDescriptor { 
+            label: None, 
+            layout: Some(pipe_layout), 
+            vertex: wgpu::VertexState { 
+                module: &shader.module, 
+                entry_point: Some("vs_main"), 
+                buffers: &[crate::vertex_type::ColorVertex::desc().clone()],
+                compilation_options: Default::default(), 
+            }, 
+            primitive: wgpu::PrimitiveState { 
+                topology: wgpu::PrimitiveTopology::TriangleList, 
+                polygon_mode: wgpu::PolygonMode::Fill,
+                front_face: wgpu::FrontFace::Cw,
+                ..Default::default()
+            }, 
+
+            depth_stencil: Some(DepthTexture::default_state()), 
+            multisample: wgpu::MultisampleState { count: sample_count, ..Default::default() }, 
+            fragment: Some(wgpu::FragmentState { 
+                module: &shader.module, 
+                entry_point: Some("fs_main"), 
+                targets: &[
+                    Some(wgpu::ColorTargetState { format: gpu_state.config.format.clone(), blend: None, write_mask: wgpu::ColorWrites::ALL })
+                ],
+                compilation_options: Default::default(), 
+            }), 
+            multiview: None,
+            cache: None
+        })
+    }
+
     /// Update the amount of time elapsed since the renderer started.
     pub fn update_time(&mut self) {
         *self.time.as_mut() = self.start_instant.elapsed().as_secs_f32();
     }
 
-    /// Calculate and update the global PVM matrix using a `Camera` (PV) and a model (M)
-    pub fn update_pvm(&self, camera: &Camera, model: &glam::Mat4) {
-        self.global_pvm.update(&self.gpu_state, &[
-            camera.calc_pvm(model)
+    /// Update the global camera uniform (projection * view) from a `Camera`.\
+    /// The model matrix of each object is applied in the shader from the SceneObject bindgroup.
+    pub fn update_camera(&self, camera: &Camera) {
+        self.global_camera.update(&self.gpu_state, &[
+            camera.uniform()
         ]);
     }
 
@@ -290,10 +432,286 @@ impl RenderManager {
         }
     }
 
+    /// Capture the next rendered frame, to be retrieved with `take_captured_frame`.
+    pub fn capture_next_frame(&mut self) {
+        self.capture_request = Some(CaptureRequest::Keep);
+    }
+
+    /// Save the next rendered frame as a PNG file.
+    pub fn save_next_frame(&mut self, path: impl Into<PathBuf>) {
+        self.capture_request = Some(CaptureRequest::SavePng(path.into()));
+    }
+
+    /// Take the last frame captured with `capture_next_frame`, or the error if it couldn't be read back.
+    pub fn take_captured_frame(&mut self) -> Option<SafehouseResult<gpu::image::RgbaImage>> {
+        self.captured_frame.take()
+    }
+
+    /// Whether frames can be captured, which needs a surface with `COPY_SRC` and an 8 bit RGBA/BGRA format.
+    pub fn can_capture(&self) -> bool {
+        self.gpu_state.config.usage.contains(wgpu::TextureUsages::COPY_SRC)
+            && gpu::readback::TextureReadback::supports_format(self.gpu_state.config.format)
+    }
+
+    /// Render a frame and return it's pixels.
+    pub fn render_to_image(&mut self, camera: &Camera) -> SafehouseResult<gpu::image::RgbaImage> {
+        self.capture_next_frame();
+        self.render(camera);
+        self.take_captured_frame()
+            .unwrap_or_else(|| Err(SafehouseError::Readback(String::from("the frame was not captured"))))
+    }
+
+    /// Group instanced SceneObjects into batches and upload their transforms.\
+    /// Returns the draws for this frame sorted by their `SortKey`, where each batch is sorted by it's first object.
+    fn prepare_draws(&mut self, camera: &Camera) -> Vec<SceneDraw> {
+        let mut draws = vec![];
+        let mut batches: HashMap<InstanceBatchKey, Vec<InstanceTransform>> = HashMap::new();
+
+        for handle in &self.scene_queue {
+            let obj = self.get_scene_object(*handle).unwrap();
+            if !obj.visible {
+                continue;
+            }
+
+            if !obj.instanced {
+                draws.push(SceneDraw::Single(*handle));
+                continue;
+            }
+
+            let key = obj.instance_batch_key();
+            match batches.get_mut(&key) {
+                Some(transforms) => transforms.push(obj.instance_transform()),
+                None => {
+                    batches.insert(key, vec![obj.instance_transform()]);
+                    draws.push(SceneDraw::Instanced(*handle, key));
+                },
+            }
+        }
+
+        // Drop buffers for batches that no longer have any objects
+        self.instance_batches.retain(|key, _| batches.contains_key(key));
+
+        for (key, transforms) in batches {
+            self.instance_batches
+                .entry(key)
+                .or_insert_with(|| InstanceBatch::new(&self.gpu_state, transforms.len()))
+                .write(&self.gpu_state, &transforms);
+        }
+
+        let mut keyed: Vec<_> = draws.into_iter()
+            .map(|draw| (self.get_scene_object(draw.handle()).unwrap().sort_key(&camera.view), draw))
+            .collect();
+        keyed.sort_by_key(|(key, _)| *key);
+
+        keyed.into_iter().map(|(_, draw)| draw).collect()
+    }
+
+    /// Record the draws for the SceneObjects in `draws`, in order.
+    fn draw_scene<'pass>(&'pass self, renderpass: &mut wgpu::RenderPass<'pass>, draws: &[SceneDraw]) {
+        // Render each SceneObject
+        for draw in draws {
+
+            let (objhandle, batch) = match draw {
+                SceneDraw::Single(handle) => (handle, None),
+                SceneDraw::Instanced(handle, key) => (handle, self.instance_batches.get(key)),
+            };
+
+            // Get object reference
+            let obj = self.get_scene_object(*objhandle).unwrap();
+
+            // Update model matrix only at render time (now)
+            obj.update_matrix(self);
+            
+            // Set the SceneObject bindgroup for this object
+            renderpass.set_bind_group(BINDGROUP_SCENEOBJECT, obj.sceneobject_bindgroup.as_ref(), &[]);
+
+            let mut curbg_id = BINDGROUP_SCENEOBJECT+1;
+
+            // Set the material BG if the pipeline has one
+            if let Some(material) = obj.material.as_ref() {
+                renderpass.set_bind_group(BINDGROUP_MATERIAL, &*material.bindgroup.borrow(), &[]);
+                curbg_id = BINDGROUP_MATERIAL+1;
+            }
+            
+            // Set model BG if there is one
+            if let Some(mbg) = obj.model_data.binding.as_ref() {
+                renderpass.set_bind_group(curbg_id,&mbg.bindgroup, &[]);
+                curbg_id +=1;
+
+            }
+
+            // Entity BG should only be active if it's model is, otherwise it wouldn't make sense to use the shader.
+            if let Some(ebg) = obj.entity_bindgroup.as_ref() {
+                renderpass.set_bind_group(curbg_id, ebg.as_ref(), &[]);
+            }
+
+            // TODO: impl shader bindgroup
+
+            // Set the model's vertex buffer
+            renderpass.set_vertex_buffer(0, obj.model_data.vertex_buffer.buffer.slice(..));
+
+            // Set the per-instance transforms if this is a batch
+            let instances = match batch {
+                Some(batch) => {
+                    renderpass.set_vertex_buffer(1, batch.buffer.slice(..));
+                    0..batch.count
+                },
+                None => 0..1,
+            };
+            
+            // Set the entity's pipeline type
+            renderpass.set_pipeline(obj.pipeline_ref.as_ref().unwrap_or(&self.default_pipeline));
+
+            // Groups with their own material switch to it, and the rest switch back to the object's material
+            let set_group_material = |renderpass: &mut wgpu::RenderPass<'pass>, group: usize| {
+                if obj.model_data.group_bindings.is_empty() {
+                    return;
+                }
+                if let Some(material) = obj.model_data.group_binding(group).or(obj.material.as_ref()) {
+                    renderpass.set_bind_group(BINDGROUP_MATERIAL, &*material.bindgroup.borrow(), &[]);
+                }
+            };
+
+            // Render each group of vertices
+            match obj.model_data.index_buffer.as_ref() {
+                Some(indices) => {
+                    renderpass.set_index_buffer(indices.buffer.slice(..), indices.format);
+                    for (i, group) in obj.model_data.groups.iter().cloned().enumerate() {
+                        set_group_material(renderpass, i);
+                        renderpass.draw_indexed(group, 0, instances.clone());
+                    }
+                },
+                None => {
+                    for (i, group) in obj.model_data.groups.iter().cloned().enumerate() {
+                        set_group_material(renderpass, i);
+                        renderpass.draw(group, instances.clone());
+                    }
+                },
+            }
+            
+        }
+    }
+
+    /// Apply any pending resize to the render target, and resize the depth and multisampled buffers to match.
+    pub fn update_resize(&mut self) {
+        let resized = self.gpu_state.update_resize();
+
+        if resized || self.depth_texture.is_outdated(&self.gpu_state) || self.depth_texture.sample_count() != self.sample_count {
+            self.depth_texture = DepthTexture::new_multisampled(&self.gpu_state, self.sample_count);
+        }
+
+        let msaa_outdated = match &self.msaa_texture {
+            Some(msaa) => resized || msaa.is_outdated(&self.gpu_state) || msaa.sample_count() != self.sample_count,
+            None => self.sample_count > 1,
+        };
+        if msaa_outdated {
+            self.msaa_texture = (self.sample_count > 1).then(|| MultisampleTexture::new(&self.gpu_state, self.sample_count));
+        }
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Set the number of samples per pixel for MSAA, where 1 disables it. Common values are 1 and 4.\
+    /// Pipelines of entities that are already loaded are rebuilt with the new sample count.\
+    /// If the device doesn't support the sample count or any pipeline fails to build, nothing is changed.
+    pub fn set_sample_count(&mut self, sample_count: u32) -> SafehouseResult<()> {
+        if !(self.gpu_state.supports_sample_count(self.gpu_state.config.format, sample_count)
+            && self.gpu_state.supports_sample_count(gpu::texture::DEPTH_FORMAT, sample_count))
+        {
+            return Err(SafehouseError::UnsupportedSampleCount(sample_count));
+        }
+
+        if sample_count == self.sample_count {
+            return Ok(());
+        }
+
+        let rebuilt = self.pipeline_recipes.values()
+            .map(|recipe| {
+                let mut recipe = recipe.clone();
+                recipe.multisample.count = sample_count;
+                let pipe = self.pipeline_cache.pipeline(&self.gpu_state, &recipe)?;
+                Ok((recipe, pipe))
+            })
+            .collect::<SafehouseResult<Vec<_>>>();
+
+        let pipelines = match rebuilt {
+            Ok(pipelines) => pipelines,
+            Err(e) => {
+                // Drop any pipelines that were built before the failure
+                self.pipeline_cache.prune();
+                return Err(e);
+            },
+        };
+
+        self.sample_count = sample_count;
+
+        let default_pipeline = Self::create_default_pipeline(&mut self.gpu_state, &self.default_pipelayout, sample_count);
+        let old_default = std::mem::replace(&mut self.default_pipeline, Rc::clone(&default_pipeline));
+
+        // Entities without a pipeline of their own share the default one under their pipeline name
+        let default_names = self.gpu_state.render_pipelines.iter()
+            .filter(|(_, pipe)| Rc::ptr_eq(pipe, &old_default))
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        for name in default_names {
+            self.gpu_state.insert_render_pipeline(&name, Rc::clone(&default_pipeline));
+        }
+        self.replace_scene_pipelines(&old_default, &default_pipeline);
+
+        for (recipe, pipe) in pipelines {
+            let pipeline_name = recipe.pipeline_name.clone();
+            self.pipeline_recipes.insert(pipeline_name.clone(), recipe);
+            if let Some(old) = self.gpu_state.insert_render_pipeline(&pipeline_name, Rc::clone(&pipe)) {
+                self.replace_scene_pipelines(&old, &pipe);
+            }
+        }
+
+        self.pipeline_cache.prune();
+        self.update_resize();
+
+        Ok(())
+    }
+
+    /// Point spawned SceneObjects drawn with `old` at `new`.
+    fn replace_scene_pipelines(&mut self, old: &Rc<wgpu::RenderPipeline>, new: &Rc<wgpu::RenderPipeline>) {
+        for handle in self.scene_queue.clone() {
+            if let Some(obj) = self.mut_scene_object(handle) {
+                if obj.pipeline_ref.as_ref().is_some_and(|p| Rc::ptr_eq(p, old)) {
+                    obj.pipeline_ref = Some(Rc::clone(new));
+                }
+            }
+        }
+    }
+
     pub fn render<'pass>(&mut self, camera: &Camera) {
 
+        self.update_resize();
+
+        if self.shader_reloader.enabled {
+            self.reload_shaders();
+        }
+
+        // update globals
+        self.time.update(&self.gpu_state);
+        self.update_camera(camera);
+
+        let draws = self.prepare_draws(camera);
+        let overlay_start = draws.iter()
+            .position(|draw| self.get_scene_object(draw.handle()).unwrap().order.layer == RenderLayer::Overlay)
+            .unwrap_or(draws.len());
+        let (scene_draws, overlay_draws) = draws.split_at(overlay_start);
+
         let mut cmd = self.gpu_state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
-        let surfacetexture = self.gpu_state.surface.get_current_texture().unwrap();
+        let surfacetexture = self.gpu_state.get_current_frame();
+
+        // With MSAA, draw into the multisampled texture and resolve it into the frame
+        let view = surfacetexture.create_view();
+        let (color_view, resolve_target) = match &self.msaa_texture {
+            Some(msaa) => (&msaa.view, Some(&view)),
+            None => (&view, None),
+        };
         {
 
             while !self.dyntexture_queue.is_empty() {
@@ -309,16 +727,22 @@ impl RenderManager {
                 };
             }
 
-            let view = surfacetexture.texture.create_view(&wgpu::TextureViewDescriptor::default());
             let mut renderpass = cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[
-                    Some(wgpu::RenderPassColorAttachment { view: &view, resolve_target: None, ops: wgpu::Operations{
+                    Some(wgpu::RenderPassColorAttachment { view: color_view, resolve_target, ops: wgpu::Operations{
                         load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
                         store: wgpu::StoreOp::Store,
                     } })
                 ],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
@@ -326,54 +750,61 @@ impl RenderManager {
             // Set global bindgroup
             renderpass.set_bind_group(BINDGROUP_GLOBAL, self.global_bindgroup.as_ref(), &[]);
 
-            // update globals
-            self.time.update(&self.gpu_state);
-            // self.camera.force_camera_update_flag
-
-            // Render each SceneObject
-            for objhandle in &self.scene_queue {
-
-                // Get object reference
-                let obj = self.get_scene_object(*objhandle).unwrap();
-
-                // Update model matrix only at render time (now)
-                obj.update_matrix(self);
-                
-                // Set the SceneObject bindgroup for this object
-                renderpass.set_bind_group(BINDGROUP_SCENEOBJECT, obj.sceneobject_bindgroup.as_ref(), &[]);
+            self.draw_scene(&mut renderpass, scene_draws);
+        }
 
-                let mut curbg_id = BINDGROUP_SCENEOBJECT+1;
-                
-                // Set model BG if there is one
-                if let Some(mbg) = obj.model_data.binding.as_ref() {
-                    renderpass.set_bind_group(curbg_id,&mbg.bindgroup, &[]);
-                    curbg_id +=1;
+        // The overlay layer is drawn over the finished scene, so it gets a fresh depth buffer
+        if !overlay_draws.is_empty() {
+            let mut renderpass = cmd.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("overlay"),
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment { view: color_view, resolve_target, ops: wgpu::Operations{
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    } })
+                ],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
 
-                }
+            renderpass.set_bind_group(BINDGROUP_GLOBAL, self.global_bindgroup.as_ref(), &[]);
 
-                // Entity BG should only be active if it's model is, otherwise it wouldn't make sense to use the shader.
-                if let Some(ebg) = obj.entity_bindgroup.as_ref() {
-                    renderpass.set_bind_group(curbg_id, ebg.as_ref(), &[]);
-                }
+            self.draw_scene(&mut renderpass, overlay_draws);
+        }
 
-                // TODO: impl shader bindgroup
+        // Copy the frame out before it is presented
+        // The copy is only recorded if the frame's format and usage allow it
+        let readback = self.capture_request.take().map(|request| {
+            let readback = gpu::readback::TextureReadback::new(&self.gpu_state, surfacetexture.texture());
+            if let Ok(readback) = &readback {
+                readback.copy(&mut cmd, surfacetexture.texture());
+            }
+            (request, readback)
+        });
 
-                // Set the model's vertex buffer
-                renderpass.set_vertex_buffer(0, obj.model_data.vertex_buffer.buffer.slice(..));
-                
-                // Set the entity's pipeline type
-                renderpass.set_pipeline(obj.pipeline_ref.as_ref().unwrap_or(&self.default_pipeline));
+        self.gpu_state.queue.submit(std::iter::once(cmd.finish()));
 
-                // Render each group of vertices
-                for group in obj.model_data.groups.iter().cloned() {
-                    self.update_pvm(camera, obj.model_matrix.as_ref());
-                    renderpass.draw(group, 0..1);
-                }
-                
+        if let Some((request, readback)) = readback {
+            let frame = readback.and_then(|readback| readback.read(&self.gpu_state));
+            match (request, frame) {
+                (CaptureRequest::Keep, frame) => self.captured_frame = Some(frame),
+                (CaptureRequest::SavePng(path), Ok(frame)) => {
+                    if let Err(e) = frame.save_with_format(&path, gpu::image::ImageFormat::Png) {
+                        println!("Could not save frame to {}: {}", path.display(), e);
+                    }
+                },
+                (CaptureRequest::SavePng(path), Err(e)) => println!("Could not save frame to {}: {}", path.display(), e),
             }
-
         }
-        self.gpu_state.queue.submit(std::iter::once(cmd.finish()));
+
         surfacetexture.present();
 
     }
@@ -392,7 +823,145 @@ impl RenderManager {
         r
     }
 
-    pub fn load_entity<E: Entity + NamedEntity>(&mut self) {
+    /// Build a shader with the manager's `ShaderBuilder`, for use in `Entity::load_shader`.\
+    /// The global and SceneObject bindings are declared automatically, and `GROUP_MODEL`/`GROUP_ENTITY` are defined when the entity has those bindgroups.
+    pub fn build_shader(&self, name: &str, source: &str, group_model: u32, group_entity: u32) -> SafehouseResult<Program> {
+        let mut builder = self.shader_builder.clone();
+        if group_model != 0 {
+            builder.define("GROUP_MODEL", group_model);
+        }
+        if group_entity != 0 {
+            builder.define("GROUP_ENTITY", group_entity);
+        }
+
+        let (wgsl, _) = builder.build_validated(name, source).map_err(|e| SafehouseError::ShaderCompile {
+            label: Some(String::from(name)),
+            message: e.to_string(),
+        })?;
+
+        Program::try_new(&self.gpu_state, Some(name), wgpu::ShaderSource::Wgsl(wgsl.into()))
+    }
+
+    /// Build a shader from a WGSL file, like `build_shader`.\
+    /// With shader hot reload enabled the file is read from `path` and watched for changes, otherwise the `embedded` source is used (usually `include_str!` of the same file).\
+    /// If the file can't be read, e.g. because the binary was moved away from it's sources, the `embedded` source is used and the file isn't watched.\
+    /// The program is added to the manager under `name`, which is also the name that is reloaded.
+    pub fn build_shader_file(&self, name: &str, path: impl AsRef<Path>, embedded: &str, group_model: u32, group_entity: u32) -> SafehouseResult<Program> {
+        if !self.shader_reloader.enabled {
+            return self.build_shader(name, embedded, group_model, group_entity);
+        }
+
+        let path = path.as_ref();
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                println!("Could not read shader '{}' from {}, using the embedded source without hot reload: {}", name, path.display(), e);
+                return self.build_shader(name, embedded, group_model, group_entity);
+            },
+        };
+
+        self.shader_reloader.watch(name, path, group_model, group_entity);
+        self.build_shader(name, &source, group_model, group_entity)
+    }
+
+    /// Read shaders built with `build_shader_file` from disk, and rebuild them and their pipelines when the files change.\
+    /// Meant for development, this should be enabled before loading entities.
+    pub fn enable_shader_hot_reload(&mut self) {
+        self.shader_reloader.enabled = true;
+    }
+
+    pub fn shader_hot_reload_enabled(&self) -> bool {
+        self.shader_reloader.enabled
+    }
+
+    /// Rebuild every watched shader whose file changed, along with the pipelines using it.\
+    /// Called before each frame when hot reload is enabled.\
+    /// If a shader or any of it's pipelines fail to build, the error is printed and the last good version is kept.
+    pub fn reload_shaders(&mut self) {
+        for name in self.shader_reloader.poll() {
+            match self.reload_shader(&name) {
+                Ok(count) => println!("Reloaded shader '{}' and {} pipeline(s).", name, count),
+                Err(e) => println!("Shader '{}' was not reloaded, keeping the last good version: {}", name, e),
+            }
+        }
+    }
+
+    /// Rebuild the watched shader `name` and it's pipelines, replacing them only if all of them succeed.
+    fn reload_shader(&mut self, name: &str) -> SafehouseResult<usize> {
+        let (source, group_model, group_entity) = match self.shader_reloader.read(name) {
+            Some(read) => read.map_err(|e| SafehouseError::ShaderCompile {
+                label: Some(String::from(name)),
+                message: e.to_string(),
+            })?,
+            None => return Ok(0),
+        };
+
+        let shader = Rc::new(self.build_shader(name, &source, group_model, group_entity)?);
+
+        let rebuilt = self.pipeline_recipes.values()
+            .filter(|recipe| recipe.uses_shader(name))
+            .map(|recipe| {
+                let recipe = recipe.with_shader(name, &shader);
+                recipe.check()?;
+                let pipe = self.pipeline_cache.pipeline(&self.gpu_state, &recipe)?;
+                Ok((recipe, pipe))
+            })
+            .collect::<SafehouseResult<Vec<_>>>();
+
+        let pipelines = match rebuilt {
+            Ok(pipelines) => pipelines,
+            Err(e) => {
+                // Drop any pipelines that were built before the failure
+                self.pipeline_cache.prune();
+                return Err(e);
+            },
+        };
+
+        self.gpu_state.shader_programs.insert(String::from(name), shader);
+
+        let count = pipelines.len();
+        for (recipe, pipe) in pipelines {
+            let pipeline_name = recipe.pipeline_name.clone();
+            self.pipeline_recipes.insert(pipeline_name.clone(), recipe);
+            if let Some(old) = self.gpu_state.insert_render_pipeline(&pipeline_name, Rc::clone(&pipe)) {
+                self.replace_scene_pipelines(&old, &pipe);
+            }
+        }
+
+        self.pipeline_cache.prune();
+
+        Ok(count)
+    }
+
+    /// Back entity pipelines with a `wgpu::PipelineCache` stored in `dir`, so drivers can reuse compiled pipelines between runs.\
+    /// Needs `Features::PIPELINE_CACHE` (see `StateDescriptor::with_features`), and should be called before loading entities.\
+    /// Returns false if pipeline caching isn't available on this device.
+    pub fn load_pipeline_cache(&mut self, dir: impl AsRef<Path>) -> bool {
+        self.pipeline_cache.load(&self.gpu_state, dir.as_ref())
+    }
+
+    /// Save the pipeline cache loaded with `load_pipeline_cache`, e.g. before exiting.
+    pub fn save_pipeline_cache(&self) -> std::io::Result<()> {
+        self.pipeline_cache.save()
+    }
+
+    /// Get an entity's shader program, adding it to the manager if it's new.\
+    /// Returns the name the program can be found under, which is `None` for references.
+    fn resolve_shader(gpu_state: &mut gpu::State, program: LoadTimeResource<Program>, new_name: &str) -> SafehouseResult<(Option<String>, Rc<Program>)> {
+        match program {
+            LoadTimeResource::UseDefault => Ok((Some(String::from("default")), Rc::clone(gpu_state.get_shader("default")?))),
+            LoadTimeResource::Search(name) => Ok((Some(String::from(name)), Rc::clone(gpu_state.get_shader(name)?))),
+            LoadTimeResource::LoadNew(program) => {
+                let name = program.label.clone().unwrap_or_else(|| String::from(new_name));
+                let program = gpu_state.add_shader(&name, program);
+                Ok((Some(name), program))
+            },
+            LoadTimeResource::Reference(program) => Ok((None, program)),
+        }
+    }
+
+    /// Load an entity's bindings, model, shader and pipeline, so it can be spawned.
+    pub fn load_entity<E: Entity + NamedEntity>(&mut self) -> SafehouseResult<()> {
 
         println!("Loading Entity: {}", E::ENTITY_TYPE_NAME);
 
@@ -411,14 +980,42 @@ impl RenderManager {
             None
         };
 
-        let model = E::load_model(&mut self.gpu_state);
-        
+        let mut model = E::load_model(&mut self.gpu_state)?;
+
+        let pipeline = E::load_pipeline(&self);
+        let material = pipeline.as_ref().and_then(|pipeargs| pipeargs.material);
+        let material_bglayout = material.map(|layout| self.material_layout(layout));
+
+        // Build the bindgroups of per-group materials with the pipeline's material layout
+        if !model.group_materials.is_empty() {
+            let mismatch = |message: String| SafehouseError::ModelMismatch { model: String::from(E::model_name()), message };
+            let (Some(layout), Some(bg_layout)) = (material, &material_bglayout) else {
+                return Err(mismatch(String::from("it has group materials but the pipeline has no material layout")));
+            };
+            if model.group_materials.len() != model.groups.len() {
+                return Err(mismatch(format!("it has {} group materials for {} groups", model.group_materials.len(), model.groups.len())));
+            }
+
+            model.group_bindings = model.group_materials.iter()
+                .map(|group_material| group_material.as_ref().map(|group_material| {
+                    if group_material.layout() != layout {
+                        return Err(mismatch(format!("group material '{}' has layout {:?} but the pipeline uses {:?}", group_material.name, group_material.layout(), layout)));
+                    }
+                    Ok(Rc::new(group_material.build(&self.gpu_state, bg_layout)))
+                }).transpose())
+                .collect::<SafehouseResult<_>>()?;
+        }
         
         let mut total_layout = vec![
             self.global_bglayout.as_ref(),
             self.sceneobj_bglayout.as_ref(),
         ];
 
+        // The material always follows the SceneObject bindgroup, at BINDGROUP_MATERIAL
+        if let Some(layout) = &material_bglayout {
+            total_layout.push(layout.as_ref());
+        }
+
         let mut group_entity = 0u32;
         let mut group_model = 0u32;
         // Note: Shader bg is currently ignored
@@ -435,69 +1032,110 @@ impl RenderManager {
             group_entity = (total_layout.len()-1) as u32;
         }
 
-        let shader_load = E::load_shader(&self, group_model, group_entity);
-        match shader_load {
-            Some(prog) => {
-                self.gpu_state.add_shader(E::shader_name(), prog);
-            },
-            None => (),
-        };
+        let shader_entry = E::load_shader(&self, group_model, group_entity);
 
-        match E::load_pipeline(&self) {
+        match pipeline {
             Some(pipeargs) => {
 
-                let shader = self.gpu_state.get_shader(E::shader_name());
-
-                let pipe_layout = Rc::new(self.gpu_state.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: None,
-                    bind_group_layouts: &total_layout,
-                    push_constant_ranges: &[] 
-                }));   
-                
-                // Create pipeline
-                // NOTE: pseudo of self.gpu_state.add_render_pipeline
-                let pipe = Rc::new(self.gpu_state.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: Some(E::pipeline_name()),
-                    layout: Some(&pipe_layout),
-                    vertex: wgpu::VertexState {
-                        module: &shader.module, 
-                        entry_point: Some("vs_main"), 
-                        buffers: &[model.vertex_buffer.desc.clone()],
-                        compilation_options: Default::default(), 
+                let shader_entry = shader_entry.unwrap_or(EntityShaderEntry::Combined(LoadTimeResource::Search(E::shader_name())));
+                let (vertex, fragment) = match shader_entry {
+                    EntityShaderEntry::Combined(program) => {
+                        let (shader_name, program) = Self::resolve_shader(&mut self.gpu_state, program, E::shader_name())?;
+                        (
+                            RecipeStage { shader_name: shader_name.clone(), program: Rc::clone(&program), entry_point: pipeargs.vertex_entry },
+                            RecipeStage { shader_name, program, entry_point: pipeargs.fragment_entry },
+                        )
+                    },
+                    EntityShaderEntry::Separate { vertex, fragment } => {
+                        let (vertex_name, vertex) = Self::resolve_shader(&mut self.gpu_state, vertex, &format!("{}_vertex", E::shader_name()))?;
+                        let (fragment_name, fragment) = Self::resolve_shader(&mut self.gpu_state, fragment, &format!("{}_fragment", E::shader_name()))?;
+                        (
+                            RecipeStage { shader_name: vertex_name, program: vertex, entry_point: pipeargs.vertex_entry },
+                            RecipeStage { shader_name: fragment_name, program: fragment, entry_point: pipeargs.fragment_entry },
+                        )
                     },
+                };
+
+                // Both render passes have a single color attachment in the surface's format
+                let color_targets = vec![Some(pipeargs.color_target.target_state(self.gpu_state.config.format))];
+
+                let mut vertex_buffers = vec![model.vertex_buffer.desc.clone()];
+                if pipeargs.instanced {
+                    vertex_buffers.push(InstanceTransform::desc().clone());
+                }
+
+                let pipe_layout = self.pipeline_cache.layout(&self.gpu_state.device, &total_layout);
+
+                let recipe = PipelineRecipe {
+                    pipeline_name: String::from(E::pipeline_name()),
+                    vertex,
+                    fragment,
+                    layout: pipe_layout,
+                    vertex_buffers,
                     primitive: pipeargs.primitive,
-                    depth_stencil: pipeargs.depth_stencil,
-                    fragment: Some(wgpu::FragmentState {
-                        module: &shader.module,
-                        entry_point: Some("fs_main"),
-                        // TODO: Support for multiple color targets
-                        targets: &[Some(wgpu::ColorTargetState {
-                            format: self.gpu_state.config.format.clone(),
-                            blend: None,
-                            write_mask: wgpu::ColorWrites::all() 
-                        })],
-                        compilation_options: Default::default(), 
-                    }),
-                    multisample: wgpu::MultisampleState::default(),
-                    multiview: None, 
-                    cache: None
-                }));
+                    depth_stencil: pipeargs.depth_stencil_or_default(),
+                    color_targets,
+                    multisample: wgpu::MultisampleState { count: self.sample_count, ..Default::default() },
+                    group_count: total_layout.len() as u32,
+                    group_entity,
+                    entity_entries: bindings.iter().map(|b| b.get_layout_entry()).collect(),
+                };
+
+                // Check the shader against the entity before wgpu does
+                recipe.check()?;
+                let pipe = self.pipeline_cache.pipeline(&self.gpu_state, &recipe)?;
+                self.pipeline_recipes.insert(String::from(E::pipeline_name()), recipe);
         
-                self.gpu_state.render_pipelines.insert(String::from(E::pipeline_name()), Rc::clone(&pipe));
+                self.gpu_state.insert_render_pipeline(E::pipeline_name(), pipe);
+                self.pipeline_cache.prune();
+
+                if pipeargs.instanced {
+                    self.instanced_pipelines.insert(String::from(E::pipeline_name()));
+                } else {
+                    self.instanced_pipelines.remove(E::pipeline_name());
+                }
+
+                self.pipeline_orders.insert(String::from(E::pipeline_name()), pipeargs.order);
+
+                match material {
+                    Some(layout) => {
+                        self.pipeline_materials.insert(String::from(E::pipeline_name()), layout);
+                    },
+                    None => {
+                        self.pipeline_materials.remove(E::pipeline_name());
+                    },
+                }
             },
             None => {
-                // Use default if not specified
-                self.gpu_state.render_pipelines.insert(String::from(E::pipeline_name()), Rc::clone(&self.default_pipeline));
+                // Use default if not specified, still adding any programs the entity gave so other entities can use them
+                match shader_entry {
+                    Some(EntityShaderEntry::Combined(program)) => {
+                        Self::resolve_shader(&mut self.gpu_state, program, E::shader_name())?;
+                    },
+                    Some(EntityShaderEntry::Separate { vertex, fragment }) => {
+                        Self::resolve_shader(&mut self.gpu_state, vertex, &format!("{}_vertex", E::shader_name()))?;
+                        Self::resolve_shader(&mut self.gpu_state, fragment, &format!("{}_fragment", E::shader_name()))?;
+                    },
+                    None => (),
+                }
+                self.gpu_state.insert_render_pipeline(E::pipeline_name(), Rc::clone(&self.default_pipeline));
+                self.instanced_pipelines.remove(E::pipeline_name());
+                self.pipeline_orders.remove(E::pipeline_name());
+                self.pipeline_recipes.remove(E::pipeline_name());
+                self.pipeline_materials.remove(E::pipeline_name());
             },
         };
 
         self.add_model(E::model_name(), model);
 
-
-
+        Ok(())
     }
 
-    pub fn add_scene_object(&mut self, object_name: &str, using_model: &str, using_pipeline: &str) -> SceneObjectHandle {
+    /// Add a SceneObject drawing a loaded model with a loaded pipeline.
+    pub fn add_scene_object(&mut self, object_name: &str, using_model: &str, using_pipeline: &str) -> SafehouseResult<SceneObjectHandle> {
+
+        let model_data = ModelData::fetch(using_model, self)?;
+        let pipeline_ref = self.get_pipeline(using_pipeline)?;
 
         let model_matrix = UniformPtr::new(&self.gpu_state, glam::Mat4::IDENTITY); 
 
@@ -506,7 +1144,7 @@ impl RenderManager {
             layout: &self.sceneobj_bglayout,
             entries: &[
                 wgpu::BindGroupEntry {
-                    binding: 0,
+                    binding: sceneobject::MODEL_MATRIX,
                     resource: model_matrix.get_buffer().as_entire_binding(),
                 }
             ],
@@ -515,31 +1153,91 @@ impl RenderManager {
         //TODO: resolve consistency of loading &Rc vs Rc for load functions 
         // Should search() return a cloned ref, while the functions return &Rc?
 
-        let sceneobj_handle = self.scene_objects.add(SceneObject {
+        let material = self.pipeline_materials.get(using_pipeline).copied()
+            .map(|layout| self.default_material(layout))
+            .transpose()?;
+
+        let generation = self.next_generation;
+        self.next_generation += 1;
+
+        let obj = SceneObject {
             name: String::from(object_name),
-            model_data: ModelData::fetch(using_model, self),
-            pipeline_ref: self.get_pipeline(using_pipeline),
+            model_data,
+            pipeline_ref: Some(pipeline_ref),
             entity_bindgroup: None,
+            material,
             model_matrix,
             sceneobject_bindgroup,
             model_matrix_changed: false,
-        });
+            instanced: self.instanced_pipelines.contains(using_pipeline),
+            visible: true,
+            order: self.pipeline_orders.get(using_pipeline).copied().unwrap_or_default(),
+            generation,
+        };
+
+        // Every empty slot is in the free list, so the TagMap only grows when it is full
+        let tag = match self.free_scene_slots.pop() {
+            Some(tag) => {
+                self.scene_objects[tag] = Some(obj);
+                tag
+            },
+            None => {
+                let tag = self.scene_objects.add(obj);
+                self.scene_slot_count = self.scene_slot_count.max(tag + 1);
+                tag
+            },
+        };
+
+        let sceneobj_handle = SceneObjectHandle { tag, generation };
 
         self.scene_queue.push(sceneobj_handle.clone());
 
-        sceneobj_handle
+        Ok(sceneobj_handle)
 
     }
 
+    /// Get a SceneObject, or `None` if the handle is stale or from another manager.
     pub fn get_scene_object(&self, handle: SceneObjectHandle) -> Option<&SceneObject> {
-        self.scene_objects[handle].as_ref()
+        if handle.tag >= self.scene_slot_count {
+            return None;
+        }
+        self.scene_objects[handle.tag].as_ref().filter(|obj| obj.generation == handle.generation)
     }
 
+    /// Get a mutable SceneObject, or `None` if the handle is stale or from another manager.
     pub fn mut_scene_object(&mut self, handle: SceneObjectHandle) -> Option<&mut SceneObject> {
-        self.scene_objects[handle].as_mut()
+        if handle.tag >= self.scene_slot_count {
+            return None;
+        }
+        self.scene_objects[handle.tag].as_mut().filter(|obj| obj.generation == handle.generation)
+    }
+
+    /// Whether the handle refers to a SceneObject that hasn't been removed.
+    pub fn is_valid_handle(&self, handle: SceneObjectHandle) -> bool {
+        self.get_scene_object(handle).is_some()
     }
 
-    pub fn get_pipeline(&self, pipeline_name: &str) -> Option<Rc<wgpu::RenderPipeline>> {
+    /// Remove a SceneObject from the scene, freeing it's slot and GPU buffers.\
+    /// The removed object is returned, and the handle becomes stale.
+    pub fn remove_scene_object(&mut self, handle: SceneObjectHandle) -> SafehouseResult<SceneObject> {
+        if !self.is_valid_handle(handle) {
+            return Err(handle.stale());
+        }
+
+        self.scene_queue.retain(|queued| *queued != handle);
+        let obj = self.scene_objects[handle.tag].take().ok_or(handle.stale())?;
+        self.free_scene_slots.push(handle.tag);
+        Ok(obj)
+    }
+
+    /// Show or hide a SceneObject. Hidden objects are kept in the scene but not rendered.
+    pub fn set_visible(&mut self, handle: SceneObjectHandle, visible: bool) -> SafehouseResult<()> {
+        self.mut_scene_object(handle)
+            .map(|obj| obj.visible = visible)
+            .ok_or(handle.stale())
+    }
+
+    pub fn get_pipeline(&self, pipeline_name: &str) -> SafehouseResult<Rc<wgpu::RenderPipeline>> {
         // TODO: default model
         self.gpu_state.get_render_pipeline(pipeline_name)
     }
@@ -550,6 +1248,58 @@ impl RenderManager {
         self.model_data_cache.get(model_name)
     }
 
+    /// Get the bindgroup layout for a material layout, creating it if it's not in use yet.
+    pub fn material_layout(&mut self, layout: MaterialLayout) -> Rc<wgpu::BindGroupLayout> {
+        let device = &self.gpu_state.device;
+        Rc::clone(self.material_bglayouts.entry(layout).or_insert_with(|| Rc::new(device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("material_bglayout"),
+            entries: &layout.layout_entries(),
+        }))))
+    }
+
+    /// The material new SceneObjects get for a layout, with zeroed parameters and white textures.
+    fn default_material(&mut self, layout: MaterialLayout) -> SafehouseResult<Rc<MaterialBinding>> {
+        if let Some(material) = self.default_materials.get(&layout) {
+            return Ok(Rc::clone(material));
+        }
+
+        let bg_layout = self.material_layout(layout);
+        let params = (layout.params_size > 0).then(|| self.gpu_state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("default_material_params"),
+            size: layout.params_size,
+            usage: wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        }));
+        let white = MaterialTexture {
+            texture: Rc::new(gpu::texture::Texture::solid(&self.gpu_state, [255, 255, 255, 255])),
+            sampler: Rc::clone(self.gpu_state.get_sampler("default")?),
+        };
+        let textures = vec![white; layout.texture_slots as usize];
+
+        let material = Rc::new(MaterialBinding {
+            layout,
+            bindgroup: std::cell::RefCell::new(MaterialBinding::create_bindgroup(
+                &self.gpu_state,
+                "default_material",
+                &bg_layout,
+                params.as_ref().map(|buffer| buffer.as_entire_binding()),
+                &textures,
+            )),
+        });
+        self.default_materials.insert(layout, Rc::clone(&material));
+        Ok(material)
+    }
+
+    /// Draw a SceneObject with `material`. It's pipeline must have been built for the material's layout.
+    pub fn set_material<P: Copy + 'static>(&mut self, handle: SceneObjectHandle, material: &Material<P>) -> SafehouseResult<()> {
+        let obj = self.mut_scene_object(handle).ok_or(handle.stale())?;
+        if obj.material.as_ref().map(|m| m.layout) != Some(material.layout()) {
+            return Err(SafehouseError::MaterialMismatch { object: obj.name.clone() });
+        }
+        obj.material = Some(Rc::clone(material.binding()));
+        Ok(())
+    }
+
     pub fn get_entity_layout(&self, layout_name: &str) -> Option<&Rc<wgpu::BindGroupLayout>> {
         self.entity_bglayout_cache.get(layout_name)
     }
@@ -573,50 +1323,58 @@ impl RenderManager {
 
     /// Spawn an entity as a static SceneObject.\ 
     /// Note: entities should only contain references to the context.
-    pub fn spawn_sceneobject_entity<E: Entity + NamedEntity>(&mut self, name: &str) -> E {
-        
-        // Create a SceneObject to accompany entity
-        let sceneobject_handle = self.add_scene_object(name, E::model_name(), E::pipeline_name());
-
-        // Instantiate the entity.
-        let e = E::on_instantiate(self, sceneobject_handle);
+    pub fn spawn_sceneobject_entity<E: Entity + NamedEntity>(&mut self, name: &str) -> SafehouseResult<E> {
 
-        // Load the bindings
+        // Load the bindings, and check their bindgroup layout was created by `load_entity` before anything is spawned
         let bindings = E::load_bindings();
-        if bindings.len() == 0 {
-            return e;
-        }
-        
-        // Check if a bindgroup layout was created yet
-        let (layout, bg_entries) = match self.entity_bglayout_cache.get(E::bindings_name()) {
-            
-            // Layout is already in memory, use it.
-            Some(l) => {
-                let layout = Rc::clone(&l);
-
-                let bg_entries: Vec<wgpu::BindGroupEntry> = bindings.iter().map(|x| {
-                    x.get_binding_entry(&e)
-                }).collect();
-
-                (layout, bg_entries)
+        let layout = match bindings.is_empty() {
+            true => None,
+            false => Some(Rc::clone(self.entity_bglayout_cache.get(E::bindings_name())
+                .ok_or_else(|| SafehouseError::missing(gpu::error::ResourceKind::BindGroupLayout, E::bindings_name()))?)),
+        };
 
-            },
+        // Create a SceneObject to accompany entity
+        let sceneobject_handle = self.add_scene_object(name, E::model_name(), E::pipeline_name())?;
 
-            // TODO: Make the new layout
-            None => panic!("Entity BindGroupLayout not loaded!"),
+        // Instantiate the entity.
+        let e = E::on_instantiate(self, sceneobject_handle);
 
+        let Some(layout) = layout else {
+            return Ok(e);
         };
 
+        let bg_entries: Vec<wgpu::BindGroupEntry> = bindings.iter().map(|x| {
+            x.get_binding_entry(&e)
+        }).collect();
+
         let bg = Rc::new(self.gpu_state.device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some(E::bindings_name()),
             layout: &layout,
             entries: &bg_entries 
         }));
 
-        self.mut_scene_object(sceneobject_handle).unwrap().entity_bindgroup = Some(bg);
+        // `on_instantiate` may have removed the object it was given
+        self.mut_scene_object(sceneobject_handle).ok_or(sceneobject_handle.stale())?.entity_bindgroup = Some(bg);
+
+        Ok(e)
 
-        e
+    }
+
+    /// Request a redraw from the window, if there is one.
+    pub fn request_redraw(&self) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    /// Despawn an entity that was spawned with `spawn_sceneobject_entity`, removing it's SceneObject.
+    pub fn despawn_sceneobject_entity<E: Entity>(&mut self, entity: E, handle: SceneObjectHandle) -> SafehouseResult<()> {
+        if !self.is_valid_handle(handle) {
+            return Err(handle.stale());
+        }
 
+        entity.on_despawn(self);
+        self.remove_scene_object(handle).map(|_| ())
     }
 
     /// Window width
@@ -0,0 +1,225 @@
+use std::{cell::RefCell, num::NonZeroU64, rc::Rc};
+
+use gpu::wgpu::util::DeviceExt;
+
+use crate::{bindgroups::material, gpu};
+use gpu::{binding::{Bindable, BindableType}, buffer::UniformPtr, texture::{sampler::TextureSampler, Texture}, wgpu};
+
+/// The bindings of a kind of material: a uniform block of parameters, and a texture and sampler for each texture slot.\
+/// Pipelines are built for one layout, and any material with that layout can be assigned to their SceneObjects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialLayout {
+    /// The size of the parameters uniform block in bytes, or 0 for no parameters.
+    pub params_size: u64,
+    pub texture_slots: u32,
+}
+
+impl MaterialLayout {
+    /// The layout of a `Material<P>` with `texture_slots` textures.
+    pub fn new<P>(texture_slots: u32) -> Self {
+        Self {
+            params_size: std::mem::size_of::<P>() as u64,
+            texture_slots,
+        }
+    }
+
+    pub fn layout_entries(&self) -> Vec<wgpu::BindGroupLayoutEntry> {
+        let mut entries = vec![];
+        if let Some(size) = NonZeroU64::new(self.params_size) {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: material::PARAMS,
+                visibility: wgpu::ShaderStages::all(),
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: Some(size),
+                },
+                count: None,
+            });
+        }
+        for slot in 0..self.texture_slots {
+            entries.push(Texture::get_layout_entry(material::texture(slot), wgpu::ShaderStages::all()));
+            entries.push(TextureSampler::get_layout_entry(material::sampler(slot), wgpu::ShaderStages::all()));
+        }
+        entries
+    }
+}
+
+/// A texture and the sampler it is read with, filling one slot of a material.
+#[derive(Clone)]
+pub struct MaterialTexture {
+    pub texture: Rc<Texture>,
+    pub sampler: Rc<TextureSampler>,
+}
+
+/// The part of a material that SceneObjects hold on to.\
+/// The bindgroup is replaced in place when the material's textures change, so every object using it follows.
+pub struct MaterialBinding {
+    pub(crate) layout: MaterialLayout,
+    pub(crate) bindgroup: RefCell<wgpu::BindGroup>,
+}
+
+impl MaterialBinding {
+    pub fn layout(&self) -> MaterialLayout {
+        self.layout
+    }
+
+    /// Create a bindgroup for `layout` from a parameters buffer and textures.
+    pub(crate) fn create_bindgroup(
+        state: &gpu::State,
+        name: &str,
+        bg_layout: &wgpu::BindGroupLayout,
+        params: Option<wgpu::BindingResource>,
+        textures: &[MaterialTexture],
+    ) -> wgpu::BindGroup {
+        let mut entries = vec![];
+        if let Some(resource) = params {
+            entries.push(wgpu::BindGroupEntry {
+                binding: material::PARAMS,
+                resource,
+            });
+        }
+        for (slot, texture) in textures.iter().enumerate() {
+            entries.push(texture.texture.get_binding_entry(material::texture(slot as u32)));
+            entries.push(texture.sampler.get_binding_entry(material::sampler(slot as u32)));
+        }
+
+        state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(name),
+            layout: bg_layout,
+            entries: &entries,
+        })
+    }
+}
+
+/// A reusable look for SceneObjects: typed parameters in a uniform block, and textures.\
+/// `P` is uploaded as-is, so it should be `#[repr(C)]` and padded like it's WGSL struct (e.g. a `vec3<f32>` takes 16 bytes).\
+/// Use `()` for a material without parameters.
+pub struct Material<P> {
+    pub name: String,
+    params: Option<UniformPtr<P>>,
+    textures: Vec<MaterialTexture>,
+    bg_layout: Rc<wgpu::BindGroupLayout>,
+    binding: Rc<MaterialBinding>,
+}
+
+impl<P: std::fmt::Debug> std::fmt::Debug for Material<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Material")
+            .field("name", &self.name)
+            .field("layout", &self.binding.layout)
+            .field("params", &self.params.as_ref().map(|p| p.as_ref()))
+            .finish()
+    }
+}
+
+impl<P: Copy + 'static> Material<P> {
+    /// Create a material with `textures` filling it's texture slots in order.
+    pub fn new(rm: &mut crate::RenderManager, name: &str, params: P, textures: Vec<MaterialTexture>) -> Self {
+        let layout = MaterialLayout::new::<P>(textures.len() as u32);
+        let bg_layout = rm.material_layout(layout);
+
+        let params = (layout.params_size > 0).then(|| UniformPtr::new(&rm.gpu_state, params));
+        let bindgroup = MaterialBinding::create_bindgroup(
+            &rm.gpu_state,
+            name,
+            &bg_layout,
+            params.as_ref().map(|p| p.get_binding_entry(material::PARAMS).resource),
+            &textures,
+        );
+
+        Self {
+            name: String::from(name),
+            params,
+            textures,
+            bg_layout,
+            binding: Rc::new(MaterialBinding {
+                layout,
+                bindgroup: RefCell::new(bindgroup),
+            }),
+        }
+    }
+
+    pub fn layout(&self) -> MaterialLayout {
+        self.binding.layout
+    }
+
+    pub fn params(&self) -> Option<&P> {
+        self.params.as_ref().map(|p| p.as_ref())
+    }
+
+    /// Change the parameters of every object using this material.
+    pub fn set_params(&mut self, rm: &crate::RenderManager, params: P) {
+        if let Some(uniform) = self.params.as_mut() {
+            *uniform.as_mut() = params;
+            uniform.update(&rm.gpu_state);
+        }
+    }
+
+    /// Replace the texture in `slot` for every object using this material.
+    pub fn set_texture(&mut self, rm: &crate::RenderManager, slot: usize, texture: MaterialTexture) {
+        assert!(slot < self.textures.len(), "Material '{}' has no texture slot {}.", self.name, slot);
+        self.textures[slot] = texture;
+
+        let bindgroup = MaterialBinding::create_bindgroup(
+            &rm.gpu_state,
+            &self.name,
+            &self.bg_layout,
+            self.params.as_ref().map(|p| p.get_binding_entry(material::PARAMS).resource),
+            &self.textures,
+        );
+        *self.binding.bindgroup.borrow_mut() = bindgroup;
+    }
+
+    /// The shared binding to assign to SceneObjects.
+    pub fn binding(&self) -> &Rc<MaterialBinding> {
+        &self.binding
+    }
+}
+
+/// The material of one group of a model, for models with a different texture per group.\
+/// Models are loaded without a `RenderManager`, so the bindgroup is built when the model's entity is loaded,
+/// and must match the entity's `EntityPipeline::material` layout.
+#[derive(Clone)]
+pub struct GroupMaterial {
+    pub name: String,
+    params: Vec<u8>,
+    pub textures: Vec<MaterialTexture>,
+}
+
+impl GroupMaterial {
+    /// A group material with the same parameters and textures as a `Material<P>`.
+    pub fn new<P: Copy + 'static>(name: &str, params: P, textures: Vec<MaterialTexture>) -> Self {
+        Self {
+            name: String::from(name),
+            params: unsafe { slicebytes::cast_bytes(&[params]) }.to_vec(),
+            textures,
+        }
+    }
+
+    pub fn layout(&self) -> MaterialLayout {
+        MaterialLayout {
+            params_size: self.params.len() as u64,
+            texture_slots: self.textures.len() as u32,
+        }
+    }
+
+    pub(crate) fn build(&self, state: &gpu::State, bg_layout: &wgpu::BindGroupLayout) -> MaterialBinding {
+        let params = (!self.params.is_empty()).then(|| state.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&self.name),
+            contents: &self.params,
+            usage: wgpu::BufferUsages::UNIFORM,
+        }));
+
+        MaterialBinding {
+            layout: self.layout(),
+            bindgroup: RefCell::new(MaterialBinding::create_bindgroup(
+                state,
+                &self.name,
+                bg_layout,
+                params.as_ref().map(|buffer| buffer.as_entire_binding()),
+                &self.textures,
+            )),
+        }
+    }
+}
@@ -1,9 +1,10 @@
 pub mod d2;
-use crate::{entity::NamedEntity, gpu, texturetype::TextureType};
+use crate::{entity::NamedEntity, gpu, material::{GroupMaterial, MaterialBinding}, texturetype::TextureType};
 use std::{ops::Range, rc::Rc};
 
 use gpu::wgpu;
-use safehouse_gpu::{binding::{Bindable, BindableType, Binder}, buffer::VertexBuffer, texture::Texture, wgpu::ShaderStages, State};
+use safehouse_gpu::{binding::{Bindable, BindableType, Binder}, buffer::{IndexBuffer, IndexType, VertexBuffer}, error::{SafehouseError, SafehouseResult}, texture::Texture, vertex::Vertex, wgpu::ShaderStages, State};
+use safehouse_shared::model::{ModelFile, ModelIndices};
 
 pub trait ModelDataRes {}
 
@@ -23,10 +24,31 @@ impl ModelResources for () {
     }
 }
 
+/// An index buffer for a model, with it's index type erased.
+pub struct ModelIndexBuffer {
+    pub buffer: wgpu::Buffer,
+    pub format: wgpu::IndexFormat,
+}
+
+impl<T: IndexType> From<IndexBuffer<T>> for ModelIndexBuffer {
+    fn from(value: IndexBuffer<T>) -> Self {
+        Self {
+            format: value.format(),
+            buffer: value.buffer,
+        }
+    }
+}
+
 pub struct ModelData {
     pub vertex_buffer: Rc<VertexBuffer>,
+    /// Ranges of vertices to draw, or ranges of indices if the model has an index buffer.
     pub groups: Box<[Range<u32>]>,
-    pub(crate) binding: Option<ModelBindings>
+    pub index_buffer: Option<ModelIndexBuffer>,
+    pub(crate) binding: Option<ModelBindings>,
+    /// The material of each group, or empty if the model's groups are drawn with their object's material.
+    pub group_materials: Vec<Option<GroupMaterial>>,
+    /// The bindgroups built from `group_materials` when the model's entity was loaded.
+    pub(crate) group_bindings: Box<[Option<Rc<MaterialBinding>>]>,
 }
 
 impl ModelData {
@@ -62,9 +84,49 @@ impl ModelData {
         Self {
             vertex_buffer,
             groups: groups.into_boxed_slice(),
-            binding
+            index_buffer: None,
+            binding,
+            group_materials: vec![],
+            group_bindings: Box::new([]),
         }
     } 
+
+    /// Load a model container written by `safehouse_data::model::container::ModelWriter` with vertex type `V`.\
+    /// Containers written with a different vertex layout are rejected instead of being drawn as garbage.
+    pub fn from_container<E: NamedEntity, V: Vertex, B: ModelResources + 'static>(state: &State, data: &[u8], resources: Option<B>) -> SafehouseResult<Self> {
+        let file = ModelFile::read::<V>(data)
+            .map_err(|e| SafehouseError::Decode(format!("Model '{}': {}", E::model_name(), e)))?;
+
+        let model = Self::new::<E, B>(
+            state,
+            VertexBuffer::new_from_raw::<V>(state, file.vertex_data),
+            file.group_ranges(),
+            resources,
+        );
+        Ok(match file.indices {
+            Some(ModelIndices::U16(indices)) => model.with_indices(IndexBuffer::<u16>::new_from_raw(state, indices)),
+            Some(ModelIndices::U32(indices)) => model.with_indices(IndexBuffer::<u32>::new_from_raw(state, indices)),
+            None => model,
+        })
+    }
+
+    /// Draw this model with an index buffer. The model's groups are then treated as ranges of indices.
+    pub fn with_indices(mut self, index_buffer: impl Into<ModelIndexBuffer>) -> Self {
+        self.index_buffer = Some(index_buffer.into());
+        self
+    }
+
+    /// Draw each group with it's own material, e.g. for OBJ files with several `usemtl` groups.\
+    /// Groups with `None` use their object's material. There must be one entry per group, and the entity's pipeline needs a matching material layout.
+    pub fn with_group_materials(mut self, materials: Vec<Option<GroupMaterial>>) -> Self {
+        self.group_materials = materials;
+        self
+    }
+
+    /// The bindgroup of a group's own material, if it has one.
+    pub(crate) fn group_binding(&self, group: usize) -> Option<&Rc<MaterialBinding>> {
+        self.group_bindings.get(group).and_then(|binding| binding.as_ref())
+    }
 }
 
 // impl ModelBindings {
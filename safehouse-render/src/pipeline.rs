@@ -0,0 +1,225 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, rc::Rc};
+
+use crate::{gpu::{self, shaderprogram::Program, wgpu}, SafehouseError, SafehouseResult};
+
+/// Everything that makes two render pipelines different.\
+/// wgpu handles compare by identity, so shaders and layouts must be the same objects to match.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct PipelineKey {
+    vertex: (wgpu::ShaderModule, &'static str),
+    fragment: (wgpu::ShaderModule, &'static str),
+    layout: wgpu::PipelineLayout,
+    vertex_buffers: Vec<wgpu::VertexBufferLayout<'static>>,
+    primitive: wgpu::PrimitiveState,
+    depth_stencil: wgpu::DepthStencilState,
+    color_targets: Vec<Option<wgpu::ColorTargetState>>,
+    multisample: wgpu::MultisampleState,
+}
+
+/// Pipelines and pipeline layouts shared between entities with the same descriptor.\
+/// Can be backed by a `wgpu::PipelineCache` that is saved to disk, so drivers can skip compiling pipelines on the next run.
+#[derive(Default)]
+pub(crate) struct PipelineCache {
+    layouts: HashMap<Vec<wgpu::BindGroupLayout>, Rc<wgpu::PipelineLayout>>,
+    pipelines: HashMap<PipelineKey, Rc<wgpu::RenderPipeline>>,
+    wgpu_cache: Option<(wgpu::PipelineCache, PathBuf)>,
+}
+
+impl PipelineCache {
+    /// Get the pipeline layout for these bindgroup layouts, creating it if there isn't one.
+    pub fn layout(&mut self, device: &wgpu::Device, bind_group_layouts: &[&wgpu::BindGroupLayout]) -> Rc<wgpu::PipelineLayout> {
+        let key: Vec<wgpu::BindGroupLayout> = bind_group_layouts.iter().map(|layout| (*layout).clone()).collect();
+        Rc::clone(self.layouts.entry(key).or_insert_with(|| Rc::new(device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts,
+            push_constant_ranges: &[]
+        }))))
+    }
+
+    /// Get the pipeline built from `recipe`, creating it if no other recipe with the same descriptor has been built.
+    pub fn pipeline(&mut self, state: &gpu::State, recipe: &PipelineRecipe) -> SafehouseResult<Rc<wgpu::RenderPipeline>> {
+        let key = recipe.key();
+        if let Some(pipeline) = self.pipelines.get(&key) {
+            return Ok(Rc::clone(pipeline));
+        }
+
+        let pipeline = Rc::new(recipe.create(state, self.wgpu_cache.as_ref().map(|(cache, _)| cache))?);
+        self.pipelines.insert(key, Rc::clone(&pipeline));
+        Ok(pipeline)
+    }
+
+    /// Drop pipelines that are no longer used outside the cache, e.g. after their shader was reloaded.
+    pub fn prune(&mut self) {
+        self.pipelines.retain(|_, pipeline| Rc::strong_count(pipeline) > 1);
+    }
+
+    /// Back the cache with pipeline data stored in `dir`, in a file named for the adapter.\
+    /// Returns false if the device doesn't have `Features::PIPELINE_CACHE` or the adapter can't use a stored cache.
+    pub fn load(&mut self, state: &gpu::State, dir: &Path) -> bool {
+        if !state.device.features().contains(wgpu::Features::PIPELINE_CACHE) {
+            return false;
+        }
+        let Some(file_name) = wgpu::util::pipeline_cache_key(&state.adapter.get_info()) else {
+            return false;
+        };
+
+        let path = dir.join(file_name);
+        let data = std::fs::read(&path).ok();
+
+        // SAFETY: The data was written by `save` from a cache for this adapter, and wgpu validates it's header before use.
+        let cache = unsafe {
+            state.device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                label: Some("safehouse_pipeline_cache"),
+                data: data.as_deref(),
+                fallback: true,
+            })
+        };
+
+        self.wgpu_cache = Some((cache, path));
+        true
+    }
+
+    /// Write the backing `wgpu::PipelineCache` to disk, if there is one.\
+    /// The data is written to a temporary file first, so an interrupted save doesn't leave a corrupt cache.
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some((cache, path)) = &self.wgpu_cache else {
+            return Ok(());
+        };
+        let Some(data) = cache.get_data() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, data)?;
+        std::fs::rename(temp, path)
+    }
+}
+
+/// A program used by one stage of a pipeline.
+#[derive(Clone)]
+pub(crate) struct RecipeStage {
+    /// The name the program was added to the manager under, or `None` if it was given by reference.
+    pub shader_name: Option<String>,
+    pub program: Rc<Program>,
+    pub entry_point: &'static str,
+}
+
+impl RecipeStage {
+    fn label(&self) -> &str {
+        self.shader_name.as_deref().unwrap_or("?")
+    }
+}
+
+/// Everything needed to build an entity's render pipeline.\
+/// Kept by the manager so pipelines can be rebuilt when their shaders change.
+#[derive(Clone)]
+pub(crate) struct PipelineRecipe {
+    pub pipeline_name: String,
+    pub vertex: RecipeStage,
+    pub fragment: RecipeStage,
+    pub layout: Rc<wgpu::PipelineLayout>,
+    pub vertex_buffers: Vec<wgpu::VertexBufferLayout<'static>>,
+    pub primitive: wgpu::PrimitiveState,
+    pub depth_stencil: wgpu::DepthStencilState,
+    pub color_targets: Vec<Option<wgpu::ColorTargetState>>,
+    pub multisample: wgpu::MultisampleState,
+    /// The number of bindgroups in `layout`.
+    pub group_count: u32,
+    /// The index of the entity bindgroup, or 0 if there is none.
+    pub group_entity: u32,
+    pub entity_entries: Vec<wgpu::BindGroupLayoutEntry>,
+}
+
+impl PipelineRecipe {
+    /// Whether either stage uses the shader added under `shader_name`.
+    pub fn uses_shader(&self, shader_name: &str) -> bool {
+        self.vertex.shader_name.as_deref() == Some(shader_name) || self.fragment.shader_name.as_deref() == Some(shader_name)
+    }
+
+    /// This recipe with `program` replacing the shader added under `shader_name`.
+    pub fn with_shader(&self, shader_name: &str, program: &Rc<Program>) -> Self {
+        let mut recipe = self.clone();
+        for stage in [&mut recipe.vertex, &mut recipe.fragment] {
+            if stage.shader_name.as_deref() == Some(shader_name) {
+                stage.program = Rc::clone(program);
+            }
+        }
+        recipe
+    }
+
+    /// Check the shaders' interfaces against this pipeline's bindgroups and vertex buffers.\
+    /// Shaders that weren't created from WGSL can't be reflected, and are not checked.
+    pub fn check(&self) -> SafehouseResult<()> {
+        if Rc::ptr_eq(&self.vertex.program, &self.fragment.program) {
+            return self.check_stage(&self.vertex, Some(self.vertex.entry_point), Some(self.fragment.entry_point));
+        }
+
+        self.check_stage(&self.vertex, Some(self.vertex.entry_point), None)?;
+        self.check_stage(&self.fragment, None, Some(self.fragment.entry_point))
+    }
+
+    fn check_stage(&self, stage: &RecipeStage, vertex_entry: Option<&str>, fragment_entry: Option<&str>) -> SafehouseResult<()> {
+        let Some(source) = &stage.program.source else {
+            return Ok(());
+        };
+
+        let reflection = safehouse_shader::validate(stage.label(), source).map_err(|e| SafehouseError::ShaderCompile {
+            label: stage.shader_name.clone(),
+            message: e.to_string(),
+        })?;
+
+        crate::shader::check_entity_interface(stage.label(), &reflection, vertex_entry, fragment_entry, &self.vertex_buffers, self.group_count, self.group_entity, &self.entity_entries)
+    }
+
+    /// The descriptor this recipe builds, for finding an identical pipeline.
+    pub fn key(&self) -> PipelineKey {
+        PipelineKey {
+            vertex: (self.vertex.program.module.as_ref().clone(), self.vertex.entry_point),
+            fragment: (self.fragment.program.module.as_ref().clone(), self.fragment.entry_point),
+            layout: self.layout.as_ref().clone(),
+            vertex_buffers: self.vertex_buffers.clone(),
+            primitive: self.primitive,
+            depth_stencil: self.depth_stencil.clone(),
+            color_targets: self.color_targets.clone(),
+            multisample: self.multisample,
+        }
+    }
+
+    /// Create the pipeline, returning wgpu's validation error instead of panicking.
+    pub fn create(&self, state: &gpu::State, cache: Option<&wgpu::PipelineCache>) -> SafehouseResult<wgpu::RenderPipeline> {
+        state.device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let pipeline = state.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&self.pipeline_name),
+            layout: Some(&self.layout),
+            vertex: wgpu::VertexState {
+                module: &self.vertex.program.module, 
+                entry_point: Some(self.vertex.entry_point), 
+                buffers: &self.vertex_buffers,
+                compilation_options: Default::default(), 
+            },
+            primitive: self.primitive,
+            depth_stencil: Some(self.depth_stencil.clone()),
+            fragment: Some(wgpu::FragmentState {
+                module: &self.fragment.program.module,
+                entry_point: Some(self.fragment.entry_point),
+                targets: &self.color_targets,
+                compilation_options: Default::default(), 
+            }),
+            multisample: self.multisample,
+            multiview: None,
+            cache,
+        });
+
+        match futures::executor::block_on(state.device.pop_error_scope()) {
+            Some(e) => Err(SafehouseError::PipelineCreation {
+                pipeline: self.pipeline_name.clone(),
+                message: e.to_string(),
+            }),
+            None => Ok(pipeline),
+        }
+    }
+}
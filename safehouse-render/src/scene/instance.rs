@@ -0,0 +1,39 @@
+use safehouse_gpu::wgpu;
+use slicebytes::cast_bytes;
+
+use crate::vertex_type::InstanceTransform;
+
+/// Identifies a batch of instanced SceneObjects by their shared model, pipeline and material.
+pub(crate) type InstanceBatchKey = (usize, usize, usize);
+
+/// A per-instance vertex buffer of transforms for one batch of instanced SceneObjects.
+pub(crate) struct InstanceBatch {
+    pub(crate) buffer: wgpu::Buffer,
+    capacity: usize,
+    pub(crate) count: u32,
+}
+
+impl InstanceBatch {
+    pub(crate) fn new(state: &crate::gpu::State, capacity: usize) -> Self {
+        let capacity = capacity.max(1).next_power_of_two();
+        Self {
+            buffer: state.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("instance_batch"),
+                size: (capacity * std::mem::size_of::<InstanceTransform>()) as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            capacity,
+            count: 0,
+        }
+    }
+
+    /// Upload this frame's transforms, growing the buffer if there are more instances than it can hold.
+    pub(crate) fn write(&mut self, state: &crate::gpu::State, transforms: &[InstanceTransform]) {
+        if transforms.len() > self.capacity {
+            *self = Self::new(state, transforms.len());
+        }
+        state.queue.write_buffer(&self.buffer, 0, unsafe { cast_bytes(transforms) });
+        self.count = transforms.len() as u32;
+    }
+}
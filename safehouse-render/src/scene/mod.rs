@@ -1,5 +1,24 @@
 mod object;
+mod instance;
+mod order;
 pub use object::*;
+pub(crate) use instance::*;
+pub use order::{RenderLayer, RenderOrder};
+pub(crate) use order::SortKey;
+
+/// A handle to a SceneObject in the `RenderManager`.\
+/// Once it's object is removed the handle is stale, and is rejected rather than referring to a newer object in the same slot.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SceneObjectHandle {
+    pub(crate) tag: usize,
+    pub(crate) generation: u64,
+}
+
+impl SceneObjectHandle {
+    /// The error for using this handle after it's object was removed.
+    pub(crate) fn stale(self) -> crate::SafehouseError {
+        crate::SafehouseError::StaleHandle { tag: self.tag, generation: self.generation }
+    }
+}
 
-pub type SceneObjectHandle = usize;
 pub type ControllerHandle = usize;
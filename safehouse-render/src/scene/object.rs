@@ -1,6 +1,7 @@
 use std::rc::Rc;
 use safehouse_gpu::buffer::{UniformPtr};
 
+use crate::material::MaterialBinding;
 use crate::model::ModelData;
 use crate::gpu::wgpu;
 
@@ -10,8 +11,18 @@ pub struct SceneObject {
     pub pipeline_ref: Option<Rc<wgpu::RenderPipeline>>,
     pub sceneobject_bindgroup: Rc<wgpu::BindGroup>,
     pub entity_bindgroup: Option<Rc<wgpu::BindGroup>>,
+    /// The material drawn with, when the object's pipeline has a material layout.
+    pub(crate) material: Option<Rc<MaterialBinding>>,
     pub model_matrix: UniformPtr<glam::Mat4>,
     pub(crate) model_matrix_changed: bool,  
+    /// Whether this object is drawn in a batch with others that share it's model and pipeline.
+    pub(crate) instanced: bool,
+    /// Hidden objects stay in the scene but are skipped when rendering.
+    pub visible: bool,
+    /// The layer and transparency this object is sorted by when drawing.
+    pub order: super::RenderOrder,
+    /// Matches the generation of the handle this object was added with.
+    pub(crate) generation: u64,
 }
 
 impl SceneObject {
@@ -56,4 +67,40 @@ impl SceneObject {
     pub(crate) fn update_matrix(&self, rm: &crate::RenderManager) {
         self.model_matrix.update(&rm.gpu_state);
     }
+
+    pub fn is_instanced(&self) -> bool {
+        self.instanced
+    }
+
+    pub fn material(&self) -> Option<&Rc<MaterialBinding>> {
+        self.material.as_ref()
+    }
+
+    /// The model, pipeline and material this object shares with the rest of it's instance batch.
+    pub(crate) fn instance_batch_key(&self) -> super::InstanceBatchKey {
+        (
+            Rc::as_ptr(&self.model_data) as usize,
+            self.pipeline_ref.as_ref().map(|p| Rc::as_ptr(p) as usize).unwrap_or(0),
+            self.material.as_ref().map(|m| Rc::as_ptr(m) as usize).unwrap_or(0),
+        )
+    }
+
+    /// The key this object is sorted by, with it's depth along the camera's view.
+    pub(crate) fn sort_key(&self, view: &glam::Mat4) -> super::SortKey {
+        super::SortKey {
+            order: self.order,
+            pipeline: self.pipeline_ref.as_ref().map(|p| Rc::as_ptr(p) as usize).unwrap_or(0),
+            material: match &self.material {
+                Some(material) => Rc::as_ptr(material) as usize,
+                None => self.entity_bindgroup.as_ref().map(|bg| Rc::as_ptr(bg) as usize).unwrap_or(0),
+            },
+            depth: view.transform_point3(self.transform_ref().w_axis.truncate()).z,
+        }
+    }
+
+    pub(crate) fn instance_transform(&self) -> crate::vertex_type::InstanceTransform {
+        crate::vertex_type::InstanceTransform {
+            model: self.transform_ref().to_cols_array_2d(),
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,108 @@
+// Sorting of SceneObjects into the order they are drawn.
+
+/// The layer a SceneObject is drawn in. Layers are drawn in order, lowest first.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderLayer {
+    /// Drawn before the world, e.g. skyboxes.
+    Background,
+
+    /// The main scene.
+    #[default]
+    World,
+
+    /// Drawn last over the whole scene with a cleared depth buffer, e.g. HUD elements like text panes.
+    Overlay,
+}
+
+/// How a SceneObject is ordered when drawing.\
+/// Defaults to the `EntityPipeline` it was spawned with, and can be changed per object.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOrder {
+    pub layer: RenderLayer,
+
+    /// Transparent objects are drawn after the opaque ones in their layer, back-to-front.
+    pub transparent: bool,
+}
+
+/// The key a draw is sorted by: layer, then opaque before transparent.\
+/// Opaque draws are then grouped by pipeline and material and drawn front-to-back,
+/// while transparent draws are drawn back-to-front.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SortKey {
+    pub order: RenderOrder,
+    pub pipeline: usize,
+    pub material: usize,
+    /// View-space depth of the object's origin.
+    pub depth: f32,
+}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.order.layer.cmp(&other.order.layer)
+            .then(self.order.transparent.cmp(&other.order.transparent))
+            .then_with(|| if self.order.transparent {
+                other.depth.total_cmp(&self.depth)
+            } else {
+                self.pipeline.cmp(&other.pipeline)
+                    .then(self.material.cmp(&other.material))
+                    .then(self.depth.total_cmp(&other.depth))
+            })
+    }
+}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Keys are equal when they sort the same, so transparent draws ignore their pipeline and material.
+impl PartialEq for SortKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for SortKey {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(layer: RenderLayer, transparent: bool, pipeline: usize, depth: f32) -> SortKey {
+        SortKey { order: RenderOrder { layer, transparent }, pipeline, material: 0, depth }
+    }
+
+    fn sorted(mut keys: Vec<SortKey>) -> Vec<SortKey> {
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn layers_sort_before_pipelines() {
+        let world = key(RenderLayer::World, false, 1, 0.0);
+        let background = key(RenderLayer::Background, false, 2, 0.0);
+        let overlay = key(RenderLayer::Overlay, false, 0, 0.0);
+        assert_eq!(sorted(vec![overlay, world, background]), [background, world, overlay]);
+
+        // Transparent draws come after every opaque draw in their layer, but before the next layer
+        let glass = key(RenderLayer::World, true, 0, 0.0);
+        assert_eq!(sorted(vec![overlay, glass, world]), [world, glass, overlay]);
+    }
+
+    #[test]
+    fn opaque_draws_group_by_pipeline_then_go_front_to_back() {
+        let near_b = key(RenderLayer::World, false, 2, 1.0);
+        let far_a = key(RenderLayer::World, false, 1, 10.0);
+        let near_a = key(RenderLayer::World, false, 1, 1.0);
+        assert_eq!(sorted(vec![near_b, far_a, near_a]), [near_a, far_a, near_b]);
+    }
+
+    #[test]
+    fn transparent_draws_go_back_to_front() {
+        let near = key(RenderLayer::World, true, 1, 1.0);
+        let far = key(RenderLayer::World, true, 2, 10.0);
+        let middle = key(RenderLayer::World, true, 1, 5.0);
+        assert_eq!(sorted(vec![near, far, middle]), [far, middle, near]);
+    }
+}
@@ -1,10 +1,257 @@
 use std::rc::Rc;
 
 use crate::gpu;
+use gpu::wgpu;
 
 // TODO: Decouple Vertex and Fragment shaders
 
 pub struct FragmentShader {
     shader_program: Rc<gpu::shaderprogram::Program>
     // shader_bindgroup: Rc<wgpu::BindGroup>,
-}
\ No newline at end of file
+}
+
+/// The name of the module declaring the global and SceneObject bindings, included in every shader built by the manager.
+pub const BINDINGS_MODULE: &str = "safehouse/bindings";
+
+/// Declares `camera`, `time` and `obj_mat` from the global and SceneObject bindgroups.
+pub fn bindings_module() -> String {
+    use crate::bindgroups::{global, sceneobject, BINDGROUP_GLOBAL, BINDGROUP_SCENEOBJECT};
+
+    format!("
+struct Camera {{
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+}}
+
+@group({BINDGROUP_GLOBAL}) @binding({camera})
+var<uniform> camera: Camera;
+@group({BINDGROUP_GLOBAL}) @binding({time})
+var<uniform> time: f32;
+
+@group({BINDGROUP_SCENEOBJECT}) @binding({model_matrix})
+var<uniform> obj_mat: mat4x4<f32>;
+",
+        camera = global::CAMERA,
+        time = global::TIME,
+        model_matrix = sceneobject::MODEL_MATRIX,
+    )
+}
+
+/// A `ShaderBuilder` with the bindings module in it's prelude, and the bindgroup indices defined.
+pub fn default_builder() -> safehouse_shader::ShaderBuilder {
+    let mut builder = safehouse_shader::ShaderBuilder::new();
+    builder
+        .add_module(BINDINGS_MODULE, &bindings_module())
+        .add_prelude(BINDINGS_MODULE)
+        .define("BINDGROUP_GLOBAL", crate::bindgroups::BINDGROUP_GLOBAL)
+        .define("BINDGROUP_SCENEOBJECT", crate::bindgroups::BINDGROUP_SCENEOBJECT)
+        .define("BINDGROUP_MATERIAL", crate::bindgroups::BINDGROUP_MATERIAL);
+    builder
+}
+
+/// The scalar kind a vertex format is read as in the shader.
+fn vertex_format_kind(format: wgpu::VertexFormat) -> safehouse_shader::ScalarKind {
+    use wgpu::VertexFormat::*;
+    match format {
+        Uint8 | Uint8x2 | Uint8x4 | Uint16 | Uint16x2 | Uint16x4 | Uint32 | Uint32x2 | Uint32x3 | Uint32x4 => safehouse_shader::ScalarKind::Uint,
+        Sint8 | Sint8x2 | Sint8x4 | Sint16 | Sint16x2 | Sint16x4 | Sint32 | Sint32x2 | Sint32x3 | Sint32x4 => safehouse_shader::ScalarKind::Sint,
+        _ => safehouse_shader::ScalarKind::Float,
+    }
+}
+
+/// Whether a shader binding can be bound with a layout entry of type `ty`.
+fn binding_matches(kind: safehouse_shader::BindingKind, ty: &wgpu::BindingType) -> bool {
+    use safehouse_shader::{BindingKind, ScalarKind};
+    match (kind, ty) {
+        (BindingKind::Uniform, wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, .. }) => true,
+        (BindingKind::Storage { read_only }, wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: layout_read_only }, .. }) => read_only || !layout_read_only,
+        (BindingKind::Texture { kind, multisampled }, wgpu::BindingType::Texture { sample_type, multisampled: layout_multisampled, .. }) => {
+            multisampled == *layout_multisampled && matches!(
+                (kind, sample_type),
+                (ScalarKind::Float, wgpu::TextureSampleType::Float { .. } | wgpu::TextureSampleType::Depth)
+                | (ScalarKind::Sint, wgpu::TextureSampleType::Sint)
+                | (ScalarKind::Uint, wgpu::TextureSampleType::Uint)
+            )
+        },
+        (BindingKind::DepthTexture { multisampled }, wgpu::BindingType::Texture { sample_type: wgpu::TextureSampleType::Depth, multisampled: layout_multisampled, .. }) => multisampled == *layout_multisampled,
+        (BindingKind::StorageTexture, wgpu::BindingType::StorageTexture { .. }) => true,
+        (BindingKind::Sampler { comparison }, wgpu::BindingType::Sampler(sampler)) => comparison == (*sampler == wgpu::SamplerBindingType::Comparison),
+        _ => false,
+    }
+}
+
+fn stage_visibility(stage: safehouse_shader::ShaderStage) -> wgpu::ShaderStages {
+    match stage {
+        safehouse_shader::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+        safehouse_shader::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+        safehouse_shader::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+    }
+}
+
+/// Check that an entity's shader matches the bindgroups and vertex buffers it will be used with,
+/// so a mismatch is reported by name instead of as a wgpu validation panic.\
+/// The entry points that are `Some` must be in the shader, so separate vertex and fragment programs are each checked for their own stage.
+pub(crate) fn check_entity_interface(
+    shader_name: &str,
+    reflection: &safehouse_shader::ShaderReflection,
+    vertex_entry: Option<&str>,
+    fragment_entry: Option<&str>,
+    vertex_buffers: &[wgpu::VertexBufferLayout],
+    group_count: u32,
+    group_entity: u32,
+    entity_entries: &[wgpu::BindGroupLayoutEntry],
+) -> crate::SafehouseResult<()> {
+    let mismatch = |message: String| crate::SafehouseError::ShaderMismatch { label: String::from(shader_name), message };
+
+    if let Some(group) = reflection.max_group().filter(|group| *group >= group_count) {
+        return Err(mismatch(format!("it uses bindgroup {} but the pipeline only has {} bindgroups", group, group_count)));
+    }
+
+    if group_entity != 0 {
+        for binding in reflection.used_bindings(group_entity) {
+            let name = binding.name.as_deref().unwrap_or("?");
+            let entry = entity_entries.iter().find(|e| e.binding == binding.binding)
+                .ok_or_else(|| mismatch(format!("'{}' at binding {} has no Binder", name, binding.binding)))?;
+
+            if !binding_matches(binding.kind, &entry.ty) {
+                return Err(mismatch(format!("'{}' at binding {} is {:?} but it's Binder is {:?}", name, binding.binding, binding.kind, entry.ty)));
+            }
+
+            for stage in &binding.stages {
+                if !entry.visibility.contains(stage_visibility(*stage)) {
+                    return Err(mismatch(format!("'{}' at binding {} is used in the {:?} stage but it's Binder is only visible to {:?}", name, binding.binding, stage, entry.visibility)));
+                }
+            }
+        }
+    }
+
+    if let Some(fragment_entry) = fragment_entry {
+        reflection.entry_point(fragment_entry)
+            .filter(|entry| entry.stage == safehouse_shader::ShaderStage::Fragment)
+            .ok_or_else(|| mismatch(format!("it has no fragment entry point '{}'", fragment_entry)))?;
+    }
+
+    let Some(vertex_entry) = vertex_entry else {
+        return Ok(());
+    };
+
+    let entry = reflection.entry_point(vertex_entry)
+        .filter(|entry| entry.stage == safehouse_shader::ShaderStage::Vertex)
+        .ok_or_else(|| mismatch(format!("it has no vertex entry point '{}'", vertex_entry)))?;
+
+    for input in &entry.vertex_inputs {
+        let name = input.name.as_deref().unwrap_or("?");
+        let attribute = vertex_buffers.iter()
+            .flat_map(|buffer| buffer.attributes.iter())
+            .find(|attribute| attribute.shader_location == input.location)
+            .ok_or_else(|| mismatch(format!("vertex input '{}' at location {} is not in the vertex type", name, input.location)))?;
+
+        if let Some(ty) = input.ty {
+            if ty.kind != vertex_format_kind(attribute.format) {
+                return Err(mismatch(format!("vertex input '{}' at location {} is {:?} but the vertex type gives {:?}", name, input.location, ty.kind, attribute.format)));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = "
+@group(0) @binding(0) var<uniform> tint: vec4<f32>;
+@group(3) @binding(0) var<uniform> offset: vec4<f32>;
+
+@vertex
+fn vs_main(@location(0) pos: vec4<f32>, @location(1) color: vec4<f32>) -> @builtin(position) vec4<f32> {
+    return pos + offset + color * 0.0;
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return tint;
+}
+";
+
+    const FLOAT_ATTRIBUTES: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4];
+    const UINT_ATTRIBUTES: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x4, 1 => Uint32x4];
+
+    fn vertex_buffer(attributes: &'static [wgpu::VertexAttribute]) -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: 32,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes,
+        }
+    }
+
+    fn uniform_entry(visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+            count: None,
+        }
+    }
+
+    /// Check `SHADER` with both entry points, the entity bindgroup at index 3 and the given vertex buffers and entity entries.
+    fn check(group_count: u32, vertex_buffers: &[wgpu::VertexBufferLayout], entity_entries: &[wgpu::BindGroupLayoutEntry]) -> crate::SafehouseResult<()> {
+        let reflection = safehouse_shader::validate("test", SHADER).unwrap();
+        check_entity_interface("test", &reflection, Some("vs_main"), Some("fs_main"), vertex_buffers, group_count, 3, entity_entries)
+    }
+
+    fn mismatch_message(result: crate::SafehouseResult<()>) -> String {
+        match result {
+            Err(crate::SafehouseError::ShaderMismatch { label, message }) => {
+                assert_eq!(label, "test");
+                message
+            },
+            other => panic!("Expected a shader mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn matching_interfaces_pass() {
+        check(4, &[vertex_buffer(&FLOAT_ATTRIBUTES)], &[uniform_entry(wgpu::ShaderStages::VERTEX)]).unwrap();
+    }
+
+    #[test]
+    fn vertex_inputs_must_match_the_vertex_type() {
+        let message = mismatch_message(check(4, &[vertex_buffer(&UINT_ATTRIBUTES)], &[uniform_entry(wgpu::ShaderStages::VERTEX)]));
+        assert!(message.contains("'color' at location 1"), "{}", message);
+
+        let message = mismatch_message(check(4, &[vertex_buffer(&FLOAT_ATTRIBUTES[..1])], &[uniform_entry(wgpu::ShaderStages::VERTEX)]));
+        assert!(message.contains("is not in the vertex type"), "{}", message);
+    }
+
+    #[test]
+    fn bindings_must_be_in_a_declared_group() {
+        let message = mismatch_message(check(3, &[vertex_buffer(&FLOAT_ATTRIBUTES)], &[uniform_entry(wgpu::ShaderStages::VERTEX)]));
+        assert!(message.contains("uses bindgroup 3"), "{}", message);
+    }
+
+    #[test]
+    fn entity_bindings_must_have_a_visible_binder() {
+        let message = mismatch_message(check(4, &[vertex_buffer(&FLOAT_ATTRIBUTES)], &[]));
+        assert!(message.contains("has no Binder"), "{}", message);
+
+        let message = mismatch_message(check(4, &[vertex_buffer(&FLOAT_ATTRIBUTES)], &[uniform_entry(wgpu::ShaderStages::FRAGMENT)]));
+        assert!(message.contains("Vertex stage"), "{}", message);
+    }
+
+    #[test]
+    fn missing_entry_points_are_mismatches() {
+        let reflection = safehouse_shader::validate("test", SHADER).unwrap();
+        let buffers = [vertex_buffer(&FLOAT_ATTRIBUTES)];
+        let entries = [uniform_entry(wgpu::ShaderStages::VERTEX)];
+
+        let message = mismatch_message(check_entity_interface("test", &reflection, Some("main"), None, &buffers, 4, 3, &entries));
+        assert!(message.contains("no vertex entry point 'main'"), "{}", message);
+
+        // An entry point of the wrong stage doesn't count
+        let message = mismatch_message(check_entity_interface("test", &reflection, None, Some("vs_main"), &buffers, 4, 3, &entries));
+        assert!(message.contains("no fragment entry point 'vs_main'"), "{}", message);
+    }
+}
@@ -1,3 +1,3 @@
 use safehouse_gpu::wgpu;
 
-pub use safehouse_shared::vertex::{AdvVertex, ColorVertex, TexVertex};
\ No newline at end of file
+pub use safehouse_shared::vertex::{AdvVertex, ColorVertex, TexVertex, SkinnedVertex, TangentVertex, InstanceTransform, INSTANCE_TRANSFORM_LOCATION};
\ No newline at end of file
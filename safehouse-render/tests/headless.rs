@@ -0,0 +1,522 @@
+// Renders the default pipeline without a window and checks the pixels.
+// Skipped when no adapter (not even a software one) is available.
+
+use safehouse_render::{camera::Camera, entity::{Entity, EntityPipeline, EntityShaderEntry, LoadTimeResource}, gpu::{self, binding::Binder, buffer::VertexBuffer}, model::ModelData, named_entity, scene::SceneObjectHandle, vertex_type::ColorVertex, RenderManager, SafehouseError, SafehouseResult};
+
+const SIZE: u32 = 64;
+const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+/// A small red triangle in front of the camera, drawn with the default pipeline.
+struct Triangle;
+
+impl Entity for Triangle {
+    const ENTITY_TYPE_NAME: &'static str = "Triangle";
+
+    fn on_instantiate(_rm: &mut RenderManager, _handle: SceneObjectHandle) -> Self {
+        Self
+    }
+
+    fn load_bindings<'a>() -> Vec<Binder<Self>> {
+        vec![]
+    }
+
+    fn load_model(state: &gpu::State) -> SafehouseResult<ModelData> {
+        let vertices = [
+            ColorVertex::new([-0.5, -0.5, 1.0, 1.0], RED),
+            ColorVertex::new([0.0, 0.5, 1.0, 1.0], RED),
+            ColorVertex::new([0.5, -0.5, 1.0, 1.0], RED),
+        ];
+        Ok(ModelData::new::<Self, ()>(state, VertexBuffer::new(state, &vertices), vec![0..3], None))
+    }
+
+    fn load_pipeline(_rm: &RenderManager) -> Option<EntityPipeline> {
+        None
+    }
+
+    fn load_shader(_rm: &RenderManager, _group_model: u32, _group_entity: u32) -> Option<EntityShaderEntry> {
+        None
+    }
+}
+
+named_entity!(Triangle);
+
+fn headless_manager() -> Option<RenderManager> {
+    match RenderManager::try_new_headless(SIZE, SIZE) {
+        Ok(rm) => Some(rm),
+        Err(e) => {
+            println!("Skipping headless test, no adapter is available: {}", e);
+            None
+        },
+    }
+}
+
+#[test]
+fn renders_default_pipeline_headless() {
+    let Some(mut rm) = headless_manager() else {
+        return;
+    };
+
+    rm.load_entity::<Triangle>().expect("Could not load the triangle!");
+    rm.spawn_sceneobject_entity::<Triangle>("triangle").expect("Could not spawn the triangle!");
+
+    let camera = Camera::new(SIZE as f32, SIZE as f32);
+    let frame = rm.render_to_image(&camera).expect("Could not capture the frame!");
+
+    assert_eq!(frame.dimensions(), (SIZE, SIZE));
+    assert_eq!(frame.get_pixel(SIZE / 2, SIZE / 2).0, [255, 0, 0, 255], "The triangle should cover the center");
+    assert_eq!(frame.get_pixel(0, 0).0, [0, 0, 0, 255], "The corner should be the clear color");
+}
+
+#[test]
+fn capture_of_unsupported_format_is_an_error() {
+    let desc = gpu::descriptor::StateDescriptor::default().with_surface_formats(&[gpu::wgpu::TextureFormat::Rgba16Float]);
+    let mut rm = match RenderManager::headless_with_descriptor(SIZE, SIZE, &desc) {
+        Ok(rm) => rm,
+        Err(e) => {
+            println!("Skipping headless test, no adapter is available: {}", e);
+            return;
+        },
+    };
+
+    assert!(!rm.can_capture());
+    let camera = Camera::new(SIZE as f32, SIZE as f32);
+    assert!(matches!(rm.render_to_image(&camera), Err(gpu::error::SafehouseError::Readback(_))));
+}
+
+/// Render the triangle with MSAA, setting the sample count before or after the entity is loaded.
+fn render_multisampled(set_before_load: bool) -> Option<gpu::image::RgbaImage> {
+    let mut rm = headless_manager()?;
+
+    if set_before_load {
+        rm.set_sample_count(4).ok()?;
+    }
+    rm.load_entity::<Triangle>().expect("Could not load the triangle!");
+    rm.spawn_sceneobject_entity::<Triangle>("triangle").expect("Could not spawn the triangle!");
+    if !set_before_load {
+        rm.set_sample_count(4).ok()?;
+    }
+
+    let camera = Camera::new(SIZE as f32, SIZE as f32);
+    Some(rm.render_to_image(&camera).expect("Could not capture the frame!"))
+}
+
+#[test]
+fn sample_count_change_rebuilds_loaded_pipelines() {
+    let Some(mut rm) = headless_manager() else {
+        return;
+    };
+
+    rm.load_entity::<Triangle>().expect("Could not load the triangle!");
+    assert!(matches!(rm.set_sample_count(3), Err(gpu::error::SafehouseError::UnsupportedSampleCount(3))));
+    assert_eq!(rm.sample_count(), 1);
+
+    // A pipeline left at the old sample count fails validation when drawn in the multisampled pass
+    let (Some(before), Some(after)) = (render_multisampled(true), render_multisampled(false)) else {
+        println!("Skipping MSAA check, the adapter doesn't support it.");
+        return;
+    };
+    for (frame, when) in [(before, "before"), (after, "after")] {
+        assert_eq!(frame.get_pixel(SIZE / 2, SIZE / 2).0, [255, 0, 0, 255], "The triangle should cover the center with the sample count set {} loading", when);
+        assert_eq!(frame.get_pixel(0, 0).0, [0, 0, 0, 255], "The corner should be the clear color with the sample count set {} loading", when);
+    }
+}
+
+#[test]
+fn invalid_scene_object_handles_are_rejected() {
+    let Some(mut rm) = headless_manager() else {
+        return;
+    };
+
+    // A default handle was never given out, and must not panic past the end of the empty map
+    assert!(rm.get_scene_object(SceneObjectHandle::default()).is_none());
+    assert!(rm.set_visible(SceneObjectHandle::default(), false).is_err());
+    assert!(rm.remove_scene_object(SceneObjectHandle::default()).is_err());
+
+    rm.load_entity::<Triangle>().expect("Could not load the triangle!");
+    let handle = rm.add_scene_object("triangle", Triangle::model_name(), Triangle::pipeline_name()).expect("Could not add the triangle!");
+    assert!(rm.get_scene_object(SceneObjectHandle::default()).is_none(), "The default handle's generation is never given out");
+
+    rm.remove_scene_object(handle).expect("Could not remove the triangle!");
+    assert!(!rm.is_valid_handle(handle));
+    assert!(rm.remove_scene_object(handle).is_err());
+
+    // The freed slot is reused, but the old handle stays stale
+    let reused = rm.add_scene_object("triangle", Triangle::model_name(), Triangle::pipeline_name()).expect("Could not add the triangle!");
+    assert!(rm.is_valid_handle(reused));
+    assert!(!rm.is_valid_handle(handle));
+}
+
+// `named_entity!` imports into the module it is used in, so each entity gets it's own module
+mod blended {
+    use super::*;
+    use safehouse_render::entity::{BlendMode, EntityColorTarget};
+
+    /// The triangle at half opacity, alpha blended over the clear color.
+    pub struct HalfRed;
+
+    impl Entity for HalfRed {
+        const ENTITY_TYPE_NAME: &'static str = "HalfRed";
+
+        fn on_instantiate(_rm: &mut RenderManager, _handle: SceneObjectHandle) -> Self {
+            Self
+        }
+
+        fn load_bindings<'a>() -> Vec<Binder<Self>> {
+            vec![]
+        }
+
+        fn load_model(state: &gpu::State) -> SafehouseResult<ModelData> {
+            let half_red = [1.0, 0.0, 0.0, 0.5];
+            let vertices = [
+                ColorVertex::new([-0.5, -0.5, 1.0, 1.0], half_red),
+                ColorVertex::new([0.0, 0.5, 1.0, 1.0], half_red),
+                ColorVertex::new([0.5, -0.5, 1.0, 1.0], half_red),
+            ];
+            Ok(ModelData::new::<Self, ()>(state, VertexBuffer::new(state, &vertices), vec![0..3], None))
+        }
+
+        fn load_pipeline(_rm: &RenderManager) -> Option<EntityPipeline> {
+            Some(EntityPipeline {
+                color_target: EntityColorTarget::blended(BlendMode::Alpha),
+                ..Default::default()
+            })
+        }
+
+        fn load_shader(_rm: &RenderManager, _group_model: u32, _group_entity: u32) -> Option<EntityShaderEntry> {
+            Some(EntityShaderEntry::Combined(LoadTimeResource::UseDefault))
+        }
+    }
+
+    named_entity!(HalfRed);
+}
+
+#[test]
+fn alpha_blended_pipelines_blend_with_the_target() {
+    let Some(mut rm) = headless_manager() else {
+        return;
+    };
+
+    rm.load_entity::<blended::HalfRed>().expect("Could not load the blended triangle!");
+    rm.spawn_sceneobject_entity::<blended::HalfRed>("half_red").expect("Could not spawn the blended triangle!");
+
+    let camera = Camera::new(SIZE as f32, SIZE as f32);
+    let frame = rm.render_to_image(&camera).expect("Could not capture the frame!");
+
+    // Half of linear red over black, stored in an sRGB target
+    let [r, g, b, a] = frame.get_pixel(SIZE / 2, SIZE / 2).0;
+    assert!((180..=195).contains(&r), "Red should be blended to about 188, got {}", r);
+    assert_eq!([g, b, a], [0, 0, 255]);
+}
+
+mod bound {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    thread_local! {
+        pub static INSTANTIATED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// An entity with a uniform binding, which can't be spawned before it's bindgroup layout is loaded.
+    pub struct Bound {
+        tint: Rc<gpu::buffer::Uniform<[f32; 4]>>,
+    }
+
+    impl Entity for Bound {
+        const ENTITY_TYPE_NAME: &'static str = "Bound";
+
+        fn on_instantiate(rm: &mut RenderManager, _handle: SceneObjectHandle) -> Self {
+            INSTANTIATED.with(|flag| flag.set(true));
+            Self { tint: gpu::buffer::Uniform::new(&rm.gpu_state, &[[1.0; 4]]) }
+        }
+
+        fn load_bindings<'a>() -> Vec<Binder<Self>> {
+            vec![Binder::new(0, gpu::wgpu::ShaderStages::all(), &|x: &Bound| x.tint.as_ref())]
+        }
+
+        fn load_model(state: &gpu::State) -> SafehouseResult<ModelData> {
+            super::Triangle::load_model(state)
+        }
+
+        fn load_pipeline(_rm: &RenderManager) -> Option<EntityPipeline> {
+            None
+        }
+
+        fn load_shader(_rm: &RenderManager, _group_model: u32, _group_entity: u32) -> Option<EntityShaderEntry> {
+            None
+        }
+    }
+
+    named_entity!(Bound);
+}
+
+#[test]
+fn spawning_an_unloaded_entity_fails_before_instantiating() {
+    let Some(mut rm) = headless_manager() else {
+        return;
+    };
+
+    let result = rm.spawn_sceneobject_entity::<bound::Bound>("bound");
+    assert!(matches!(result, Err(SafehouseError::MissingResource { kind: gpu::error::ResourceKind::BindGroupLayout, .. })));
+    assert!(!bound::INSTANTIATED.with(|flag| flag.get()), "The entity must not be instantiated when it can't be spawned");
+}
+
+mod unclipped {
+    use super::*;
+
+    /// The triangle with `unclipped_depth`, which needs `Features::DEPTH_CLIP_CONTROL`.
+    pub struct Unclipped;
+
+    impl Entity for Unclipped {
+        const ENTITY_TYPE_NAME: &'static str = "Unclipped";
+
+        fn on_instantiate(_rm: &mut RenderManager, _handle: SceneObjectHandle) -> Self {
+            Self
+        }
+
+        fn load_bindings<'a>() -> Vec<Binder<Self>> {
+            vec![]
+        }
+
+        fn load_model(state: &gpu::State) -> SafehouseResult<ModelData> {
+            super::Triangle::load_model(state)
+        }
+
+        fn load_pipeline(_rm: &RenderManager) -> Option<EntityPipeline> {
+            let mut pipeline = EntityPipeline::default();
+            pipeline.primitive.unclipped_depth = true;
+            Some(pipeline)
+        }
+
+        fn load_shader(_rm: &RenderManager, _group_model: u32, _group_entity: u32) -> Option<EntityShaderEntry> {
+            Some(EntityShaderEntry::Combined(LoadTimeResource::UseDefault))
+        }
+    }
+
+    named_entity!(Unclipped);
+}
+
+#[test]
+fn pipeline_creation_errors_name_the_pipeline() {
+    let Some(mut rm) = headless_manager() else {
+        return;
+    };
+    if rm.gpu_state.device.features().contains(gpu::wgpu::Features::DEPTH_CLIP_CONTROL) {
+        println!("Skipping pipeline creation check, the device supports unclipped depth.");
+        return;
+    }
+
+    match rm.load_entity::<unclipped::Unclipped>() {
+        Err(gpu::error::SafehouseError::PipelineCreation { pipeline, .. }) => assert_eq!(pipeline, unclipped::Unclipped::pipeline_name()),
+        Err(e) => panic!("Expected a pipeline creation error, got {}", e),
+        Ok(()) => panic!("The pipeline should need DEPTH_CLIP_CONTROL"),
+    }
+}
+
+#[test]
+fn hot_reload_falls_back_to_embedded_shaders() {
+    let Some(mut rm) = headless_manager() else {
+        return;
+    };
+    rm.enable_shader_hot_reload();
+
+    let embedded = "@vertex\nfn vs_main() -> @builtin(position) vec4<f32> {\n    return camera.position;\n}\n";
+    rm.build_shader_file("embedded_only", "/nonexistent/embedded_only.wgsl", embedded, 0, 0)
+        .expect("A missing shader file should fall back to the embedded source");
+}
+
+mod searched {
+    use super::*;
+
+    /// An entity without a pipeline, asking for a shader that was never added.
+    pub struct Searched;
+
+    impl Entity for Searched {
+        const ENTITY_TYPE_NAME: &'static str = "Searched";
+
+        fn on_instantiate(_rm: &mut RenderManager, _handle: SceneObjectHandle) -> Self {
+            Self
+        }
+
+        fn load_bindings<'a>() -> Vec<Binder<Self>> {
+            vec![]
+        }
+
+        fn load_model(state: &gpu::State) -> SafehouseResult<ModelData> {
+            super::Triangle::load_model(state)
+        }
+
+        fn load_pipeline(_rm: &RenderManager) -> Option<EntityPipeline> {
+            None
+        }
+
+        fn load_shader(_rm: &RenderManager, _group_model: u32, _group_entity: u32) -> Option<EntityShaderEntry> {
+            Some(EntityShaderEntry::Combined(LoadTimeResource::Search("never_added")))
+        }
+    }
+
+    named_entity!(Searched);
+}
+
+#[test]
+fn missing_shaders_fail_loading_without_a_pipeline() {
+    let Some(mut rm) = headless_manager() else {
+        return;
+    };
+
+    let result = rm.load_entity::<searched::Searched>();
+    assert!(matches!(result, Err(SafehouseError::MissingResource { kind: gpu::error::ResourceKind::Shader, name }) if name == "never_added"));
+}
+
+mod grouped {
+    use super::*;
+    use std::rc::Rc;
+    use safehouse_render::{material::{GroupMaterial, MaterialLayout, MaterialTexture}, vertex_type::TexVertex};
+
+    const SHADER: &str = "
+@group(#{BINDGROUP_MATERIAL}) @binding(0) var<uniform> tint: vec4<f32>;
+@group(#{BINDGROUP_MATERIAL}) @binding(1) var tex: texture_2d<f32>;
+@group(#{BINDGROUP_MATERIAL}) @binding(2) var samp: sampler;
+
+struct VOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@location(0) pos: vec4<f32>, @location(1) uv: vec2<f32>) -> VOut {
+    var o: VOut;
+    o.pos = camera.view_proj * obj_mat * pos;
+    o.uv = uv;
+    return o;
+}
+
+@fragment
+fn fs_main(i: VOut) -> @location(0) vec4<f32> {
+    return tint * textureSample(tex, samp, i.uv);
+}
+";
+
+    /// A triangle left of the center and one right of it, in two groups.
+    pub fn two_groups(state: &gpu::State) -> ModelData {
+        let vertex = |x: f32, y: f32| TexVertex { pos: [x, y, 1.0, 1.0], tex_coord: [0.5, 0.5] };
+        let vertices = [
+            vertex(-0.9, -0.5), vertex(-0.1, -0.5), vertex(-0.5, 0.5),
+            vertex(0.1, -0.5), vertex(0.9, -0.5), vertex(0.5, 0.5),
+        ];
+        ModelData::new::<GroupedQuad, ()>(state, VertexBuffer::new(state, &vertices), vec![0..3, 3..6], None)
+    }
+
+    /// A group material tinting a solid texture.
+    pub fn group_material(state: &gpu::State, name: &str, tint: [f32; 4], texel: [u8; 4]) -> SafehouseResult<GroupMaterial> {
+        let texture = MaterialTexture {
+            texture: Rc::new(gpu::texture::Texture::solid(state, texel)),
+            sampler: Rc::clone(state.get_sampler("default")?),
+        };
+        Ok(GroupMaterial::new(name, tint, vec![texture]))
+    }
+
+    pub fn load_pipeline() -> Option<EntityPipeline> {
+        Some(EntityPipeline {
+            material: Some(MaterialLayout::new::<[f32; 4]>(1)),
+            ..Default::default()
+        })
+    }
+
+    pub fn load_shader(rm: &RenderManager, name: &str, group_model: u32, group_entity: u32) -> Option<EntityShaderEntry> {
+        Some(rm.build_shader(name, SHADER, group_model, group_entity).expect("Could not build the group material shader!").into())
+    }
+
+    /// Red from the left group's parameters, and green from the right group's texture.
+    pub struct GroupedQuad;
+
+    impl Entity for GroupedQuad {
+        const ENTITY_TYPE_NAME: &'static str = "GroupedQuad";
+
+        fn on_instantiate(_rm: &mut RenderManager, _handle: SceneObjectHandle) -> Self {
+            Self
+        }
+
+        fn load_bindings<'a>() -> Vec<Binder<Self>> {
+            vec![]
+        }
+
+        fn load_model(state: &gpu::State) -> SafehouseResult<ModelData> {
+            Ok(two_groups(state).with_group_materials(vec![
+                Some(group_material(state, "red", [1.0, 0.0, 0.0, 1.0], [255, 255, 255, 255])?),
+                Some(group_material(state, "green", [1.0, 1.0, 1.0, 1.0], [0, 255, 0, 255])?),
+            ]))
+        }
+
+        fn load_pipeline(_rm: &RenderManager) -> Option<EntityPipeline> {
+            load_pipeline()
+        }
+
+        fn load_shader(rm: &RenderManager, group_model: u32, group_entity: u32) -> Option<EntityShaderEntry> {
+            load_shader(rm, Self::shader_name(), group_model, group_entity)
+        }
+    }
+
+    named_entity!(GroupedQuad);
+}
+
+mod miscounted {
+    use super::*;
+
+    /// The two group model with only one group material.
+    pub struct Miscounted;
+
+    impl Entity for Miscounted {
+        const ENTITY_TYPE_NAME: &'static str = "Miscounted";
+
+        fn on_instantiate(_rm: &mut RenderManager, _handle: SceneObjectHandle) -> Self {
+            Self
+        }
+
+        fn load_bindings<'a>() -> Vec<Binder<Self>> {
+            vec![]
+        }
+
+        fn load_model(state: &gpu::State) -> SafehouseResult<ModelData> {
+            Ok(super::grouped::two_groups(state).with_group_materials(vec![
+                Some(super::grouped::group_material(state, "red", [1.0, 0.0, 0.0, 1.0], [255, 255, 255, 255])?),
+            ]))
+        }
+
+        fn load_pipeline(_rm: &RenderManager) -> Option<EntityPipeline> {
+            super::grouped::load_pipeline()
+        }
+
+        fn load_shader(rm: &RenderManager, group_model: u32, group_entity: u32) -> Option<EntityShaderEntry> {
+            super::grouped::load_shader(rm, Self::shader_name(), group_model, group_entity)
+        }
+    }
+
+    named_entity!(Miscounted);
+}
+
+#[test]
+fn group_materials_draw_each_group_with_its_own_material() {
+    let Some(mut rm) = headless_manager() else {
+        return;
+    };
+
+    rm.load_entity::<grouped::GroupedQuad>().expect("Could not load the grouped model!");
+    rm.spawn_sceneobject_entity::<grouped::GroupedQuad>("grouped").expect("Could not spawn the grouped model!");
+
+    let camera = Camera::new(SIZE as f32, SIZE as f32);
+    let frame = rm.render_to_image(&camera).expect("Could not capture the frame!");
+
+    assert_eq!(frame.get_pixel(SIZE / 4, SIZE / 2).0, [255, 0, 0, 255], "The left group should use the red material's parameters");
+    assert_eq!(frame.get_pixel(SIZE * 3 / 4, SIZE / 2).0, [0, 255, 0, 255], "The right group should use the green material's texture");
+    assert_eq!(frame.get_pixel(SIZE / 2, SIZE / 2).0, [0, 0, 0, 255], "The gap between the groups should be the clear color");
+}
+
+#[test]
+fn group_material_count_must_match_the_groups() {
+    let Some(mut rm) = headless_manager() else {
+        return;
+    };
+
+    let result = rm.load_entity::<miscounted::Miscounted>();
+    assert!(matches!(result, Err(SafehouseError::ModelMismatch { message, .. }) if message.contains("1 group materials for 2 groups")));
+}
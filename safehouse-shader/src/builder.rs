@@ -0,0 +1,390 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{ShaderError, ShaderReflection};
+
+/// Composes WGSL from named modules, with a small preprocessor:
+///
+/// -   `#include "name"` pastes in a module added with `add_module`. Each module is only included once per shader.
+/// -   `#define NAME value` defines `NAME` for the rest of the shader.
+/// -   `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif` select variants.
+/// -   `#{NAME}` is replaced with the value of `NAME`, e.g. `@group(#{GROUP_ENTITY})`.
+///
+/// Modules in the prelude are included at the start of every shader, so entities only write their unique code.
+#[derive(Debug, Clone, Default)]
+pub struct ShaderBuilder {
+    global: HashMap<String, String>,
+    prelude: Vec<String>,
+    defines: HashMap<String, String>,
+}
+
+/// The state of one `#ifdef`/`#ifndef` block.
+struct Conditional {
+    line: usize,
+    /// Whether the enclosing block is active.
+    parent_active: bool,
+    /// Whether the current branch is taken.
+    taken: bool,
+    seen_else: bool,
+}
+
+impl Conditional {
+    fn active(&self) -> bool {
+        self.parent_active && self.taken
+    }
+}
+
+/// The state of a single `build`.
+struct Expansion {
+    defines: HashMap<String, String>,
+    included: HashSet<String>,
+    stack: Vec<String>,
+    out: String,
+    /// The module and line that each output line came from.
+    lines: Vec<(String, usize)>,
+}
+
+impl Expansion {
+    /// Point an error in the output back at the module and line it came from, with the line's code as context.
+    fn remap(&self, error: ShaderError) -> ShaderError {
+        let locate = |line: usize| {
+            let (module, source_line) = self.lines.get(line.checked_sub(1)?)?;
+            let text = self.out.lines().nth(line - 1).unwrap_or("").trim();
+            Some((module.clone(), *source_line, text))
+        };
+
+        match error {
+            ShaderError::Parse { module, line, column, message } => match locate(line) {
+                Some((module, line, text)) => ShaderError::Parse { module, line, column, message: format!("{}\n    | {}", message, text) },
+                None => ShaderError::Parse { module, line, column, message },
+            },
+            ShaderError::Validation { module, line: Some(line), message } => match locate(line) {
+                Some((module, line, text)) => ShaderError::Validation { module, line: Some(line), message: format!("{}\n    | {}", message, text) },
+                None => ShaderError::Validation { module, line: Some(line), message },
+            },
+            e => e,
+        }
+    }
+}
+
+impl ShaderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a module that can be included by name. Replaces any module with the same name.
+    pub fn add_module(&mut self, name: &str, source: &str) -> &mut Self {
+        if self.global.insert(String::from(name), String::from(source)).is_some() {
+            println!("Replaced shader module '{}'.", name);
+        }
+        self
+    }
+
+    pub fn get_module(&self, name: &str) -> Option<&str> {
+        self.global.get(name).map(String::as_str)
+    }
+
+    /// Include a module at the start of every shader built.
+    pub fn add_prelude(&mut self, name: &str) -> &mut Self {
+        if !self.prelude.iter().any(|m| m == name) {
+            self.prelude.push(String::from(name));
+        }
+        self
+    }
+
+    /// Define `name` for every shader built. An empty value can still be checked with `#ifdef`.
+    pub fn define(&mut self, name: &str, value: impl ToString) -> &mut Self {
+        self.defines.insert(String::from(name), value.to_string());
+        self
+    }
+
+    pub fn undefine(&mut self, name: &str) -> &mut Self {
+        self.defines.remove(name);
+        self
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.defines.contains_key(name)
+    }
+
+    /// Build the final WGSL for `source`, with the prelude included before it.\
+    /// `name` is used for `source` in errors.
+    pub fn build(&self, name: &str, source: &str) -> Result<String, ShaderError> {
+        Ok(self.expand_all(name, source)?.out)
+    }
+
+    /// Build the shader and validate it with naga, returning the WGSL and it's interface.\
+    /// Parse and validation errors point to the module and line the failing code came from.
+    pub fn build_validated(&self, name: &str, source: &str) -> Result<(String, ShaderReflection), ShaderError> {
+        let expansion = self.expand_all(name, source)?;
+        match crate::validate(name, &expansion.out) {
+            Ok(reflection) => Ok((expansion.out, reflection)),
+            Err(e) => Err(expansion.remap(e)),
+        }
+    }
+
+    fn expand_all(&self, name: &str, source: &str) -> Result<Expansion, ShaderError> {
+        let mut expansion = Expansion {
+            defines: self.defines.clone(),
+            included: HashSet::new(),
+            stack: vec![],
+            out: String::new(),
+            lines: vec![],
+        };
+
+        for module in &self.prelude {
+            self.include(&mut expansion, name, 0, module)?;
+        }
+
+        self.expand(&mut expansion, name, source)?;
+
+        Ok(expansion)
+    }
+
+    fn include(&self, expansion: &mut Expansion, from: &str, line: usize, name: &str) -> Result<(), ShaderError> {
+        if expansion.stack.iter().any(|m| m == name) {
+            let mut chain = expansion.stack.clone();
+            chain.push(String::from(name));
+            return Err(ShaderError::IncludeCycle { chain });
+        }
+
+        if !expansion.included.insert(String::from(name)) {
+            return Ok(());
+        }
+
+        let source = self.global.get(name).ok_or_else(|| ShaderError::MissingModule {
+            module: String::from(from),
+            line,
+            name: String::from(name),
+        })?;
+
+        self.expand(expansion, name, source)
+    }
+
+    fn expand(&self, expansion: &mut Expansion, module: &str, source: &str) -> Result<(), ShaderError> {
+        expansion.stack.push(String::from(module));
+        let mut conditionals: Vec<Conditional> = vec![];
+
+        for (i, text) in source.lines().enumerate() {
+            let line = i + 1;
+            let active = conditionals.last().map(Conditional::active).unwrap_or(true);
+            let invalid = || ShaderError::InvalidDirective {
+                module: String::from(module),
+                line,
+                directive: String::from(text.trim()),
+            };
+
+            let trimmed = text.trim_start();
+            if !trimmed.starts_with('#') || trimmed.starts_with("#{") {
+                if active {
+                    let substituted = substitute(text, &expansion.defines).map_err(|name| ShaderError::UndefinedDefine {
+                        module: String::from(module),
+                        line,
+                        name,
+                    })?;
+                    expansion.out.push_str(&substituted);
+                    expansion.out.push('\n');
+                    expansion.lines.push((String::from(module), line));
+                }
+                continue;
+            }
+
+            let mut parts = trimmed[1..].splitn(2, char::is_whitespace);
+            let directive = parts.next().unwrap_or("");
+            let arg = parts.next().unwrap_or("").trim();
+
+            match directive {
+                "ifdef" | "ifndef" if !arg.is_empty() => {
+                    let defined = expansion.defines.contains_key(arg);
+                    conditionals.push(Conditional {
+                        line,
+                        parent_active: active,
+                        taken: defined == (directive == "ifdef"),
+                        seen_else: false,
+                    });
+                },
+                "else" => {
+                    let cond = conditionals.last_mut().filter(|c| !c.seen_else).ok_or_else(invalid)?;
+                    cond.taken = !cond.taken;
+                    cond.seen_else = true;
+                },
+                "endif" => {
+                    conditionals.pop().ok_or_else(invalid)?;
+                },
+                _ if !active => (),
+                "include" => {
+                    let name = arg.trim_matches(|c| c == '"' || c == '<' || c == '>');
+                    if name.is_empty() {
+                        return Err(invalid());
+                    }
+                    self.include(expansion, module, line, name)?;
+                },
+                "define" if !arg.is_empty() => {
+                    let mut def = arg.splitn(2, char::is_whitespace);
+                    let name = def.next().unwrap_or("");
+                    let value = def.next().unwrap_or("").trim();
+                    expansion.defines.insert(String::from(name), String::from(value));
+                },
+                "undef" if !arg.is_empty() => {
+                    expansion.defines.remove(arg);
+                },
+                _ => return Err(invalid()),
+            }
+        }
+
+        if let Some(cond) = conditionals.last() {
+            return Err(ShaderError::UnterminatedConditional { module: String::from(module), line: cond.line });
+        }
+
+        expansion.stack.pop();
+        Ok(())
+    }
+}
+
+/// Replace each `#{NAME}` in `text`, returning the name of the first undefined one on failure.
+fn substitute(text: &str, defines: &HashMap<String, String>) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("#{") {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + len];
+        let value = defines.get(name.trim()).ok_or_else(|| String::from(name.trim()))?;
+        out.push_str(&rest[..start]);
+        out.push_str(value);
+        rest = &rest[start + len + 1..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The non-empty lines of a built shader, trimmed.
+    fn lines(wgsl: &str) -> Vec<&str> {
+        wgsl.lines().map(str::trim).filter(|l| !l.is_empty()).collect()
+    }
+
+    #[test]
+    fn nested_conditionals_select_variants() {
+        let source = "\
+#ifdef A
+a
+#ifdef B
+ab
+#else
+a_not_b
+#endif
+#else
+not_a
+#ifndef B
+not_a_not_b
+#endif
+#endif
+";
+        let mut builder = ShaderBuilder::new();
+        assert_eq!(lines(&builder.build("main", source).unwrap()), ["not_a", "not_a_not_b"]);
+
+        builder.define("A", "");
+        assert_eq!(lines(&builder.build("main", source).unwrap()), ["a", "a_not_b"]);
+
+        builder.define("B", "");
+        assert_eq!(lines(&builder.build("main", source).unwrap()), ["a", "ab"]);
+
+        // Defines made inside an inactive branch don't apply
+        builder.undefine("A");
+        let skipped = "#ifdef A\n#define C\n#endif\n#ifdef C\nc\n#endif\n";
+        assert_eq!(lines(&builder.build("main", skipped).unwrap()), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn unbalanced_conditionals_are_errors() {
+        let builder = ShaderBuilder::new();
+
+        assert_eq!(
+            builder.build("main", "x\n#ifdef A\n#ifdef B\n#endif\n"),
+            Err(ShaderError::UnterminatedConditional { module: String::from("main"), line: 2 })
+        );
+        assert_eq!(
+            builder.build("main", "#endif\n"),
+            Err(ShaderError::InvalidDirective { module: String::from("main"), line: 1, directive: String::from("#endif") })
+        );
+        assert_eq!(
+            builder.build("main", "#ifdef A\n#else\n#else\n#endif\n"),
+            Err(ShaderError::InvalidDirective { module: String::from("main"), line: 3, directive: String::from("#else") })
+        );
+    }
+
+    #[test]
+    fn modules_are_included_once() {
+        let mut builder = ShaderBuilder::new();
+        builder.add_module("common", "common_line");
+        builder.add_module("lights", "#include \"common\"\nlights_line");
+        builder.add_prelude("common");
+
+        let wgsl = builder.build("main", "#include \"lights\"\n#include <common>\nmain_line").unwrap();
+        assert_eq!(lines(&wgsl), ["common_line", "lights_line", "main_line"]);
+    }
+
+    #[test]
+    fn include_cycles_and_missing_modules_are_errors() {
+        let mut builder = ShaderBuilder::new();
+        builder.add_module("a", "#include \"b\"");
+        builder.add_module("b", "#include \"a\"");
+
+        assert_eq!(
+            builder.build("main", "#include \"a\""),
+            Err(ShaderError::IncludeCycle { chain: vec![String::from("main"), String::from("a"), String::from("b"), String::from("a")] })
+        );
+        assert_eq!(
+            builder.build("main", "\n#include \"missing\""),
+            Err(ShaderError::MissingModule { module: String::from("main"), line: 2, name: String::from("missing") })
+        );
+    }
+
+    #[test]
+    fn placeholders_are_substituted() {
+        let mut builder = ShaderBuilder::new();
+        builder.define("GROUP", 2);
+
+        let wgsl = builder.build("main", "#define BINDING 1\n@group(#{GROUP}) @binding(#{ BINDING })").unwrap();
+        assert_eq!(lines(&wgsl), ["@group(2) @binding(1)"]);
+
+        assert_eq!(
+            builder.build("main", "ok\n@group(#{MISSING})"),
+            Err(ShaderError::UndefinedDefine { module: String::from("main"), line: 2, name: String::from("MISSING") })
+        );
+
+        // Placeholders in inactive branches are not checked
+        assert!(builder.build("main", "#ifdef MISSING\n#{MISSING}\n#endif").is_ok());
+    }
+
+    #[test]
+    fn validation_errors_point_at_the_original_module() {
+        let mut builder = ShaderBuilder::new();
+        builder.add_module("common", "fn helper() -> f32 {\n    return 1.0;\n}\n");
+        builder.add_module("broken", "\n\nfn broken() -> f32 { return true; }\n");
+        builder.add_prelude("common");
+
+        let source = "#include \"broken\"\n@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(helper());\n}\n";
+        match builder.build_validated("main", source) {
+            Err(ShaderError::Validation { module, line, message }) => {
+                assert_eq!(module, "broken");
+                assert_eq!(line, Some(3));
+                assert!(message.contains("return true;"), "The message should quote the line: {}", message);
+            },
+            other => panic!("Expected a validation error in 'broken', got {:?}", other),
+        }
+
+        match builder.build_validated("main", "#include \"common\"\n\nfn oops( {\n") {
+            Err(ShaderError::Parse { module, line, .. }) => assert_eq!((module.as_str(), line), ("main", 3)),
+            other => panic!("Expected a parse error in 'main', got {:?}", other),
+        }
+
+        assert!(builder.build_validated("main", source.replace("#include \"broken\"", "").as_str()).is_ok());
+    }
+}
@@ -0,0 +1,41 @@
+/// An error from building a shader, with the module and line it happened on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShaderError {
+    /// An `#include` named a module that was never added.
+    MissingModule { module: String, line: usize, name: String },
+
+    /// A module includes itself, directly or through other modules.
+    IncludeCycle { chain: Vec<String> },
+
+    /// A `#{NAME}` placeholder has no define.
+    UndefinedDefine { module: String, line: usize, name: String },
+
+    /// A directive was malformed or unknown, or an `#else`/`#endif` had no matching `#ifdef`.
+    InvalidDirective { module: String, line: usize, directive: String },
+
+    /// An `#ifdef`/`#ifndef` was not closed with `#endif`.
+    UnterminatedConditional { module: String, line: usize },
+
+    /// The WGSL could not be parsed.
+    Parse { module: String, line: usize, column: usize, message: String },
+
+    /// The WGSL parsed, but is not a valid shader, e.g. a type mismatch or an unknown binding type.
+    Validation { module: String, line: Option<usize>, message: String },
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShaderError::MissingModule { module, line, name } => write!(f, "{}:{}: module '{}' not found", module, line, name),
+            ShaderError::IncludeCycle { chain } => write!(f, "include cycle: {}", chain.join(" -> ")),
+            ShaderError::UndefinedDefine { module, line, name } => write!(f, "{}:{}: '{}' is not defined", module, line, name),
+            ShaderError::InvalidDirective { module, line, directive } => write!(f, "{}:{}: invalid directive '{}'", module, line, directive),
+            ShaderError::UnterminatedConditional { module, line } => write!(f, "{}:{}: conditional is missing '#endif'", module, line),
+            ShaderError::Parse { module, line, column, message } => write!(f, "{}:{}:{}: {}", module, line, column, message),
+            ShaderError::Validation { module, line: Some(line), message } => write!(f, "{}:{}: {}", module, line, message),
+            ShaderError::Validation { module, line: None, message } => write!(f, "{}: {}", module, message),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
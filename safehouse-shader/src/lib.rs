@@ -1,7 +1,7 @@
-use std::collections::HashMap;
+mod builder;
+mod error;
+mod reflect;
 
-pub struct ShaderBuilder {
-    global: HashMap<String, String>,
-
-
-}
\ No newline at end of file
+pub use builder::*;
+pub use error::*;
+pub use reflect::*;
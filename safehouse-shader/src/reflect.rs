@@ -0,0 +1,307 @@
+// Offline WGSL validation and reflection with naga. No GPU is needed.
+
+use crate::ShaderError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Compute,
+}
+
+/// The scalar type of a shader value, without it's width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScalarKind {
+    Float,
+    Sint,
+    Uint,
+    Bool,
+}
+
+/// A scalar or vector type, e.g. `vec4<f32>` is `Float` with 4 components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ValueType {
+    pub kind: ScalarKind,
+    pub components: u8,
+}
+
+/// A `@location` input of a vertex entry point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexInput {
+    pub location: u32,
+    pub name: Option<String>,
+    pub ty: Option<ValueType>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryPoint {
+    pub name: String,
+    pub stage: ShaderStage,
+    /// The `@location` inputs, only filled for vertex entry points.
+    pub vertex_inputs: Vec<VertexInput>,
+}
+
+/// The kind of resource a binding holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    Uniform,
+    Storage { read_only: bool },
+    Texture { kind: ScalarKind, multisampled: bool },
+    DepthTexture { multisampled: bool },
+    StorageTexture,
+    Sampler { comparison: bool },
+    Other,
+}
+
+/// A `@group @binding` resource declared in the shader.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceBinding {
+    pub group: u32,
+    pub binding: u32,
+    pub name: Option<String>,
+    pub kind: BindingKind,
+    /// The entry points that use this binding. Declared but unused bindings have none.
+    pub stages: Vec<ShaderStage>,
+}
+
+/// The interface of a validated shader module.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ShaderReflection {
+    pub entry_points: Vec<EntryPoint>,
+    pub bindings: Vec<ResourceBinding>,
+}
+
+impl ShaderReflection {
+    pub fn entry_point(&self, name: &str) -> Option<&EntryPoint> {
+        self.entry_points.iter().find(|e| e.name == name)
+    }
+
+    /// The bindings in `group` that are used by at least one entry point.
+    pub fn used_bindings(&self, group: u32) -> impl Iterator<Item = &ResourceBinding> {
+        self.bindings.iter().filter(move |b| b.group == group && !b.stages.is_empty())
+    }
+
+    /// The highest bindgroup index used by any entry point.
+    pub fn max_group(&self) -> Option<u32> {
+        self.bindings.iter().filter(|b| !b.stages.is_empty()).map(|b| b.group).max()
+    }
+}
+
+/// Parse and validate WGSL, returning it's interface.\
+/// Error lines refer to `source` itself, with `name` as the module.
+pub fn validate(name: &str, source: &str) -> Result<ShaderReflection, ShaderError> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| {
+        let location = e.location(source);
+        ShaderError::Parse {
+            module: String::from(name),
+            line: location.map(|l| l.line_number as usize).unwrap_or(0),
+            column: location.map(|l| l.line_position as usize).unwrap_or(0),
+            message: String::from(e.message()),
+        }
+    })?;
+
+    let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+        .validate(&module)
+        .map_err(|e| ShaderError::Validation {
+            module: String::from(name),
+            line: e.location(source).map(|l| l.line_number as usize),
+            message: error_chain(e.as_inner()),
+        })?;
+
+    Ok(reflect(&module, &info))
+}
+
+/// An error's message followed by the messages of it's sources.
+fn error_chain(e: &dyn std::error::Error) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(next) = source {
+        message.push_str(": ");
+        message.push_str(&next.to_string());
+        source = next.source();
+    }
+    message
+}
+
+fn stage(stage: naga::ShaderStage) -> ShaderStage {
+    match stage {
+        naga::ShaderStage::Vertex => ShaderStage::Vertex,
+        naga::ShaderStage::Fragment => ShaderStage::Fragment,
+        naga::ShaderStage::Compute => ShaderStage::Compute,
+    }
+}
+
+fn scalar_kind(kind: naga::ScalarKind) -> ScalarKind {
+    match kind {
+        naga::ScalarKind::Sint | naga::ScalarKind::AbstractInt => ScalarKind::Sint,
+        naga::ScalarKind::Uint => ScalarKind::Uint,
+        naga::ScalarKind::Float | naga::ScalarKind::AbstractFloat => ScalarKind::Float,
+        naga::ScalarKind::Bool => ScalarKind::Bool,
+    }
+}
+
+fn value_type(module: &naga::Module, ty: naga::Handle<naga::Type>) -> Option<ValueType> {
+    match module.types[ty].inner {
+        naga::TypeInner::Scalar(scalar) => Some(ValueType { kind: scalar_kind(scalar.kind), components: 1 }),
+        naga::TypeInner::Vector { size, scalar } => Some(ValueType { kind: scalar_kind(scalar.kind), components: size as u8 }),
+        _ => None,
+    }
+}
+
+/// Collect the `@location` inputs of an argument, looking inside structs.
+fn collect_inputs(module: &naga::Module, name: Option<&String>, ty: naga::Handle<naga::Type>, binding: Option<&naga::Binding>, inputs: &mut Vec<VertexInput>) {
+    match binding {
+        Some(naga::Binding::Location { location, .. }) => inputs.push(VertexInput {
+            location: *location,
+            name: name.cloned(),
+            ty: value_type(module, ty),
+        }),
+        Some(naga::Binding::BuiltIn(_)) => (),
+        None => {
+            if let naga::TypeInner::Struct { members, .. } = &module.types[ty].inner {
+                for member in members {
+                    collect_inputs(module, member.name.as_ref(), member.ty, member.binding.as_ref(), inputs);
+                }
+            }
+        },
+    }
+}
+
+fn binding_kind(module: &naga::Module, var: &naga::GlobalVariable) -> BindingKind {
+    let mut ty = var.ty;
+    if let naga::TypeInner::BindingArray { base, .. } = module.types[ty].inner {
+        ty = base;
+    }
+
+    match (var.space, &module.types[ty].inner) {
+        (naga::AddressSpace::Uniform, _) => BindingKind::Uniform,
+        (naga::AddressSpace::Storage { access }, _) => BindingKind::Storage { read_only: !access.contains(naga::StorageAccess::STORE) },
+        (naga::AddressSpace::Handle, naga::TypeInner::Sampler { comparison }) => BindingKind::Sampler { comparison: *comparison },
+        (naga::AddressSpace::Handle, naga::TypeInner::Image { class, .. }) => match class {
+            naga::ImageClass::Sampled { kind, multi } => BindingKind::Texture { kind: scalar_kind(*kind), multisampled: *multi },
+            naga::ImageClass::Depth { multi } => BindingKind::DepthTexture { multisampled: *multi },
+            naga::ImageClass::Storage { .. } => BindingKind::StorageTexture,
+        },
+        _ => BindingKind::Other,
+    }
+}
+
+fn reflect(module: &naga::Module, info: &naga::valid::ModuleInfo) -> ShaderReflection {
+    let entry_points = module.entry_points.iter().map(|ep| {
+        let mut vertex_inputs = vec![];
+        if ep.stage == naga::ShaderStage::Vertex {
+            for arg in &ep.function.arguments {
+                collect_inputs(module, arg.name.as_ref(), arg.ty, arg.binding.as_ref(), &mut vertex_inputs);
+            }
+            vertex_inputs.sort_by_key(|input| input.location);
+        }
+
+        EntryPoint {
+            name: ep.name.clone(),
+            stage: stage(ep.stage),
+            vertex_inputs,
+        }
+    }).collect();
+
+    let bindings = module.global_variables.iter().filter_map(|(handle, var)| {
+        let binding = var.binding.as_ref()?;
+        let stages = module.entry_points.iter().enumerate()
+            .filter(|(i, _)| !info.get_entry_point(*i)[handle].is_empty())
+            .map(|(_, ep)| stage(ep.stage))
+            .collect();
+
+        Some(ResourceBinding {
+            group: binding.group,
+            binding: binding.binding,
+            name: var.name.clone(),
+            kind: binding_kind(module, var),
+            stages,
+        })
+    }).collect();
+
+    ShaderReflection {
+        entry_points,
+        bindings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = "
+struct VertexIn {
+    @location(0) pos: vec4<f32>,
+    @location(2) id: u32,
+}
+
+@group(0) @binding(0) var<uniform> tint: vec4<f32>;
+@group(1) @binding(0) var tex: texture_2d<f32>;
+@group(1) @binding(1) var samp: sampler;
+@group(1) @binding(2) var<storage, read> unused: array<f32>;
+
+@vertex
+fn vs_main(input: VertexIn, @location(1) uv: vec2<f32>, @builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    return input.pos * tint;
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return textureSample(tex, samp, vec2<f32>(0.5, 0.5)) * tint;
+}
+";
+
+    #[test]
+    fn entry_points_and_vertex_inputs_are_reflected() {
+        let reflection = validate("main", SHADER).unwrap();
+        assert_eq!(reflection.entry_points.len(), 2);
+        assert_eq!(reflection.entry_point("fs_main").unwrap().stage, ShaderStage::Fragment);
+        assert!(reflection.entry_point("missing").is_none());
+
+        // Inputs from structs and plain arguments, sorted by location, without builtins
+        let vertex = reflection.entry_point("vs_main").unwrap();
+        assert_eq!(vertex.stage, ShaderStage::Vertex);
+        let inputs: Vec<_> = vertex.vertex_inputs.iter().map(|input| (input.location, input.name.as_deref(), input.ty)).collect();
+        assert_eq!(inputs, [
+            (0, Some("pos"), Some(ValueType { kind: ScalarKind::Float, components: 4 })),
+            (1, Some("uv"), Some(ValueType { kind: ScalarKind::Float, components: 2 })),
+            (2, Some("id"), Some(ValueType { kind: ScalarKind::Uint, components: 1 })),
+        ]);
+    }
+
+    #[test]
+    fn bindings_record_the_stages_that_use_them() {
+        let reflection = validate("main", SHADER).unwrap();
+        let binding = |group, binding| reflection.bindings.iter().find(|b| b.group == group && b.binding == binding).unwrap();
+
+        assert_eq!(binding(0, 0).kind, BindingKind::Uniform);
+        assert_eq!(binding(0, 0).stages, [ShaderStage::Vertex, ShaderStage::Fragment]);
+        assert_eq!(binding(1, 0).kind, BindingKind::Texture { kind: ScalarKind::Float, multisampled: false });
+        assert_eq!(binding(1, 0).stages, [ShaderStage::Fragment]);
+        assert_eq!(binding(1, 1).kind, BindingKind::Sampler { comparison: false });
+
+        // Declared but unused bindings are reflected, but don't count as used
+        assert_eq!(binding(1, 2).kind, BindingKind::Storage { read_only: true });
+        assert!(binding(1, 2).stages.is_empty());
+        assert_eq!(reflection.used_bindings(1).count(), 2);
+        assert_eq!(reflection.max_group(), Some(1));
+    }
+
+    #[test]
+    fn parse_errors_have_the_source_line() {
+        let source = "@vertex\nfn vs_main() -> @builtin(position) vec4<f32> {\n    return vec4<f32>(0.0)\n}\n";
+        match validate("broken", source) {
+            Err(ShaderError::Parse { module, line, .. }) => {
+                assert_eq!(module, "broken");
+                assert!((3..=4).contains(&line), "The missing semicolon is at the end of line 3, got line {}", line);
+            },
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_shaders_fail_validation() {
+        let source = "@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return 1u;\n}\n";
+        assert!(matches!(validate("mistyped", source), Err(ShaderError::Validation { module, .. }) if module == "mistyped"));
+    }
+}
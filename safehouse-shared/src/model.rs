@@ -0,0 +1,304 @@
+//! The binary model container written by `safehouse-data` and read at runtime.
+//!
+//! All values are little-endian, and every section starts on a 4 byte boundary.
+//!
+//! | Section    | Contents |
+//! |------------|----------|
+//! | Header     | magic `b"SHMD"`, version `u16`, flags `u16`, vertex stride, vertex count, attribute count, group count, index count and material count as `u32`, then the bounds as 6 `f32` (min xyz, max xyz) |
+//! | Attributes | per attribute: format (the `wgpu::VertexFormat` discriminant), offset and shader location as `u32` |
+//! | Groups     | per group: start, end and material as `u32`, where the material is `NO_MATERIAL` or an index into the material table |
+//! | Materials  | per material: name length as `u32`, then the UTF-8 name padded to 4 bytes |
+//! | Vertices   | vertex count * stride bytes, padded to 4 bytes |
+//! | Indices    | index count `u16` or `u32` indices, if `FLAG_INDEXED` is set |
+//!
+//! Groups are ranges of indices if the model is indexed, or ranges of vertices otherwise.
+
+use std::ops::Range;
+
+use crate::vertex::Vertex;
+
+pub const MODEL_MAGIC: [u8; 4] = *b"SHMD";
+pub const MODEL_VERSION: u16 = 1;
+
+/// The model has an index buffer.
+pub const FLAG_INDEXED: u16 = 1 << 0;
+/// The indices are `u32` instead of `u16`.
+pub const FLAG_INDEX_U32: u16 = 1 << 1;
+
+/// The material of a group without one.
+pub const NO_MATERIAL: u32 = u32::MAX;
+
+const HEADER_SIZE: usize = 4 + 2 + 2 + 4 * 6 + 4 * 6;
+
+/// An error while reading a model container.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModelFileError {
+    /// The data does not start with `MODEL_MAGIC`, e.g. a raw vertex dump.
+    BadMagic,
+
+    /// The container was written by a newer or older version of the format.
+    UnsupportedVersion(u16),
+
+    /// A section ends past the end of the data.
+    Truncated { section: &'static str, needed: usize, len: usize },
+
+    /// The vertex type does not match the vertex layout the model was written with.
+    VertexMismatch(String),
+
+    /// A group, index or material reference is out of range.
+    Invalid(String),
+}
+
+impl std::fmt::Display for ModelFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelFileError::BadMagic => write!(f, "Not a model container"),
+            ModelFileError::UnsupportedVersion(version) => write!(f, "Model container version {} is not supported (expected {})", version, MODEL_VERSION),
+            ModelFileError::Truncated { section, needed, len } => write!(f, "Model container is truncated: the {} section needs {} bytes but there are {}", section, needed, len),
+            ModelFileError::VertexMismatch(message) => write!(f, "Model vertex layout mismatch: {}", message),
+            ModelFileError::Invalid(message) => write!(f, "Invalid model container: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ModelFileError {}
+
+/// One vertex attribute of the layout a model was written with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttributeDesc {
+    /// The `wgpu::VertexFormat` discriminant.
+    pub format: u32,
+    pub offset: u32,
+    pub shader_location: u32,
+}
+
+impl VertexAttributeDesc {
+    /// The attributes of a vertex type, as they are stored in a model container.
+    pub fn of<V: Vertex>() -> Vec<Self> {
+        V::desc().attributes.iter().map(|attribute| Self {
+            format: attribute.format as u32,
+            offset: attribute.offset as u32,
+            shader_location: attribute.shader_location,
+        }).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelBounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl ModelBounds {
+    /// The bounds of a set of positions, or zero bounds if there are none.
+    pub fn from_positions(positions: impl IntoIterator<Item = [f32; 3]>) -> Self {
+        let mut positions = positions.into_iter();
+        let Some(first) = positions.next() else {
+            return Self::default();
+        };
+        positions.fold(Self { min: first, max: first }, |bounds, p| Self {
+            min: [bounds.min[0].min(p[0]), bounds.min[1].min(p[1]), bounds.min[2].min(p[2])],
+            max: [bounds.max[0].max(p[0]), bounds.max[1].max(p[1]), bounds.max[2].max(p[2])],
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelGroup {
+    pub range: Range<u32>,
+    /// An index into `ModelFile::materials`.
+    pub material: Option<u32>,
+}
+
+/// The index buffer of a model container.
+#[derive(Debug, Clone, Copy)]
+pub enum ModelIndices<'a> {
+    U16(&'a [u8]),
+    U32(&'a [u8]),
+}
+
+/// A validated model container, borrowing it's vertex and index data.
+#[derive(Debug, Clone)]
+pub struct ModelFile<'a> {
+    pub version: u16,
+    pub vertex_stride: u32,
+    pub vertex_count: u32,
+    pub attributes: Vec<VertexAttributeDesc>,
+    pub groups: Vec<ModelGroup>,
+    pub materials: Vec<String>,
+    pub bounds: ModelBounds,
+    /// The raw vertices, for `VertexBuffer::new_from_raw`.
+    pub vertex_data: &'a [u8],
+    pub indices: Option<ModelIndices<'a>>,
+}
+
+/// Reads little-endian values from a container, checking that each section fits.
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, section: &'static str, len: usize) -> Result<&'a [u8], ModelFileError> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len())
+            .ok_or(ModelFileError::Truncated { section, needed: self.pos.saturating_add(len), len: self.data.len() })?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn align(&mut self) {
+        self.pos = self.pos.next_multiple_of(4).min(self.data.len());
+    }
+
+    fn u16(&mut self, section: &'static str) -> Result<u16, ModelFileError> {
+        Ok(u16::from_le_bytes(self.take(section, 2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self, section: &'static str) -> Result<u32, ModelFileError> {
+        Ok(u32::from_le_bytes(self.take(section, 4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self, section: &'static str) -> Result<f32, ModelFileError> {
+        Ok(f32::from_le_bytes(self.take(section, 4)?.try_into().unwrap()))
+    }
+}
+
+impl<'a> ModelFile<'a> {
+    /// Read a model container and check that it is consistent, without checking it's vertex type.
+    pub fn parse(data: &'a [u8]) -> Result<Self, ModelFileError> {
+        if data.len() < 4 || data[0..4] != MODEL_MAGIC {
+            return Err(ModelFileError::BadMagic);
+        }
+        let mut cursor = Cursor { data, pos: 4 };
+
+        let version = cursor.u16("header")?;
+        if version != MODEL_VERSION {
+            return Err(ModelFileError::UnsupportedVersion(version));
+        }
+        if data.len() < HEADER_SIZE {
+            return Err(ModelFileError::Truncated { section: "header", needed: HEADER_SIZE, len: data.len() });
+        }
+        let flags = cursor.u16("header")?;
+        let vertex_stride = cursor.u32("header")?;
+        let vertex_count = cursor.u32("header")?;
+        let attribute_count = cursor.u32("header")?;
+        let group_count = cursor.u32("header")?;
+        let index_count = cursor.u32("header")?;
+        let material_count = cursor.u32("header")?;
+        let mut bounds = ModelBounds::default();
+        for axis in bounds.min.iter_mut().chain(bounds.max.iter_mut()) {
+            *axis = cursor.f32("header")?;
+        }
+
+        let mut attributes = vec![];
+        for _ in 0..attribute_count {
+            attributes.push(VertexAttributeDesc {
+                format: cursor.u32("attributes")?,
+                offset: cursor.u32("attributes")?,
+                shader_location: cursor.u32("attributes")?,
+            });
+        }
+
+        let mut groups = vec![];
+        for _ in 0..group_count {
+            let start = cursor.u32("groups")?;
+            let end = cursor.u32("groups")?;
+            let material = cursor.u32("groups")?;
+            groups.push(ModelGroup {
+                range: start..end,
+                material: (material != NO_MATERIAL).then_some(material),
+            });
+        }
+
+        let mut materials = vec![];
+        for _ in 0..material_count {
+            let len = cursor.u32("materials")? as usize;
+            let name = std::str::from_utf8(cursor.take("materials", len)?)
+                .map_err(|_| ModelFileError::Invalid(String::from("a material name is not UTF-8")))?;
+            materials.push(String::from(name));
+            cursor.align();
+        }
+
+        let vertex_len = (vertex_stride as usize).checked_mul(vertex_count as usize)
+            .ok_or_else(|| ModelFileError::Invalid(String::from("the vertex data size overflows")))?;
+        let vertex_data = cursor.take("vertices", vertex_len)?;
+        cursor.align();
+
+        let indices = if flags & FLAG_INDEXED != 0 {
+            Some(if flags & FLAG_INDEX_U32 != 0 {
+                ModelIndices::U32(cursor.take("indices", index_count as usize * 4)?)
+            } else {
+                ModelIndices::U16(cursor.take("indices", index_count as usize * 2)?)
+            })
+        } else {
+            None
+        };
+
+        let file = Self {
+            version,
+            vertex_stride,
+            vertex_count,
+            attributes,
+            groups,
+            materials,
+            bounds,
+            vertex_data,
+            indices,
+        };
+        file.check_ranges(index_count)?;
+        Ok(file)
+    }
+
+    /// Read a model container written with vertex type `V`.
+    pub fn read<V: Vertex>(data: &'a [u8]) -> Result<Self, ModelFileError> {
+        let file = Self::parse(data)?;
+        file.check_vertex::<V>()?;
+        Ok(file)
+    }
+
+    /// Check that `V` has the same size and attributes as the vertices in this container.
+    pub fn check_vertex<V: Vertex>(&self) -> Result<(), ModelFileError> {
+        let size = std::mem::size_of::<V>() as u32;
+        if size != self.vertex_stride {
+            return Err(ModelFileError::VertexMismatch(format!("the vertex type is {} bytes but the model's vertices are {} bytes", size, self.vertex_stride)));
+        }
+        let attributes = VertexAttributeDesc::of::<V>();
+        if attributes != self.attributes {
+            return Err(ModelFileError::VertexMismatch(format!("the vertex type has attributes {:?} but the model has {:?}", attributes, self.attributes)));
+        }
+        Ok(())
+    }
+
+    /// The draw ranges of the groups, for `ModelData::new`.
+    pub fn group_ranges(&self) -> Vec<Range<u32>> {
+        self.groups.iter().map(|group| group.range.clone()).collect()
+    }
+
+    /// The material name of a group.
+    pub fn group_material(&self, group: usize) -> Option<&str> {
+        self.groups[group].material.map(|material| self.materials[material as usize].as_str())
+    }
+
+    fn check_ranges(&self, index_count: u32) -> Result<(), ModelFileError> {
+        let draw_count = if self.indices.is_some() { index_count } else { self.vertex_count };
+        for (i, group) in self.groups.iter().enumerate() {
+            if group.range.start > group.range.end || group.range.end > draw_count {
+                return Err(ModelFileError::Invalid(format!("group {} draws {:?} but there are {} {}", i, group.range, draw_count, if self.indices.is_some() { "indices" } else { "vertices" })));
+            }
+            if let Some(material) = group.material.filter(|&material| material as usize >= self.materials.len()) {
+                return Err(ModelFileError::Invalid(format!("group {} uses material {} but there are {} materials", i, material, self.materials.len())));
+            }
+        }
+
+        let out_of_range = match self.indices {
+            Some(ModelIndices::U16(bytes)) => bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u32).find(|&index| index >= self.vertex_count),
+            Some(ModelIndices::U32(bytes)) => bytes.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).find(|&index| index >= self.vertex_count),
+            None => None,
+        };
+        if let Some(index) = out_of_range {
+            return Err(ModelFileError::Invalid(format!("index {} is out of range of {} vertices", index, self.vertex_count)));
+        }
+        Ok(())
+    }
+}
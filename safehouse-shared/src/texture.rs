@@ -0,0 +1,129 @@
+//! The baked texture container written by `safehouse-data` and read at runtime.
+//!
+//! All values are little-endian.
+//!
+//! | Section | Contents |
+//! |---------|----------|
+//! | Header  | magic `b"SHTX"`, version `u16`, format `u16` (a `TextureFileFormat`), width, height and mip level count as `u32` |
+//! | Levels  | each mip level's pixels, tightly packed, starting with the full size image and halving each dimension down to 1 |
+
+pub const TEXTURE_MAGIC: [u8; 4] = *b"SHTX";
+pub const TEXTURE_VERSION: u16 = 1;
+
+const HEADER_SIZE: usize = 4 + 2 + 2 + 4 * 3;
+
+/// The pixel format of a baked texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFileFormat {
+    /// Linear RGBA, e.g. for normal maps and other data.
+    Rgba8 = 0,
+    /// sRGB RGBA, for colors.
+    Rgba8Srgb = 1,
+}
+
+impl TextureFileFormat {
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(Self::Rgba8),
+            1 => Some(Self::Rgba8Srgb),
+            _ => None,
+        }
+    }
+
+    pub fn bytes_per_pixel(&self) -> u32 {
+        4
+    }
+
+    pub fn wgpu_format(&self) -> wgpu::TextureFormat {
+        match self {
+            Self::Rgba8 => wgpu::TextureFormat::Rgba8Unorm,
+            Self::Rgba8Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+        }
+    }
+}
+
+/// The size of a mip level, where level 0 is the full size.
+pub fn mip_size(width: u32, height: u32, level: u32) -> (u32, u32) {
+    ((width >> level).max(1), (height >> level).max(1))
+}
+
+/// An error while reading a baked texture.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextureFileError {
+    /// The data does not start with `TEXTURE_MAGIC`.
+    BadMagic,
+
+    /// The texture was written by a newer or older version of the format.
+    UnsupportedVersion(u16),
+
+    /// The texture is truncated or has an invalid header.
+    Invalid(String),
+}
+
+impl std::fmt::Display for TextureFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureFileError::BadMagic => write!(f, "Not a baked texture"),
+            TextureFileError::UnsupportedVersion(version) => write!(f, "Baked texture version {} is not supported (expected {})", version, TEXTURE_VERSION),
+            TextureFileError::Invalid(message) => write!(f, "Invalid baked texture: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for TextureFileError {}
+
+/// A validated baked texture, borrowing the pixels of each mip level.
+#[derive(Debug, Clone)]
+pub struct TextureFile<'a> {
+    pub format: TextureFileFormat,
+    pub width: u32,
+    pub height: u32,
+    pub levels: Vec<&'a [u8]>,
+}
+
+impl<'a> TextureFile<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, TextureFileError> {
+        if data.len() < 4 || data[0..4] != TEXTURE_MAGIC {
+            return Err(TextureFileError::BadMagic);
+        }
+        if data.len() < HEADER_SIZE {
+            return Err(TextureFileError::Invalid(format!("the header needs {} bytes but there are {}", HEADER_SIZE, data.len())));
+        }
+        let u16_at = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+
+        let version = u16_at(4);
+        if version != TEXTURE_VERSION {
+            return Err(TextureFileError::UnsupportedVersion(version));
+        }
+        let format = TextureFileFormat::from_u16(u16_at(6))
+            .ok_or_else(|| TextureFileError::Invalid(format!("unknown format {}", u16_at(6))))?;
+        let width = u32_at(8);
+        let height = u32_at(12);
+        let level_count = u32_at(16);
+        if width == 0 || height == 0 {
+            return Err(TextureFileError::Invalid(format!("the texture is {}x{}", width, height)));
+        }
+        if level_count == 0 || level_count > 32 - width.max(height).leading_zeros() {
+            return Err(TextureFileError::Invalid(format!("{} mip levels for a {}x{} texture", level_count, width, height)));
+        }
+
+        let mut levels = vec![];
+        let mut pos = HEADER_SIZE;
+        for level in 0..level_count {
+            let (w, h) = mip_size(width, height, level);
+            let len = w as usize * h as usize * format.bytes_per_pixel() as usize;
+            let bytes = data.get(pos..pos + len)
+                .ok_or_else(|| TextureFileError::Invalid(format!("mip level {} needs {} bytes but there are {}", level, pos + len, data.len())))?;
+            levels.push(bytes);
+            pos += len;
+        }
+
+        Ok(Self {
+            format,
+            width,
+            height,
+            levels,
+        })
+    }
+}
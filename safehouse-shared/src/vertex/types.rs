@@ -119,4 +119,135 @@ impl super::Vertex for TexVertex {
             ]  
         }
     }
-}
\ No newline at end of file
+}
+/// A vertex skinned to up to 4 joints, e.g. from a glTF skin.
+#[repr(C)]
+#[derive(Debug,Clone,Copy,Default)]
+pub struct SkinnedVertex {
+    pub pos: [f32; 4],
+    pub normal: [f32; 3],
+    pub tex_coord: [f32; 2],
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
+impl super::Vertex for SkinnedVertex {
+    fn desc() -> &'static wgpu::VertexBufferLayout<'static> {
+        &wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SkinnedVertex>() as u64,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x3,
+                    offset: std::mem::size_of::<f32>() as u64 * 4u64,
+                    shader_location: 1,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: std::mem::size_of::<f32>() as u64 * 7u64,
+                    shader_location: 2,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Uint32x4,
+                    offset: std::mem::size_of::<f32>() as u64 * 9u64,
+                    shader_location: 3,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: std::mem::size_of::<f32>() as u64 * 13u64,
+                    shader_location: 4,
+                },
+ 
+            ]  
+        }
+    }
+}
+
+/// A vertex with a tangent for normal mapping. The tangent's `w` is the bitangent sign, as in glTF and MikkTSpace.
+#[repr(C)]
+#[derive(Debug,Clone,Copy,Default)]
+pub struct TangentVertex {
+    pub pos: [f32; 4],
+    pub normal: [f32; 3],
+    pub tex_coord: [f32; 2],
+    pub tangent: [f32; 4],
+}
+
+impl super::Vertex for TangentVertex {
+    fn desc() -> &'static wgpu::VertexBufferLayout<'static> {
+        &wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TangentVertex>() as u64,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x3,
+                    offset: std::mem::size_of::<f32>() as u64 * 4u64,
+                    shader_location: 1,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: std::mem::size_of::<f32>() as u64 * 7u64,
+                    shader_location: 2,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: std::mem::size_of::<f32>() as u64 * 9u64,
+                    shader_location: 3,
+                },
+            ]
+        }
+    }
+}
+
+/// The first shader location used by `InstanceTransform`.\
+/// The model matrix takes up this location and the 3 following it, one for each column.
+pub const INSTANCE_TRANSFORM_LOCATION: u32 = 8;
+
+/// A per-instance model matrix, for drawing many objects that share a model in one draw call.
+#[repr(C)]
+#[derive(Debug,Clone,Copy,Default)]
+pub struct InstanceTransform {
+    pub model: [[f32; 4]; 4],
+}
+
+impl super::Vertex for InstanceTransform {
+    fn desc() -> &'static wgpu::VertexBufferLayout<'static> {
+        &wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceTransform>() as u64,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 0,
+                    shader_location: INSTANCE_TRANSFORM_LOCATION,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: std::mem::size_of::<f32>() as u64 * 4u64,
+                    shader_location: INSTANCE_TRANSFORM_LOCATION + 1,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: std::mem::size_of::<f32>() as u64 * 8u64,
+                    shader_location: INSTANCE_TRANSFORM_LOCATION + 2,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: std::mem::size_of::<f32>() as u64 * 12u64,
+                    shader_location: INSTANCE_TRANSFORM_LOCATION + 3,
+                },
+ 
+            ]  
+        }
+    }
+}
@@ -1,14 +1,9 @@
-use safehouse_data::{create_file, render::vertex_type::*};
+use safehouse_data::bake::Baker;
 
 fn main() {
-    let obj = safehouse_data::model::obj::build_obj::<TexVertex>(
-        include_bytes!("res/obj/bunny/bunny.obj"),
-        &|p,t,n, group_id| {
-            let tt = t.unwrap();
-            TexVertex { pos: p.clone().into(), tex_coord: [tt.0, tt.1]  }
-        }
-    );
-
-   create_file("src/model/bunny.dat", &obj).expect("Could not create file!");
-
-}
\ No newline at end of file
+    Baker::new("assets.manifest")
+        .out_dir(std::env::var("OUT_DIR").unwrap())
+        .cargo(true)
+        .bake()
+        .expect("Could not bake the walk demo assets!");
+}
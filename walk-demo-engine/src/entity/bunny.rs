@@ -1,6 +1,6 @@
 use std::rc::Rc;
 
-use crate::render::{BINDGROUP_GLOBAL, BINDGROUP_SCENEOBJECT, entity::{Entity, EntityPipeline}, gpu::{self, binding::Binder, buffer::{Uniform, VertexBuffer}, dataunit::ImageFormat, program, shaderprogram::Program, texture::{sampler::TextureSampler, Texture}, wgpu::{self, PrimitiveState, ShaderStages}}, model::{ModelData, ModelResources}, named_entity, scene::SceneObjectHandle, texturetype::TextureType, vertex_type::TexVertex };
+use crate::render::{entity::{Entity, EntityPipeline}, gpu::{self, binding::Binder, buffer::Uniform, dataunit::ImageFormat, shaderprogram::Program, texture::{sampler::TextureSampler, Texture}, wgpu::{self, PrimitiveState, ShaderStages}}, model::{ModelData, ModelResources}, named_entity, scene::SceneObjectHandle, texturetype::TextureType, vertex_type::TexVertex };
 
 use super::ActiveEntity;
 
@@ -25,9 +25,7 @@ impl Entity for Bunny {
         vec![]
     }
 
-    fn load_model(state: &safehouse_render::gpu::State) -> safehouse_render::model::ModelData {
-        let data = include_bytes!("../model/bunny.dat");
-
+    fn load_model(state: &safehouse_render::gpu::State) -> safehouse_render::SafehouseResult<safehouse_render::model::ModelData> {
         struct BunnyModelRes {
             texture: Texture,
             sampler: Rc<TextureSampler>
@@ -42,13 +40,12 @@ impl Entity for Bunny {
             }
         }
 
-        ModelData::new::<Self,BunnyModelRes>(
+        ModelData::from_container::<Self, TexVertex, BunnyModelRes>(
             state,
-            VertexBuffer::new_from_raw::<TexVertex>(state, data),
-            vec![0..(data.len()/std::mem::size_of::<TexVertex>()) as u32],
+            include_bytes!(concat!(env!("OUT_DIR"), "/bunny.model")),
             Some(BunnyModelRes {
-                texture: Texture::load_encoded(state, include_bytes!("../../res/obj/bunny/buntex.1001.png"), gpu::dataunit::ImageFormat::Png),
-                sampler: Rc::clone(&state.get_sampler("default"))
+                texture: Texture::load_encoded(state, include_bytes!("../../res/obj/bunny/buntex.1001.png"), gpu::dataunit::ImageFormat::Png)?,
+                sampler: Rc::clone(state.get_sampler("default")?)
             })
         )
 
@@ -58,52 +55,18 @@ impl Entity for Bunny {
         Some(EntityPipeline {
             primitive: PrimitiveState::default(),
             depth_stencil: None,
+            ..Default::default()
         })
     }
 
-    fn load_shader(rm: &safehouse_render::RenderManager, group_model: u32, group_entity: u32) -> Option<safehouse_render::gpu::shaderprogram::Program> {
-        Some(program!(
-            &rm.gpu_state,
-            source: format!("
-            @group({BINDGROUP_GLOBAL}) @binding(0)
-            var<uniform> pvm: mat4x4<f32>;
-            @group({BINDGROUP_GLOBAL}) @binding(1)
-            var<uniform> time: f32;
-
-            @group({BINDGROUP_SCENEOBJECT}) @binding(0)
-            var<uniform> obj_mat: mat4x4<f32>;
-
-            @group({group_model}) @binding(0)
-            var texture: texture_2d<f32>;
-            @group({group_model}) @binding(1)
-            var tex_sampler: sampler;
-
-
-            struct TexVertexIn {{
-                @location(0) pos: vec4<f32>,
-                @location(1) tcoord: vec2<f32>,
-            }}
-
-            struct TexVertexOut {{
-                @builtin(position) pos: vec4<f32>,
-                @location(0) tcoord: vec2<f32>,
-            }}
-
-            @vertex
-            fn vs_main(in: TexVertexIn) -> TexVertexOut {{
-                var out: TexVertexOut;
-                out.pos = pvm * in.pos;
-                return out;
-            }}
-
-            @fragment
-            fn fs_main(vo: TexVertexOut) -> @location(0) vec4<f32> {{
-                return textureSample(texture, tex_sampler, vo.tcoord); 
-            }}
-
-
-            ")
-        ))
+    fn load_shader(rm: &safehouse_render::RenderManager, group_model: u32, group_entity: u32) -> Option<safehouse_render::entity::EntityShaderEntry> {
+        Some(rm.build_shader_file(
+            Self::shader_name(),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader/bunny.wgsl"),
+            include_str!("../shader/bunny.wgsl"),
+            group_model,
+            group_entity,
+        ).expect("Could not build the Bunny shader!").into())
     }
 
 }
@@ -1,7 +1,7 @@
 
 use std::rc::Rc;
 
-use safehouse_render::{entity::Entity, gpu::{binding::Binder, buffer::VertexBuffer, program, shaderprogram::Program, texture::sampler::TextureSampler, wgpu}, model::ModelData, named_entity, texturetype::DynamicTexture, vertex_type::TexVertex};
+use safehouse_render::{entity::{BlendMode, Entity, EntityColorTarget}, gpu::{binding::Binder, buffer::VertexBuffer, shaderprogram::Program, texture::sampler::TextureSampler, wgpu}, model::ModelData, named_entity, scene::{RenderLayer, RenderOrder}, texturetype::DynamicTexture, vertex_type::TexVertex};
 struct TextPane {
     text_texture: DynamicTexture, 
     text_texture_sampler: Rc<TextureSampler> 
@@ -19,7 +19,7 @@ impl Entity for TextPane {
     fn on_instantiate(rm: &mut safehouse_render::RenderManager, handle: safehouse_render::scene::SceneObjectHandle) -> Self {
         Self {
             text_texture: DynamicTexture::new_text(rm, wgpu::Color::TRANSPARENT, "This is some text, can you see it?"),
-            text_texture_sampler: Rc::clone(rm.gpu_state.get_sampler("default")),
+            text_texture_sampler: Rc::clone(rm.gpu_state.get_sampler("default").expect("No default sampler!")),
         } 
     }
 
@@ -30,8 +30,8 @@ impl Entity for TextPane {
         ]
     }
 
-    fn load_model(state: &crate::gpu::State) -> ModelData {
-        ModelData::new::<Self,()>(
+    fn load_model(state: &crate::gpu::State) -> safehouse_render::SafehouseResult<ModelData> {
+        Ok(ModelData::new::<Self,()>(
             state,
             VertexBuffer::new(state, &[
                 
@@ -46,7 +46,7 @@ impl Entity for TextPane {
             ]),
             vec![0..6],
             None,
-        )
+        ))
     }
 
     fn load_pipeline(rm: &safehouse_render::RenderManager) -> Option<safehouse_render::entity::EntityPipeline> {
@@ -57,47 +57,43 @@ impl Entity for TextPane {
                 ..Default::default()
             },
             depth_stencil: None,
+            order: RenderOrder { layer: RenderLayer::Overlay, transparent: true },
+            color_target: EntityColorTarget::blended(BlendMode::Alpha),
+            ..Default::default()
         })
     }
 
-    fn load_shader(rm: &safehouse_render::RenderManager, group_model: u32, group_entity: u32) -> Option<crate::gpu::shaderprogram::Program> {
-        Some(
-            program!(
-                &rm.gpu_state,
-                source: format!("
-
-                @group({group_entity}) @binding(0)
-                var dyntexture: texture_2d<f32>;
-
-                @group({group_entity}) @binding(1)
-                var dyntex_sampler: sampler;
-
-                struct TextureVertexInput {{
-                    @location(0) pos: vec4<f32>,
-                    @location(1) tex_coord: vec2<f32>,
-                }}
-
-                struct TextureVertexOutput {{
-                    @builtin(position) pos: vec4<f32>,
-                    @location(0) tex_coord: vec2<f32>,
-                }}
-
-                @vertex
-                fn vs_main(i: TextureVertexInput) -> TextureVertexOutput {{
-                    var o: TextureVertexOutput;
-                    o.pos = i.pos;
-                    o.tex_coord = i.tex_coord;
-                    return o;
-                }}
-
-                @fragment
-                fn fs_main(iv: TextureVertexOutput) -> @location(0) vec4<f32> {{
-                    return textureSample(dyntexture, dyntex_sampler, iv.tex_coord);
-                }}
-                
-                ")
-            )
-        )
+    fn load_shader(rm: &safehouse_render::RenderManager, group_model: u32, group_entity: u32) -> Option<safehouse_render::entity::EntityShaderEntry> {
+        Some(rm.build_shader(Self::ENTITY_TYPE_NAME, "
+            @group(#{GROUP_ENTITY}) @binding(0)
+            var dyntexture: texture_2d<f32>;
+
+            @group(#{GROUP_ENTITY}) @binding(1)
+            var dyntex_sampler: sampler;
+
+            struct TextureVertexInput {
+                @location(0) pos: vec4<f32>,
+                @location(1) tex_coord: vec2<f32>,
+            }
+
+            struct TextureVertexOutput {
+                @builtin(position) pos: vec4<f32>,
+                @location(0) tex_coord: vec2<f32>,
+            }
+
+            @vertex
+            fn vs_main(i: TextureVertexInput) -> TextureVertexOutput {
+                var o: TextureVertexOutput;
+                o.pos = i.pos;
+                o.tex_coord = i.tex_coord;
+                return o;
+            }
+
+            @fragment
+            fn fs_main(iv: TextureVertexOutput) -> @location(0) vec4<f32> {
+                return textureSample(dyntexture, dyntex_sampler, iv.tex_coord);
+            }
+            ", group_model, group_entity).expect("Could not build the TextPane shader!").into())
     }
 
 }
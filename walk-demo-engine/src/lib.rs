@@ -25,8 +25,11 @@ impl<'engine> Engine<'engine> {
 
     pub fn new(window: &Arc<Window>) -> Self {
 
-        let rm = RenderManager::new(window);         
+        let mut rm = RenderManager::new(window);         
         rm.gpu_state.print_info();
+        if cfg!(debug_assertions) {
+            rm.enable_shader_hot_reload();
+        }
 
         let controller = Controller::new(None); 
         let mut camera = Camera::new(window.inner_size().width as f32, window.inner_size().height as f32);
@@ -88,14 +91,14 @@ impl<'engine> Engine<'engine> {
                     // Render
                     if Instant::now().duration_since(self.last_rendered) >= Duration::from_millis(16) {
                         // println!("draw");
-                        self.rm.gpu_state.update_resize();
+                        self.rm.update_resize();
                         self.rm.render(&self.camera);
                         self.rm.update_time();
                         if !self.dyn_texture_queue.is_empty() {
                             self.dyn_texture_queue.clear();
                         }
                     }
-                    self.rm.window.request_redraw();
+                    self.rm.request_redraw();
                 }
 
                 _ => (),
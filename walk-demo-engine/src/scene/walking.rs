@@ -10,9 +10,9 @@ pub struct WalkingScene {
 
 impl SceneInit for WalkingScene {
     fn init(engine: &mut crate::Engine) -> Self {
-        engine.rm.load_entity::<Bunny>();
+        engine.rm.load_entity::<Bunny>().expect("Could not load Bunny!");
 
-        let mut bunny = engine.rm.spawn_sceneobject_entity::<Bunny>("test bunny");
+        let mut bunny = engine.rm.spawn_sceneobject_entity::<Bunny>("test bunny").expect("Could not spawn Bunny!");
         let sub_zoom_pos = subject_zoom_pos(engine.camera.position, bunny.get_position(engine), f32::sin(engine.get_delta_time().as_secs_f32()));
         engine.camera.set_pos(sub_zoom_pos);
         
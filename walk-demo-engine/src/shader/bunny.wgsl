@@ -0,0 +1,27 @@
+@group(#{GROUP_MODEL}) @binding(0)
+var texture: texture_2d<f32>;
+@group(#{GROUP_MODEL}) @binding(1)
+var tex_sampler: sampler;
+
+
+struct TexVertexIn {
+    @location(0) pos: vec4<f32>,
+    @location(1) tcoord: vec2<f32>,
+}
+
+struct TexVertexOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) tcoord: vec2<f32>,
+}
+
+@vertex
+fn vs_main(in: TexVertexIn) -> TexVertexOut {
+    var out: TexVertexOut;
+    out.pos = camera.view_proj * obj_mat * in.pos;
+    return out;
+}
+
+@fragment
+fn fs_main(vo: TexVertexOut) -> @location(0) vec4<f32> {
+    return textureSample(texture, tex_sampler, vo.tcoord); 
+}